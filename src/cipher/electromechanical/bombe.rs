@@ -0,0 +1,406 @@
+use super::enigma::{self, Enigma};
+use crate::{
+    error::{Error, Result},
+    key::{ReflectorType, RotorType},
+    lang::Language,
+};
+
+/// The Enigma machines use the 26 letter alphabet
+const ALPHABET_LEN: usize = 26;
+
+/// Finds each position of a crib in a ciphertext where no letter of the crib would
+/// encrypt to itself, which the Enigma never does.
+///
+/// # Arguments
+///
+/// * `language` A [`Language`] instance
+/// * `crib` The expected plaintext
+/// * `ciphertext` The ciphertext to search
+///
+pub fn crib_positions(language: &mut Language, crib: &str, ciphertext: &str) -> Vec<usize> {
+    language.set_alph_len(ALPHABET_LEN);
+
+    let crib = language.string_to_vec(crib);
+    let ciphertext = language.string_to_vec(ciphertext);
+
+    if crib.len() > ciphertext.len() {
+        return Vec::new();
+    }
+
+    (0..=ciphertext.len() - crib.len())
+        .filter(|&position| {
+            crib.iter()
+                .zip(&ciphertext[position..])
+                .all(|(pt, ct)| pt != ct)
+        })
+        .collect()
+}
+
+/// A menu for the bombe, linking each letter of a crib to the ciphertext letter
+/// beneath it. Each link is a scrambler at the position of that letter in the
+/// message. Loops in the menu are what allow the bombe to reject wrong positions.
+///
+pub struct Menu {
+    /// The crib letter, ciphertext letter and message position of each link
+    links: Vec<(i16, i16, usize)>,
+
+    /// The letter with the most links, used as the test register
+    test_letter: i16,
+}
+
+impl Menu {
+    /// Builds a menu from a crib placed at `position` in the ciphertext
+    ///
+    /// # Arguments
+    ///
+    /// * `language` A [`Language`] instance
+    /// * `crib` The expected plaintext
+    /// * `ciphertext` The ciphertext
+    /// * `position` The position of the first letter of the crib in the ciphertext
+    ///
+    pub fn new(
+        language: &mut Language,
+        crib: &str,
+        ciphertext: &str,
+        position: usize,
+    ) -> Result<Menu> {
+        language.set_alph_len(ALPHABET_LEN);
+
+        let crib = language.string_to_vec(crib);
+        let ciphertext = language.string_to_vec(ciphertext);
+
+        if crib.is_empty() || position + crib.len() > ciphertext.len() {
+            return Err(Error::InsufficientInputLen {
+                expected: position + crib.len().max(1),
+                actual: ciphertext.len(),
+            });
+        }
+
+        let links = crib
+            .iter()
+            .zip(&ciphertext[position..])
+            .enumerate()
+            .map(|(idx, (&pt, &ct))| (pt, ct, position + idx))
+            .collect::<Vec<_>>();
+
+        if links.iter().any(|&(pt, ct, _)| pt == ct) {
+            return Err(Error::InvalidKeyFmt {
+                expected: "A crib position where no letter encrypts to itself".to_string(),
+                actual: position.to_string(),
+            });
+        }
+
+        let mut degrees = [0; ALPHABET_LEN];
+        for &(pt, ct, _) in links.iter() {
+            degrees[pt as usize] += 1;
+            degrees[ct as usize] += 1;
+        }
+        let test_letter = (0..ALPHABET_LEN)
+            .max_by_key(|&letter| degrees[letter])
+            .unwrap() as i16;
+
+        Ok(Menu { links, test_letter })
+    }
+
+    /// The crib letter, ciphertext letter and message position of each link
+    ///
+    pub fn links(&self) -> &[(i16, i16, usize)] {
+        &self.links
+    }
+
+    /// The number of independent loops in the menu, which is the number of links
+    /// less the number of links needed to connect its letters.
+    ///
+    pub fn loops(&self) -> usize {
+        // union find over the letters, counting links that close a loop
+        let mut parents = (0..ALPHABET_LEN).collect::<Vec<_>>();
+        fn root(parents: &mut [usize], letter: usize) -> usize {
+            let mut letter = letter;
+            while parents[letter] != letter {
+                parents[letter] = parents[parents[letter]];
+                letter = parents[letter];
+            }
+            letter
+        }
+
+        self.links
+            .iter()
+            .filter(|&&(pt, ct, _)| {
+                let (a, b) = (
+                    root(&mut parents, pt as usize),
+                    root(&mut parents, ct as usize),
+                );
+                parents[a] = b;
+                a == b
+            })
+            .count()
+    }
+}
+
+/// A candidate setting found by the bombe
+///
+pub struct Stop {
+    /// The Enigma at the start of the message, with the rings at zero and the implied
+    /// plugs set
+    pub enigma: Enigma,
+
+    /// The implied stecker partner of each letter in the menu, as pairs of letters. A
+    /// letter paired with itself is unplugged.
+    pub steckers: Vec<(i16, i16)>,
+}
+
+/// A simulation of the Turing–Welchman bombe, which finds the rotor orders and
+/// positions that are consistent with a crib (See [`Menu`]).
+///
+/// For each position, the bombe assumes a stecker partner for the test letter of the
+/// menu, and follows every implication through the scramblers of the menu and the
+/// diagonal board (if `a` is steckered to `b`, then `b` is steckered to `a`). If every
+/// partner of the test letter is implied, the assumption contradicts itself and so
+/// does every other, and the position is rejected. Otherwise the bombe stops, and the
+/// remaining partners of the test letter are checked for a consistent set of steckers.
+///
+/// Like the original, it assumes that the middle rotor does not step between the start
+/// of the message and the end of the crib, so the ring settings of a stop are found
+/// afterwards.
+///
+pub struct Bombe {
+    /// The rotors to choose the rotor orders from
+    pub rotor_types: Vec<RotorType>,
+
+    /// The reflector used by the Enigma
+    pub reflector: ReflectorType,
+}
+
+impl Default for Bombe {
+    fn default() -> Self {
+        Bombe {
+            rotor_types: vec![
+                RotorType::I,
+                RotorType::II,
+                RotorType::III,
+                RotorType::IV,
+                RotorType::V,
+            ],
+            reflector: ReflectorType::B,
+        }
+    }
+}
+
+impl Bombe {
+    /// Runs the bombe on a menu for every rotor order and position, returning each
+    /// stop along with its implied steckers
+    ///
+    /// # Arguments
+    ///
+    /// * `language` A [`Language`] instance
+    /// * `menu` The menu to test
+    ///
+    pub fn run(&self, language: &mut Language, menu: &Menu) -> Vec<Stop> {
+        language.set_alph_len(ALPHABET_LEN);
+
+        // the links touching each letter, as the letter at the other end and the link
+        let mut connections = vec![Vec::new(); ALPHABET_LEN];
+        for (idx, &(pt, ct, _)) in menu.links.iter().enumerate() {
+            connections[pt as usize].push((ct, idx));
+            connections[ct as usize].push((pt, idx));
+        }
+
+        let wheel_orders = enigma::wheel_orders(&self.rotor_types)
+            .iter()
+            .map(|rotors| Enigma::with_wheel_order(language, rotors, self.reflector))
+            .collect::<Vec<_>>();
+
+        enigma::parallel_map(&wheel_orders, |enigma| {
+            test_positions(enigma, menu, &connections)
+        })
+        .into_iter()
+        .flatten()
+        .collect()
+    }
+}
+
+/// Tests every position of the rotors of an Enigma against a menu, returning the stops
+///
+/// # Arguments
+///
+/// * `enigma` The Enigma with the rotor order to test
+/// * `menu` The menu to test
+/// * `connections` The links touching each letter, as the other letter and the link
+///
+fn test_positions(enigma: &Enigma, menu: &Menu, connections: &[Vec<(i16, usize)>]) -> Vec<Stop> {
+    let table = enigma.scrambler_table();
+    let inner_len = ALPHABET_LEN * ALPHABET_LEN;
+
+    let mut stops = Vec::new();
+    for start in 0..table.len() {
+        // the right rotor steps before each letter, but the others stay still
+        let right = start / inner_len;
+        let scramblers = menu
+            .links
+            .iter()
+            .map(|&(_, _, position)| {
+                let right_offset = (right + position + 1) % ALPHABET_LEN;
+                &table[right_offset * inner_len + start % inner_len]
+            })
+            .collect::<Vec<_>>();
+
+        let live = energise(connections, &scramblers, menu.test_letter, 0);
+        let register = &live[menu.test_letter as usize];
+        let live_count = register.iter().filter(|&&live| live).count();
+        if live_count == ALPHABET_LEN {
+            continue;
+        }
+
+        // the first assumption is consistent if only it is live, otherwise the true
+        // partner must be one of the letters that are not live
+        let partners = (0..ALPHABET_LEN as i16)
+            .filter(|&partner| match live_count {
+                1 => partner == 0,
+                _ => !register[partner as usize],
+            })
+            .collect::<Vec<_>>();
+
+        for partner in partners {
+            let live = energise(connections, &scramblers, menu.test_letter, partner);
+
+            if let Some(steckers) = consistent_steckers(&live) {
+                let mut enigma = enigma.clone();
+                enigma.set_positions(start);
+                for &(a, b) in steckers.iter() {
+                    if a != b && enigma.plugboard.is_valid_plug(a, b) {
+                        enigma.plugboard.add_plug(a, b);
+                    }
+                }
+                stops.push(Stop { enigma, steckers });
+            }
+        }
+    }
+
+    stops
+}
+
+/// Follows every stecker implied by assuming that `letter` is steckered to `partner`,
+/// through the scramblers of the menu and the diagonal board. Returns the live wires,
+/// where `live[a][b]` means that `a` may be steckered to `b`. Stops early once every
+/// partner of `letter` is live, since the position is then rejected.
+///
+/// # Arguments
+///
+/// * `connections` The links touching each letter, as the other letter and the link
+/// * `scramblers` The scrambler of each link
+/// * `letter` The letter to assume a partner for
+/// * `partner` The assumed partner
+///
+fn energise(
+    connections: &[Vec<(i16, usize)>],
+    scramblers: &[&[i16; ALPHABET_LEN]],
+    letter: i16,
+    partner: i16,
+) -> [[bool; ALPHABET_LEN]; ALPHABET_LEN] {
+    let mut live = [[false; ALPHABET_LEN]; ALPHABET_LEN];
+    let mut stack = vec![(letter, partner)];
+    live[letter as usize][partner as usize] = true;
+    let mut letter_live = 1;
+
+    while let Some((a, b)) = stack.pop() {
+        if letter_live == ALPHABET_LEN {
+            break;
+        }
+
+        // the diagonal board, then for each link from a, the letter at its other end is
+        // steckered to the scrambled b
+        let diagonal = std::iter::once((b, a));
+        let links = connections[a as usize]
+            .iter()
+            .map(|&(other, link)| (other, scramblers[link][b as usize]));
+
+        for (a, b) in diagonal.chain(links) {
+            if !live[a as usize][b as usize] {
+                live[a as usize][b as usize] = true;
+                stack.push((a, b));
+
+                if a == letter {
+                    letter_live += 1;
+                }
+            }
+        }
+    }
+
+    live
+}
+
+/// Checks that the live wires give at most one stecker partner for every letter,
+/// returning the implied pairs of letters if so.
+///
+/// # Arguments
+///
+/// * `live` The live wires (See [`energise`])
+///
+fn consistent_steckers(live: &[[bool; ALPHABET_LEN]; ALPHABET_LEN]) -> Option<Vec<(i16, i16)>> {
+    let mut steckers = Vec::new();
+
+    for (a, register) in live.iter().enumerate() {
+        let mut partners = (0..ALPHABET_LEN).filter(|&b| register[b]);
+
+        match (partners.next(), partners.next()) {
+            (Some(b), None) if a <= b => steckers.push((a as i16, b as i16)),
+            (_, Some(_)) => return None,
+            _ => {}
+        }
+    }
+
+    Some(steckers)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        cipher::{Keyed, Symmetric},
+        key::{Key, StatefulKey},
+    };
+
+    const CRIB: &str = "WETTERVORHERSAGEBISKAYA";
+
+    fn enigma(language: &mut Language) -> Enigma {
+        let mut enigma = Enigma::new(language);
+        enigma.rotors[0].set(language, "iii:22:0").unwrap();
+        enigma.rotors[1].set(language, "i:11:0").unwrap();
+        enigma.rotors[2].set(language, "ii:20:0").unwrap();
+        enigma.plugboard.set(language, "aq:bj:dk:gz:mo:py").unwrap();
+        enigma
+    }
+
+    #[test]
+    fn menu() {
+        let mut language = Language::from_file("examples/data/english.bin").unwrap();
+        let ciphertext = enigma(&mut language).run(&mut language, CRIB);
+
+        let menu = Menu::new(&mut language, CRIB, &ciphertext, 0).unwrap();
+
+        assert_eq!(menu.links().len(), CRIB.len());
+        assert!(menu.loops() >= 3);
+        assert!(crib_positions(&mut language, CRIB, &ciphertext).contains(&0));
+        assert!(Menu::new(&mut language, "AAAA", "ABCD", 0).is_err());
+    }
+
+    #[test]
+    fn run() {
+        let mut language = Language::from_file("examples/data/english.bin").unwrap();
+        let enigma = enigma(&mut language);
+        let ciphertext = enigma.run(&mut language, CRIB);
+
+        let menu = Menu::new(&mut language, CRIB, &ciphertext, 0).unwrap();
+        let bombe = Bombe {
+            rotor_types: vec![RotorType::I, RotorType::II, RotorType::III],
+            reflector: ReflectorType::B,
+        };
+        let stops = bombe.run(&mut language, &menu);
+
+        assert!(stops.iter().any(|stop| {
+            stop.enigma.run(&mut language, &ciphertext) == CRIB
+                && stop.enigma.plugboard.to_string(&mut language)
+                    == enigma.plugboard.to_string(&mut language)
+        }));
+    }
+}
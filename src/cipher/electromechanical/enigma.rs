@@ -1,16 +1,170 @@
 use crate::key::{Key, Plugboard, Reflector, ReflectorType, Rotor, RotorType};
-use crate::lang::Language;
+use crate::lang::{Language, ScoreSize};
 use crate::{
-    cipher::{Keyed, Symmetric},
+    cipher::{Keyed, Solve, Symmetric},
     key::{IdentityKey, IoKey},
+    util,
 };
 
+/// The Enigma machines use the 26 letter alphabet
+const ALPHABET_LEN: usize = 26;
+/// The number of rotor types used by the M3 Enigma
+const M3_ROTORS: usize = 8;
+/// The reflectors used by the M3 Enigma
+const M3_REFLECTORS: [ReflectorType; 2] = [ReflectorType::B, ReflectorType::C];
+/// The number of start positions of each wheel order which are scored again with a few
+/// plugs in the index of coincedence search
+const PLUGGED_STARTS: usize = 60;
+/// The number of plugs found for each of those start positions
+const SEARCH_PLUGS: usize = 4;
+/// The number of rotor settings kept from the index of coincedence search
+const CANDIDATES: usize = 300;
+/// The ring settings of the right rotor used in the index of coincedence search. The
+/// right ring decides when the middle rotor steps, so one of these steps the middle
+/// rotor within a few letters of the true ring setting.
+const SEARCH_RINGS: [i16; 3] = [0, 9, 18];
+
+/// The entry wheel of the commercial machines connects the keyboard to the rotors in
+/// keyboard (QWERTZ) order. Maps each letter to its contact on the first rotor.
+#[rustfmt::skip]
+const QWERTZ_ENTRY: [i16; 26] = [
+    9, 22, 20, 11, 2, 12, 13, 14, 7, 15, 16, 25, 24, 23, 8, 17, 0, 3, 10, 4, 6, 21, 1, 19, 18, 5,
+];
+/// Maps each contact of the QWERTZ entry wheel back to its letter
+#[rustfmt::skip]
+const QWERTZ_EXIT: [i16; 26] = [
+    16, 22, 4, 17, 19, 25, 20, 8, 14, 0, 18, 3, 5, 6, 7, 9, 10, 15, 24, 23, 2, 21, 1, 13, 12, 11,
+];
+
+/// The Enigma models that can be simulated, which decide the rotors and reflectors
+/// that can be used, the wiring of the entry wheel and how the rotors step
+///
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum EnigmaModel {
+    /// The Enigma I used by the Wehrmacht, with rotors I to V
+    I,
+    /// The three rotor naval Enigma, with rotors I to VIII
+    M3,
+    /// The four rotor naval Enigma, with rotors I to VIII, a greek wheel (Beta or
+    /// Gamma) that never steps, and a thin reflector (B thin or C thin)
+    M4,
+    /// The commercial Enigma K, with a settable reflector
+    K,
+    /// The Enigma K used by the Swiss army, with its own rotor wirings
+    SwissK,
+    /// The Enigma K used by the Deutsche Reichsbahn, with its own rotor and
+    /// reflector wirings
+    Railway,
+    /// The Abwehr Enigma G-312, with cog wheel stepping and a moving reflector
+    G312,
+    /// The Enigma G-260, with cog wheel stepping and a moving reflector
+    G260,
+    /// The Enigma G-111, with cog wheel stepping and a moving reflector
+    G111,
+}
+
+impl EnigmaModel {
+    /// The rotor types that can be used in the three stepping rotors
+    ///
+    fn rotor_types(&self) -> Vec<RotorType> {
+        match self {
+            EnigmaModel::I => (0..5).map(RotorType::from).collect(),
+            EnigmaModel::M3 | EnigmaModel::M4 => (0..M3_ROTORS).map(RotorType::from).collect(),
+            EnigmaModel::K => vec![RotorType::KI, RotorType::KII, RotorType::KIII],
+            EnigmaModel::SwissK => vec![
+                RotorType::SwissKI,
+                RotorType::SwissKII,
+                RotorType::SwissKIII,
+            ],
+            EnigmaModel::Railway => vec![
+                RotorType::RailwayI,
+                RotorType::RailwayII,
+                RotorType::RailwayIII,
+            ],
+            EnigmaModel::G312 => vec![RotorType::G312I, RotorType::G312II, RotorType::G312III],
+            EnigmaModel::G260 => vec![RotorType::G260I, RotorType::G260II, RotorType::G260III],
+            EnigmaModel::G111 => vec![RotorType::G111I, RotorType::G111II, RotorType::G111V],
+        }
+    }
+
+    /// The rotor types that can be used as the greek wheel
+    ///
+    fn greek_types(&self) -> Option<[RotorType; 2]> {
+        match self {
+            EnigmaModel::M4 => Some([RotorType::Beta, RotorType::Gamma]),
+            _ => None,
+        }
+    }
+
+    /// The reflector types that can be used
+    ///
+    fn reflector_types(&self) -> Vec<ReflectorType> {
+        match self {
+            EnigmaModel::I | EnigmaModel::M3 => vec![
+                ReflectorType::A,
+                ReflectorType::B,
+                ReflectorType::C,
+                ReflectorType::D,
+            ],
+            EnigmaModel::M4 => vec![ReflectorType::BThin, ReflectorType::CThin],
+            EnigmaModel::K | EnigmaModel::SwissK | EnigmaModel::G260 | EnigmaModel::G111 => {
+                vec![ReflectorType::K]
+            }
+            EnigmaModel::Railway => vec![ReflectorType::Railway],
+            EnigmaModel::G312 => vec![ReflectorType::G312],
+        }
+    }
+
+    /// Whether the model is a commercial machine, with a QWERTZ entry wheel and a
+    /// settable reflector
+    ///
+    pub fn is_commercial(&self) -> bool {
+        !matches!(self, EnigmaModel::I | EnigmaModel::M3 | EnigmaModel::M4)
+    }
+
+    /// Whether the rotors are driven by cog wheels rather than pawls. Each rotor
+    /// turns the next as it moves past one of its notches, so there is no double
+    /// stepping, and the left rotor turns the reflector.
+    ///
+    pub fn has_cog_stepping(&self) -> bool {
+        matches!(
+            self,
+            EnigmaModel::G312 | EnigmaModel::G260 | EnigmaModel::G111
+        )
+    }
+}
+
+/// The Enigma cipher. Each letter passes through the plugboard, the rotors, the
+/// reflector, then back through the rotors and plugboard, with the rotors stepping
+/// before each letter.
+///
+/// The solver uses the Gillogly / Weierud–Sullivan ciphertext-only attack on the M3
+/// Enigma. Each wheel order (rotors I to VIII, with reflector B or C) and start
+/// position is decrypted without plugs and scored by the index of coincedence, with
+/// the wheel orders split between threads. The best rotor settings are then refined:
+/// the plugboard is hill climbed using the index of coincedence and bigrams, the ring
+/// settings of the right and middle rotors are found, then the plugboard is hill
+/// climbed again using quadgrams. Since the first search uses no plugs, it can break
+/// messages of a few hundred letters with up to around six plugs; more plugs need
+/// much longer messages. For the M4, the solver only finds settings where the greek
+/// wheel is at its zero offset, which with a thin reflector act as reflector B or C.
+///
+/// The military Enigma I, M3 and M4, the commercial Enigma K, Swiss-K and Railway
+/// Enigma, and the Abwehr Enigma G are supported (See [`EnigmaModel`]). Only the
+/// three rightmost rotors step, so the greek wheel of the M4 keeps its position,
+/// while the reflector of the Enigma G is turned by the left rotor. The solver only
+/// breaks the military models, and leaves the key of the other models unchanged.
+///
+/// The Enigma I and M3 can use the rewirable reflector UKW-D, and the plugboard can be
+/// replaced by an Uhr box, which makes the plugboard non-reciprocal.
+///
 #[derive(Clone)]
 pub struct Enigma {
     pub plugboard: Plugboard,
     // 0=Rightmost ... 2/3=Leftmost rotor
     pub rotors: Vec<Rotor>,
     pub reflector: Reflector,
+    model: EnigmaModel,
 }
 
 impl Enigma {
@@ -21,10 +175,12 @@ impl Enigma {
                     let mut cp = language.get_cp(&c);
                     self.step_rotors();
                     cp = self.plugboard.input(cp);
+                    cp = self.entry_pass(cp, false);
                     cp = self.rotor_pass(cp, false);
                     cp = self.reflector.input(cp);
                     cp = self.rotor_pass(cp, true);
-                    cp = self.plugboard.input(cp);
+                    cp = self.entry_pass(cp, true);
+                    cp = self.plugboard.output(cp);
                     language.update_cp(&c, cp)
                 } else {
                     c
@@ -33,9 +189,24 @@ impl Enigma {
             .collect()
     }
 
+    /// Steps the three rightmost rotors, including the double step of the middle
+    /// rotor for machines stepped by pawls. The greek wheel of the M4 never steps.
+    ///
     fn step_rotors(&mut self) {
         debug_assert!(self.rotors.len() >= 3);
 
+        if self.model.has_cog_stepping() {
+            for rotor in self.rotors.iter_mut().take(3) {
+                let on_notch = rotor.is_on_notch();
+                rotor.step();
+                if !on_notch {
+                    return;
+                }
+            }
+            self.reflector.step();
+            return;
+        }
+
         if self.rotors[1].is_on_notch() {
             self.rotors[1].step();
             self.rotors[2].step();
@@ -45,6 +216,16 @@ impl Enigma {
         self.rotors[0].step();
     }
 
+    fn entry_pass(&self, letter: i16, reverse: bool) -> i16 {
+        if !self.model.is_commercial() {
+            letter
+        } else if reverse {
+            QWERTZ_EXIT[letter as usize]
+        } else {
+            QWERTZ_ENTRY[letter as usize]
+        }
+    }
+
     fn rotor_pass(&self, letter: i16, reverse: bool) -> i16 {
         let mut out_letter = letter;
 
@@ -59,35 +240,550 @@ impl Enigma {
 
         out_letter
     }
-}
-
-impl Symmetric for Enigma {
-    fn run(&self, language: &mut Language, msg: &str) -> String {
-        let mut enigma_mut = self.clone();
-        enigma_mut.run_mut(language, msg)
-    }
-}
 
-impl Keyed for Enigma {
-    fn new(language: &mut Language) -> Enigma {
+    /// Creates an Enigma of the given model, with the first three rotors of the model
+    /// from left to right (I, II and III for the military models), reflector B (or B
+    /// thin and the Beta greek wheel for the M4, or the reflector of a commercial
+    /// model) and no plugs.
+    /// Setting a rotor or reflector that the model does not use gives an error.
+    ///
+    /// # Arguments
+    ///
+    /// * `language` A [`Language`] instance
+    /// * `model` The Enigma model
+    ///
+    pub fn with_model(language: &mut Language, model: EnigmaModel) -> Enigma {
+        let rotor_types = model.rotor_types();
         let mut result = Enigma {
             plugboard: Plugboard::identity(language),
-            rotors: vec![
-                *Rotor::new(language, RotorType::III).unwrap(),
-                *Rotor::new(language, RotorType::II).unwrap(),
-                *Rotor::new(language, RotorType::I).unwrap(),
-            ],
+            rotors: (0..3)
+                .rev()
+                .map(|rotor| *Rotor::new(language, rotor_types[rotor]).unwrap())
+                .collect(),
             reflector: *Reflector::new(language, ReflectorType::B).unwrap(),
+            model,
         };
 
+        result
+            .rotors
+            .iter_mut()
+            .for_each(|rotor| rotor.set_allowed(&rotor_types));
+        if let Some(greek_types) = model.greek_types() {
+            let mut greek = *Rotor::new(language, greek_types[0]).unwrap();
+            greek.set_allowed(&greek_types);
+            result.rotors.push(greek);
+        }
+        result.reflector.set_allowed(&model.reflector_types());
+
         result.plugboard.info_mut().set("Plugboard", "plug");
         result.rotors[0].info_mut().set("Right rotor", "r0");
         result.rotors[1].info_mut().set("Middle rotor", "r1");
         result.rotors[2].info_mut().set("Left rotor", "r2");
+        if let Some(greek) = result.rotors.get_mut(3) {
+            greek.info_mut().set("Greek wheel", "r3");
+        }
         result.reflector.info_mut().set("Reflector", "ref");
 
         result
     }
+
+    /// The model of the Enigma
+    ///
+    pub fn model(&self) -> EnigmaModel {
+        self.model
+    }
+
+    /// Creates an M3 Enigma using the given rotors and reflector, with the rotors at
+    /// their zero positions and no plugs
+    ///
+    /// # Arguments
+    ///
+    /// * `language` A [`Language`] instance
+    /// * `rotors` The types of the rotors, from right to left
+    /// * `reflector` The type of the reflector
+    ///
+    pub(crate) fn with_wheel_order(
+        language: &mut Language,
+        rotors: &[RotorType],
+        reflector: ReflectorType,
+    ) -> Enigma {
+        let mut enigma = Enigma::new(language);
+        for (rotor, &rotor_type) in enigma.rotors.iter_mut().zip(rotors) {
+            rotor.set(language, rotor_type).unwrap();
+        }
+        enigma.reflector.set(language, reflector).unwrap();
+        enigma
+    }
+
+    /// Sets the outer positions (grund) of the rotors from an index into a scrambler
+    /// table, keeping the current rings (See [`Enigma::scrambler_idx`])
+    ///
+    /// # Arguments
+    ///
+    /// * `idx` The index of the rotor positions
+    ///
+    pub(crate) fn set_positions(&mut self, idx: usize) {
+        let mut idx = idx;
+        for rotor in self.rotors.iter_mut().rev() {
+            rotor.grund = util::modulo(
+                (idx % ALPHABET_LEN) as i16 + rotor.rings,
+                ALPHABET_LEN as i16,
+            );
+            idx /= ALPHABET_LEN;
+        }
+    }
+
+    /// Finds the index into a scrambler table for the current offsets of the rotors,
+    /// which is `(right * 26 + middle) * 26 + left`. The left rotor varies fastest
+    /// since it never affects the stepping.
+    ///
+    fn scrambler_idx(&self) -> usize {
+        self.rotors.iter().fold(0, |idx, rotor| {
+            idx * ALPHABET_LEN
+                + util::modulo(rotor.grund - rotor.rings, ALPHABET_LEN as i16) as usize
+        })
+    }
+
+    /// Finds the output of the scrambler (the rotors and reflector, without the
+    /// plugboard) for every letter and every combination of rotor offsets, indexed by
+    /// [`Enigma::scrambler_idx`].
+    ///
+    pub(crate) fn scrambler_table(&self) -> Vec<[i16; ALPHABET_LEN]> {
+        // the output of each rotor for each offset, forwards then in reverse
+        let wirings = self
+            .rotors
+            .iter()
+            .map(|rotor| {
+                let mut rotor = rotor.clone();
+                rotor.reset_positions();
+
+                let mut forward = [[0; ALPHABET_LEN]; ALPHABET_LEN];
+                let mut reverse = [[0; ALPHABET_LEN]; ALPHABET_LEN];
+                for offset in 0..ALPHABET_LEN {
+                    rotor.grund = offset as i16;
+                    for letter in 0..ALPHABET_LEN {
+                        forward[offset][letter] = rotor.input(letter as i16, false);
+                        reverse[offset][letter] = rotor.input(letter as i16, true);
+                    }
+                }
+                (forward, reverse)
+            })
+            .collect::<Vec<_>>();
+
+        let mut offsets = vec![0; self.rotors.len()];
+        let mut table = vec![[0; ALPHABET_LEN]; ALPHABET_LEN.pow(self.rotors.len() as u32)];
+        for (idx, row) in table.iter_mut().enumerate() {
+            let mut rest = idx;
+            for offset in offsets.iter_mut().rev() {
+                *offset = rest % ALPHABET_LEN;
+                rest /= ALPHABET_LEN;
+            }
+
+            for (letter, output) in row.iter_mut().enumerate() {
+                let mut cp = letter as i16;
+                for ((forward, _), &offset) in wirings.iter().zip(&offsets) {
+                    cp = forward[offset][cp as usize];
+                }
+                cp = self.reflector.input(cp);
+                for ((_, reverse), &offset) in wirings.iter().zip(&offsets).rev() {
+                    cp = reverse[offset][cp as usize];
+                }
+                *output = cp;
+            }
+        }
+
+        table
+    }
+
+    /// Finds the scrambler table index of each letter of a message, stepping the
+    /// rotors from their current positions.
+    ///
+    /// # Arguments
+    ///
+    /// * `len` The length of the message
+    ///
+    fn scrambler_indices(&self, len: usize) -> Vec<usize> {
+        let mut enigma = self.clone();
+        (0..len)
+            .map(|_| {
+                enigma.step_rotors();
+                enigma.scrambler_idx()
+            })
+            .collect()
+    }
+
+    /// Decrypts every start position of the rotors without plugs, for each of the
+    /// [`SEARCH_RINGS`] of the right rotor, and scores each by the index of coincedence.
+    /// The best of these are scored again with a few plugs (See [`search_plugs`]).
+    /// Returns the best start positions, as the sum of the squared letter counts, the
+    /// right ring setting and the scrambler index of the start position.
+    ///
+    /// # Arguments
+    ///
+    /// * `ciphertext` The ciphertext code points
+    ///
+    fn search_starts(&self, language: &Language, ciphertext: &[i16]) -> Vec<(f64, i16, usize)> {
+        let table = self.scrambler_table();
+        let mut enigma = self.clone();
+        enigma.rotors.iter_mut().for_each(Rotor::reset_positions);
+
+        // the left rotor never affects the stepping, so the scrambler indices for each
+        // position of the other rotors are found once, then offset for each left rotor
+        // position
+        let mut starts = Vec::with_capacity(table.len() * SEARCH_RINGS.len());
+        for (&rings, inner) in SEARCH_RINGS.iter().flat_map(|rings| {
+            (0..table.len())
+                .step_by(ALPHABET_LEN)
+                .map(move |inner| (rings, inner))
+        }) {
+            enigma.rotors[0].rings = rings;
+            enigma.set_positions(inner);
+            let indices = enigma.scrambler_indices(ciphertext.len());
+
+            let mut counts = [[0; ALPHABET_LEN]; ALPHABET_LEN];
+            for (&cp, &idx) in ciphertext.iter().zip(&indices) {
+                let (first, left_offset) = (idx - idx % ALPHABET_LEN, idx % ALPHABET_LEN);
+                let rows = &table[first..first + ALPHABET_LEN];
+
+                for (left, counts) in counts.iter_mut().enumerate() {
+                    let row = match left_offset + left {
+                        offset if offset >= ALPHABET_LEN => offset - ALPHABET_LEN,
+                        offset => offset,
+                    };
+                    counts[rows[row][cp as usize] as usize] += 1;
+                }
+            }
+
+            for (left, counts) in counts.iter().enumerate() {
+                // the total length is fixed, so this ranks the same as the IoC
+                let score = counts.iter().map(|count| count * count).sum::<usize>();
+                starts.push((score, rings, inner + left));
+            }
+        }
+
+        starts.sort_unstable_by(|a, b| b.cmp(a));
+        starts.truncate(PLUGGED_STARTS);
+
+        // plugs lower the index of coincedence of the right start position a lot, so a
+        // few plugs are found for the best start positions before they are compared
+        let positions = letter_positions(ciphertext);
+        let mut plaintext = vec![0; ciphertext.len()];
+        let mut starts = starts
+            .into_iter()
+            .map(|(_, rings, start)| {
+                enigma.rotors[0].rings = rings;
+                enigma.set_positions(start);
+                let indices = enigma.scrambler_indices(ciphertext.len());
+                let plugboard = search_plugs(&table, &indices, ciphertext, &positions);
+                decrypt_with(&table, &indices, &plugboard, ciphertext, &mut plaintext);
+                (language.score(&plaintext, ScoreSize::Bigrams), rings, start)
+            })
+            .collect::<Vec<_>>();
+
+        starts.sort_unstable_by(|a, b| b.0.total_cmp(&a.0));
+        starts.truncate(CANDIDATES);
+        starts
+    }
+
+    /// Finds the plugboard and ring settings for a start position of the rotors. The
+    /// plugboard is hill climbed using the index of coincedence then bigrams, the rings
+    /// of the right and middle rotors are chosen using quadgrams, keeping the starting
+    /// offsets of the rotors, and the plugboard is hill climbed again using quadgrams.
+    /// Returns the plugboard substitution and the quadgram score.
+    ///
+    /// # Arguments
+    ///
+    /// * `language` The current language instance
+    /// * `table` The scrambler table for the rotors and reflector
+    /// * `ciphertext` The ciphertext code points
+    ///
+    fn refine(
+        &mut self,
+        language: &Language,
+        table: &[[i16; ALPHABET_LEN]],
+        ciphertext: &[i16],
+    ) -> (Vec<i16>, f64) {
+        let mut plugboard = (0..ALPHABET_LEN as i16).collect::<Vec<_>>();
+        let mut plaintext = vec![0; ciphertext.len()];
+
+        let indices = self.scrambler_indices(ciphertext.len());
+        climb_plugboard(&mut plugboard, |plugboard| {
+            decrypt_with(table, &indices, plugboard, ciphertext, &mut plaintext);
+            language.index_of_coincedence(&plaintext)
+        });
+        climb_plugboard(&mut plugboard, |plugboard| {
+            decrypt_with(table, &indices, plugboard, ciphertext, &mut plaintext);
+            language.score(&plaintext, ScoreSize::Bigrams)
+        });
+
+        // the rings only change when the next rotor steps, so try every ring setting
+        // of the right rotor, then of the middle rotor. Since the search only tried a few
+        // right rings, the middle rotor may also start one step away from its offset.
+        decrypt_with(table, &indices, &plugboard, ciphertext, &mut plaintext);
+        let mut best_score = language.score(&plaintext, ScoreSize::Quadgrams);
+        for &(rotor, middle_steps) in [(0, &[-1, 0, 1][..]), (1, &[0][..])].iter() {
+            let start = self.clone();
+            for rings in 0..ALPHABET_LEN as i16 {
+                for &middle_step in middle_steps.iter() {
+                    let mut enigma = start.clone();
+                    let grund = enigma.rotors[rotor].grund + rings - enigma.rotors[rotor].rings;
+                    enigma.rotors[rotor].grund = util::modulo(grund, ALPHABET_LEN as i16);
+                    enigma.rotors[rotor].rings = rings;
+                    enigma.rotors[1].grund =
+                        util::modulo(enigma.rotors[1].grund + middle_step, ALPHABET_LEN as i16);
+
+                    let indices = enigma.scrambler_indices(ciphertext.len());
+                    decrypt_with(table, &indices, &plugboard, ciphertext, &mut plaintext);
+                    let score = language.score(&plaintext, ScoreSize::Quadgrams);
+
+                    if score > best_score {
+                        best_score = score;
+                        *self = enigma;
+                    }
+                }
+            }
+        }
+
+        let indices = self.scrambler_indices(ciphertext.len());
+        let score = climb_plugboard(&mut plugboard, |plugboard| {
+            decrypt_with(table, &indices, plugboard, ciphertext, &mut plaintext);
+            language.score(&plaintext, ScoreSize::Quadgrams)
+        });
+
+        (plugboard, score)
+    }
+}
+
+/// Finds every order of three different rotors chosen from `rotor_types`, with the
+/// rotors of each order from right to left.
+///
+/// # Arguments
+///
+/// * `rotor_types` The rotors to choose from
+///
+pub(crate) fn wheel_orders(rotor_types: &[RotorType]) -> Vec<[RotorType; 3]> {
+    let len = rotor_types.len();
+    let mut orders = Vec::new();
+    for left in 0..len {
+        for middle in (0..len).filter(|&middle| middle != left) {
+            for right in (0..len).filter(|&right| right != left && right != middle) {
+                orders.push([rotor_types[right], rotor_types[middle], rotor_types[left]]);
+            }
+        }
+    }
+    orders
+}
+
+/// Decrypts a message using a scrambler table (See [`Enigma::scrambler_table`]) and a
+/// plugboard substitution.
+///
+/// # Arguments
+///
+/// * `table` The scrambler table for the rotors and reflector
+/// * `indices` The scrambler table index of each letter
+/// * `plugboard` The plugboard substitution
+/// * `ciphertext` The ciphertext code points
+/// * `plaintext` The buffer to decrypt into
+///
+fn decrypt_with(
+    table: &[[i16; ALPHABET_LEN]],
+    indices: &[usize],
+    plugboard: &[i16],
+    ciphertext: &[i16],
+    plaintext: &mut [i16],
+) {
+    for ((pt, &ct), &idx) in plaintext.iter_mut().zip(ciphertext).zip(indices) {
+        *pt = plugboard[table[idx][plugboard[ct as usize] as usize] as usize];
+    }
+}
+
+/// Hill climbs a plugboard substitution to maximise `score`. For each pair of letters,
+/// the existing plugs of both letters are removed and the pair is plugged together (or
+/// left unplugged if they were plugged together), optionally plugging their old
+/// partners together. Returns the best score.
+///
+/// # Arguments
+///
+/// * `plugboard` The plugboard substitution, which is set to the best found
+/// * `score` A function of type: (plugboard) -> score, where a greater score is better
+///
+fn climb_plugboard<F>(plugboard: &mut [i16], mut score: F) -> f64
+where
+    F: FnMut(&[i16]) -> f64,
+{
+    let mut best_score = score(plugboard);
+    let mut trial = plugboard.to_vec();
+
+    let mut improved = true;
+    while improved {
+        improved = false;
+
+        for a in 0..ALPHABET_LEN {
+            for b in a + 1..ALPHABET_LEN {
+                for &reconnect in [false, true].iter() {
+                    let old_a = plugboard[a] as usize;
+                    let old_b = plugboard[b] as usize;
+
+                    // only letters plugged to two other letters have partners to reconnect
+                    if reconnect && (old_a == a || old_b == b || old_a == b) {
+                        continue;
+                    }
+
+                    trial.copy_from_slice(plugboard);
+                    trial[old_a] = old_a as i16;
+                    trial[old_b] = old_b as i16;
+                    trial[a] = a as i16;
+                    trial[b] = b as i16;
+                    if old_a != b {
+                        trial[a] = b as i16;
+                        trial[b] = a as i16;
+                    }
+                    if reconnect {
+                        trial[old_a] = old_b as i16;
+                        trial[old_b] = old_a as i16;
+                    }
+
+                    let trial_score = score(&trial);
+                    if trial_score > best_score {
+                        best_score = trial_score;
+                        plugboard.copy_from_slice(&trial);
+                        improved = true;
+                    }
+                }
+            }
+        }
+    }
+
+    best_score
+}
+
+/// Finds the positions of each letter in a message
+///
+/// # Arguments
+///
+/// * `ciphertext` The ciphertext code points
+///
+fn letter_positions(ciphertext: &[i16]) -> Vec<Vec<usize>> {
+    let mut positions = vec![Vec::new(); ALPHABET_LEN];
+    for (idx, &cp) in ciphertext.iter().enumerate() {
+        positions[cp as usize].push(idx);
+    }
+    positions
+}
+
+/// Plugs the pair of ciphertext letters which most raises the index of coincedence,
+/// up to [`SEARCH_PLUGS`] times. Plugging the output of the scrambler only relabels the
+/// letters, so doesn't change the index of coincedence, and plugging a pair only
+/// changes the letters enciphered with that pair, so only their counts are updated.
+/// On a short message, plugging more pairs raises the index of coincedence of wrong
+/// start positions as much as that of the right one. Returns the sum of the squared
+/// letter counts, which ranks the same as the index of coincedence.
+///
+/// # Arguments
+///
+/// * `table` The scrambler table for the rotors and reflector
+/// * `indices` The scrambler table index of each letter
+/// * `ciphertext` The ciphertext code points
+/// * `positions` The positions of each letter in the ciphertext
+///
+fn search_plugs(
+    table: &[[i16; ALPHABET_LEN]],
+    indices: &[usize],
+    ciphertext: &[i16],
+    positions: &[Vec<usize>],
+) -> [i16; ALPHABET_LEN] {
+    let mut counts = [0; ALPHABET_LEN];
+    for (&cp, &idx) in ciphertext.iter().zip(indices) {
+        counts[table[idx][cp as usize] as usize] += 1;
+    }
+    let score = |counts: &[usize]| counts.iter().map(|count| count * count).sum::<usize>();
+
+    // moves the letters enciphered with `a` or `b` to the outputs for `a` and `b` plugged
+    // together
+    let plug = |counts: &mut [usize; ALPHABET_LEN], a: usize, b: usize| {
+        for (from, to) in [(a, b), (b, a)] {
+            for &pos in positions[from].iter() {
+                let row = &table[indices[pos]];
+                counts[row[from] as usize] -= 1;
+                counts[row[to] as usize] += 1;
+            }
+        }
+    };
+
+    let mut plugboard = [0; ALPHABET_LEN];
+    for (letter, plug) in plugboard.iter_mut().enumerate() {
+        *plug = letter as i16;
+    }
+    let mut best_score = score(&counts);
+    for _ in 0..SEARCH_PLUGS {
+        let mut best_pair = None;
+        for a in (0..ALPHABET_LEN).filter(|&a| plugboard[a] == a as i16) {
+            for b in (a + 1..ALPHABET_LEN).filter(|&b| plugboard[b] == b as i16) {
+                let mut trial = counts;
+                plug(&mut trial, a, b);
+                let trial_score = score(&trial);
+                if trial_score > best_score {
+                    best_score = trial_score;
+                    best_pair = Some((a, b));
+                }
+            }
+        }
+
+        match best_pair {
+            Some((a, b)) => {
+                plug(&mut counts, a, b);
+                plugboard[a] = b as i16;
+                plugboard[b] = a as i16;
+            }
+            None => break,
+        }
+    }
+
+    plugboard
+}
+
+/// Applies `f` to every item, splitting the items between threads, and returns the
+/// results in order.
+///
+/// # Arguments
+///
+/// * `items` The items to process
+/// * `f` A function of type: (item) -> result
+///
+pub(crate) fn parallel_map<T, R, F>(items: &[T], f: F) -> Vec<R>
+where
+    T: Sync,
+    R: Send,
+    F: Fn(&T) -> R + Sync,
+{
+    let threads = std::thread::available_parallelism().map_or(1, |n| n.get());
+    let chunk_size = items.len().div_ceil(threads).max(1);
+    let f = &f;
+
+    std::thread::scope(|scope| {
+        let handles = items
+            .chunks(chunk_size)
+            .map(|chunk| scope.spawn(move || chunk.iter().map(f).collect::<Vec<_>>()))
+            .collect::<Vec<_>>();
+
+        handles
+            .into_iter()
+            .flat_map(|handle| handle.join().unwrap())
+            .collect()
+    })
+}
+
+impl Symmetric for Enigma {
+    fn run(&self, language: &mut Language, msg: &str) -> String {
+        let mut enigma_mut = self.clone();
+        enigma_mut.run_mut(language, msg)
+    }
+}
+
+impl Keyed for Enigma {
+    fn new(language: &mut Language) -> Enigma {
+        Enigma::with_model(language, EnigmaModel::M3)
+    }
     fn keys(&self) -> Vec<&dyn IoKey> {
         let mut result: Vec<&dyn IoKey> = vec![&self.plugboard, &self.reflector];
 
@@ -103,3 +799,295 @@ impl Keyed for Enigma {
         result
     }
 }
+
+impl Solve for Enigma {
+    fn solve(&mut self, language: &mut Language, msg: &str) {
+        if self.model.is_commercial() {
+            return;
+        }
+
+        language.set_alph_len(ALPHABET_LEN);
+        let ciphertext = language.string_to_vec(msg);
+
+        // every wheel order of the model, using reflector B or C
+        let rotor_types = self.model.rotor_types();
+        let wheel_orders = M3_REFLECTORS
+            .iter()
+            .flat_map(|&reflector| {
+                wheel_orders(&rotor_types)
+                    .into_iter()
+                    .map(move |rotors| (rotors, reflector))
+            })
+            .map(|(rotors, reflector)| Enigma::with_wheel_order(language, &rotors, reflector))
+            .collect::<Vec<_>>();
+
+        // the best start positions over all wheel orders
+        let mut candidates =
+            parallel_map(&wheel_orders, |enigma| {
+                enigma.search_starts(language, &ciphertext)
+            })
+                .into_iter()
+                .enumerate()
+                .flat_map(|(order, starts)| {
+                    starts
+                        .into_iter()
+                        .map(move |(score, rings, start)| (score, order, rings, start))
+                })
+                .collect::<Vec<_>>();
+        candidates.sort_unstable_by(|a, b| b.0.total_cmp(&a.0));
+        candidates.truncate(CANDIDATES);
+        candidates.sort_unstable_by_key(|&(_, order, rings, start)| (order, rings, start));
+
+        // group the start positions by wheel order, so each scrambler table is only
+        // found once
+        let mut groups = Vec::<(usize, Vec<(i16, usize)>)>::new();
+        for (_, order, rings, start) in candidates {
+            match groups.last_mut() {
+                Some((last, starts)) if *last == order => starts.push((rings, start)),
+                _ => groups.push((order, vec![(rings, start)])),
+            }
+        }
+
+        let solutions = parallel_map(&groups, |(order, starts)| {
+            let table = wheel_orders[*order].scrambler_table();
+
+            starts
+                .iter()
+                .map(|&(rings, start)| {
+                    let mut enigma = wheel_orders[*order].clone();
+                    enigma.rotors[0].rings = rings;
+                    enigma.set_positions(start);
+                    let (plugboard, score) = enigma.refine(language, &table, &ciphertext);
+                    (score, enigma, plugboard)
+                })
+                .max_by(|a, b| a.0.total_cmp(&b.0))
+                .unwrap()
+        });
+
+        if let Some((_, mut enigma, plugboard)) =
+            solutions.into_iter().max_by(|a, b| a.0.total_cmp(&b.0))
+        {
+            for (letter, &plug) in plugboard.iter().enumerate() {
+                if plug as usize > letter {
+                    enigma.plugboard.add_plug(letter as i16, plug);
+                }
+            }
+
+            // the thin reflectors act as reflector B or C when paired with the Beta or
+            // Gamma greek wheel at its zero offset
+            let mut solved = Enigma::with_model(language, self.model);
+            solved.plugboard = enigma.plugboard;
+            for (rotor, solved_rotor) in enigma.rotors.iter().zip(solved.rotors.iter_mut()) {
+                solved_rotor
+                    .set(language, rotor.wiring_type().unwrap())
+                    .unwrap();
+                solved_rotor.grund = rotor.grund;
+                solved_rotor.rings = rotor.rings;
+            }
+            let (greek, reflector) = match enigma.reflector.wiring_type() {
+                ReflectorType::B => (RotorType::Beta, ReflectorType::BThin),
+                _ => (RotorType::Gamma, ReflectorType::CThin),
+            };
+            if let Some(solved_greek) = solved.rotors.get_mut(3) {
+                solved_greek.set(language, greek).unwrap();
+                solved.reflector.set(language, reflector).unwrap();
+            } else {
+                solved.reflector = enigma.reflector;
+            }
+            *self = solved;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::key::Key;
+
+    #[test]
+    fn m4() {
+        let mut language = Language::from_file("examples/data/english.bin").unwrap();
+
+        // U-264, 25 November 1942
+        let mut enigma = Enigma::with_model(&mut language, EnigmaModel::M4);
+        enigma.rotors[0].set(&mut language, "i:0:21").unwrap();
+        enigma.rotors[1].set(&mut language, "iv:13:0").unwrap();
+        enigma.rotors[2].set(&mut language, "ii:9:0").unwrap();
+        enigma.rotors[3].set(&mut language, "beta:21:0").unwrap();
+        enigma.reflector.set(&mut language, "bthin").unwrap();
+        enigma
+            .plugboard
+            .set(&mut language, "at:bl:df:gj:hm:nw:op:qy:rz:vx")
+            .unwrap();
+        assert_eq!(
+            enigma.run(&mut language, "NCZWVUSXPNYMINHZXMQXSFWXWLKJAHSHNMCOCCAKUQPMKCSMHKSEINJUSBLKIOSXCKUBHMLLXCSJUSRRDVKOHULXWCCBGVLIYXEOAHXRHKKFVDREWEZLXOBAFGYUJQUKGRTVUKAMEURBVEKSUHHVOYHABCJWMAKLFKLMYFVNRIZRVVRTKOFDANJMOLBGFFLEOPRGTFLVRHOWOPBEKVWMUQFMPWPARMFHAGKXIIBG"),
+            "VONVONJLOOKSJHFFTTTEINSEINSDREIZWOYYQNNSNEUNINHALTXXBEIANGRIFFUNTERWASSERGEDRUECKTYWABOSXLETZTERGEGNERSTANDNULACHTDREINULUHRMARQUANTONJOTANEUNACHTSEYHSDREIYZWOZWONULGRADYACHTSMYSTOSSENACHXEKNSVIERMBFAELLTYNNNNNNOOOVIERYSICHTEINSNULL"
+        );
+
+        // U-534, 1 May 1945
+        let mut enigma = Enigma::with_model(&mut language, EnigmaModel::M4);
+        enigma.rotors[0].set(&mut language, "viii:25:11").unwrap();
+        enigma.rotors[1].set(&mut language, "vi:18:4").unwrap();
+        enigma.rotors[2].set(&mut language, "v:3:15").unwrap();
+        enigma.rotors[3].set(&mut language, "beta:2:4").unwrap();
+        enigma.reflector.set(&mut language, "cthin").unwrap();
+        enigma
+            .plugboard
+            .set(&mut language, "ae:bf:cm:dq:hu:jn:lx:pr:sz:vw")
+            .unwrap();
+        assert_eq!(
+            enigma.run(&mut language, "LANOTCTOUARBBFPMHPHGCZXTDYGAHGUFXGEWKBLKGJWLQXXTGPJJAVTOCKZFSLPPQIHZFXOEBWIIEKFZLCLOAQJULJOYHSSMBBGWHZANVOIIPYRBRTDJQDJJOQKCXWDNBBTYVXLYTAPGVEATXSONPNYNQFUDBBHHVWEPYEYDOHNLXKZDNWRHDUWUJUMWWVIIWZXIVIUQDRHYMNCYEFUAPNHOTKHKGDNPSAKNUAGHJZSMJBMHVTREQEDGXHLZWIFUSKDQVELNMIMITHBHDBWVHDFYHJOQIHORTDJDBWXEMEAYXGYQXOHFDMYUXXNOJAZRSGHPLWMLRECWWUTLRTTVLBHYOORGLGOWUXNXHMHYFAACQEKTHSJW"),
+            "KRKRALLEXXFOLGENDESISTSOFORTBEKANNTZUGEBENXXICHHABEFOLGELNBEBEFEHLERHALTENXXJANSTERLEDESBISHERIGXNREICHSMARSCHALLSJGOERINGJSETZTDERFUEHRERSIEYHVRRGRZSSADMIRALYALSSEINENNACHFOLGEREINXSCHRIFTLSCHEVOLLMACHTUNTERWEGSXABSOFORTSOLLENSIESAEMTLICHEMASSNAHMENVERFUEGENYDIESICHAUSDERGEGENWAERTIGENLAGEERGEBENXGEZXREICHSLEITEIKKTULPEKKJBORMANNJXXOBXDXMMMDURNHFKSTXKOMXADMXUUUBOOIEXKP"
+        );
+    }
+
+    #[test]
+    fn models() {
+        let mut language = Language::from_file("examples/data/english.bin").unwrap();
+
+        let mut enigma = Enigma::with_model(&mut language, EnigmaModel::I);
+        assert!(enigma.rotors[0].set(&mut language, "vi").is_err());
+        assert!(enigma.rotors[0].set(&mut language, "beta").is_err());
+        assert_eq!(enigma.rotors.len(), 3);
+
+        let mut enigma = Enigma::with_model(&mut language, EnigmaModel::M4);
+        assert_eq!(enigma.rotors.len(), 4);
+        assert!(enigma.rotors[0].set(&mut language, "gamma").is_err());
+        assert!(enigma.rotors[3].set(&mut language, "viii").is_err());
+        assert!(enigma.rotors[3].set(&mut language, "gamma").is_ok());
+        assert!(enigma.reflector.set(&mut language, "b").is_err());
+        assert_eq!(enigma.reflector.wiring_type(), ReflectorType::BThin);
+    }
+
+    #[test]
+    fn commercial() {
+        let mut language = Language::from_file("examples/data/english.bin").unwrap();
+        let plaintext = "THEQUICKBROWNFOXJUMPSOVERTHELAZYDOG";
+
+        for &model in [EnigmaModel::K, EnigmaModel::SwissK, EnigmaModel::Railway].iter() {
+            let mut enigma = Enigma::with_model(&mut language, model);
+            enigma.rotors[0].grund = 24;
+            enigma.rotors[1].rings = 7;
+            enigma.reflector.position = 11;
+
+            let ciphertext = enigma.run(&mut language, plaintext);
+            assert!(ciphertext
+                .chars()
+                .zip(plaintext.chars())
+                .all(|(a, b)| a != b));
+            assert_eq!(enigma.run(&mut language, &ciphertext), plaintext);
+        }
+
+        let mut enigma = Enigma::with_model(&mut language, EnigmaModel::K);
+        assert!(enigma.reflector.set(&mut language, "k:12").is_ok());
+        assert!(enigma.rotors[0].set(&mut language, "i").is_err());
+        let mut enigma = Enigma::new(&mut language);
+        assert!(enigma.reflector.set(&mut language, "b:12").is_err());
+    }
+
+    #[test]
+    fn cog_stepping() {
+        let mut language = Language::from_file("examples/data/english.bin").unwrap();
+        let mut enigma = Enigma::with_model(&mut language, EnigmaModel::G312);
+        enigma.rotors[0].set(&mut language, "g312-iii:0:0").unwrap();
+        enigma.rotors[1].set(&mut language, "g312-ii:0:0").unwrap();
+        enigma.rotors[2].set(&mut language, "g312-i:0:0").unwrap();
+
+        // every rotor is on a notch, so each turns the next and the left rotor turns the
+        // reflector
+        let mut stepped = enigma.clone();
+        stepped.run_mut(&language, "A");
+        assert!(stepped.rotors.iter().all(|rotor| rotor.grund == 1));
+        assert_eq!(stepped.reflector.position, 1);
+
+        // off a notch, the right rotor steps alone and the middle rotor never double
+        // steps
+        enigma.rotors[0].grund = 1;
+        enigma.rotors[1].grund = 1;
+        enigma.run_mut(&language, "A");
+        assert_eq!(enigma.rotors[0].grund, 2);
+        assert_eq!(enigma.rotors[1].grund, 1);
+        assert_eq!(enigma.rotors[2].grund, 0);
+        assert_eq!(enigma.reflector.position, 0);
+
+        let ciphertext = enigma.run(&mut language, "ATTACKATDAWN");
+        assert_eq!(enigma.run(&mut language, &ciphertext), "ATTACKATDAWN");
+    }
+
+    #[test]
+    fn ukw_d_and_uhr() {
+        let mut language = Language::from_file("examples/data/english.bin").unwrap();
+        let plaintext = "THEQUICKBROWNFOXJUMPSOVERTHELAZYDOG";
+
+        let mut enigma = Enigma::with_model(&mut language, EnigmaModel::I);
+        enigma
+            .reflector
+            .set(&mut language, "d:ai:cx:dw:eq:fg:hr:jz:kt:lm:np:su:vy")
+            .unwrap();
+        assert_eq!(enigma.reflector.input(0), 8);
+        assert_eq!(enigma.reflector.input(14), 1);
+        enigma
+            .plugboard
+            .set(&mut language, "uhr:13:ab:cd:ef:gh:ij:kl:mn:op:qr:st")
+            .unwrap();
+        assert!((0..26).any(|letter| {
+            let plugged = enigma.plugboard.input(letter);
+            enigma.plugboard.input(plugged) != letter && enigma.plugboard.output(plugged) == letter
+        }));
+
+        let ciphertext = enigma.run(&mut language, plaintext);
+        assert!(ciphertext
+            .chars()
+            .zip(plaintext.chars())
+            .all(|(a, b)| a != b));
+        assert_eq!(enigma.run(&mut language, &ciphertext), plaintext);
+
+        assert!(enigma
+            .reflector
+            .set(&mut language, "d:ab:cx:dw:eq:fg:hr:jz:kt:lm:np:su:vy")
+            .is_err());
+        assert!(enigma.reflector.set(&mut language, "d:ai:cx").is_err());
+        assert!(enigma
+            .plugboard
+            .set(&mut language, "uhr:40:ab:cd:ef:gh:ij:kl:mn:op:qr:st")
+            .is_err());
+        assert!(enigma
+            .plugboard
+            .set(&mut language, "uhr:0:ab:cd:ef:gh:ij:kl:mn:op:qr")
+            .is_err());
+
+        let mut enigma = Enigma::with_model(&mut language, EnigmaModel::M4);
+        assert!(enigma.reflector.set(&mut language, "d").is_err());
+    }
+
+    #[test]
+    fn custom_rotors() {
+        let mut language = Language::from_file("examples/data/english.bin").unwrap();
+        let plaintext = "THEQUICKBROWNFOXJUMPSOVERTHELAZYDOG";
+
+        let mut enigma = Enigma::new(&mut language);
+        enigma.rotors[0].set(&mut language, "iii:5:3").unwrap();
+        enigma.rotors[1].set(&mut language, "i:21:7").unwrap();
+        let ciphertext = enigma.run(&mut language, plaintext);
+
+        // the same machine, with rotor I defined at runtime
+        let mut custom = enigma.clone();
+        custom.rotors[1]
+            .set_key_str(&mut language, "EKMFLGDQVZNTOWYHXUSPAIBRCJ/Q:21:7")
+            .unwrap();
+        assert_eq!(custom.rotors[1].wiring_type(), None);
+        assert_eq!(custom.run(&mut language, plaintext), ciphertext);
+
+        // a fictional rotor mixed with the built-in rotors
+        custom.rotors[2]
+            .set_key_str(&mut language, "QWERTYUIOPASDFGHJKLZXCVBNM/AM:3:0")
+            .unwrap();
+        let ciphertext = custom.run(&mut language, plaintext);
+        assert_ne!(ciphertext, enigma.run(&mut language, plaintext));
+        assert_eq!(custom.run(&mut language, &ciphertext), plaintext);
+
+        assert!(custom.rotors[2]
+            .set_key_str(&mut language, "QWERTYUIOPASDFGHJKLZXCVBNQ/A")
+            .is_err());
+    }
+}
@@ -0,0 +1,266 @@
+use crate::{
+    cipher::{self, Keyed, Symmetric},
+    error::{Error, Result},
+    key::{IdentityKey, IoKey, LugCage, PinWheels, StatefulKey},
+    lang::Language,
+    util,
+};
+
+/// The M-209 uses the 26 letter alphabet
+const ALPHABET_LEN: usize = 26;
+/// The number of pin wheels
+const WHEELS: usize = 6;
+/// The number of bars in the lug cage
+const BARS: usize = 27;
+
+/// The number of temperature steps of each restart of the known-plaintext solver
+const SOLVE_TEMP_STEPS: usize = 200;
+/// The number of keys tried at each temperature step
+const SOLVE_ITERATIONS: usize = 1000;
+/// The initial temperature, in letters of the crib
+const SOLVE_START_TEMP: f64 = 3.0;
+/// The maximum number of restarts before giving up on finding an exact key
+const SOLVE_MAX_RESTARTS: usize = 100;
+
+/// The Hagelin M-209 (and the similar C-38), a pin-and-lug cipher machine. For each
+/// letter, the pins of the six wheels that are against the guide arms select the bars
+/// of the lug cage that are shifted, and the number of shifted bars is the shift of the
+/// letter. The letter is enciphered like the Beaufort cipher, as the shift minus the
+/// letter (so with no shift, A becomes Z), and then every wheel steps. Since
+/// enciphering twice with the same shift gives the original letter, the machine is
+/// self-reciprocal.
+///
+#[derive(Clone)]
+pub struct M209 {
+    pub wheels: PinWheels,
+    pub lugs: LugCage,
+}
+
+impl M209 {
+    pub fn run_mut(&mut self, language: &Language, msg: &str) -> String {
+        msg.chars()
+            .map(|c| {
+                if language.is_letter(&c) {
+                    let shift = self.lugs.shift(self.wheels.active());
+                    let cp = util::modulo(shift - 1 - language.get_cp(&c), ALPHABET_LEN as i16);
+                    self.wheels.step();
+                    language.update_cp(&c, cp)
+                } else {
+                    c
+                }
+            })
+            .collect()
+    }
+
+    /// Finds the pins and lugs from a crib, which is known plaintext from the start of
+    /// the message. The wheels are left at their current positions, which should be
+    /// those given by the message indicator, and the pins and lugs are found by
+    /// simulated annealing until the key enciphers the crib exactly. About 100 letters
+    /// of crib are needed for the key to be unique, and shorter cribs may find a key
+    /// which enciphers the crib but not the rest of the message. Returns whether the key
+    /// found enciphers the crib exactly.
+    ///
+    /// # Arguments
+    ///
+    /// * `language` A [`Language`] instance
+    /// * `crib` The known plaintext at the start of the message
+    /// * `ciphertext` The ciphertext, at least as long as the crib
+    ///
+    pub fn solve_crib(
+        &mut self,
+        language: &mut Language,
+        crib: &str,
+        ciphertext: &str,
+    ) -> Result<bool> {
+        language.set_alph_len(ALPHABET_LEN);
+
+        let crib = language.string_to_vec(crib);
+        let ciphertext = language.string_to_vec(ciphertext);
+        if ciphertext.len() < crib.len() {
+            return Err(Error::InsufficientInputLen {
+                expected: crib.len(),
+                actual: ciphertext.len(),
+            });
+        }
+
+        // the shift of each letter is known modulo 26
+        let expected = crib
+            .iter()
+            .zip(ciphertext.iter())
+            .map(|(&pt, &ct)| util::modulo(pt + ct + 1, ALPHABET_LEN as i16))
+            .collect::<Vec<_>>();
+
+        // the pin of each wheel against the guide arm for each letter
+        let active_pins = (0..crib.len())
+            .map(|idx| {
+                let mut pins = [0; WHEELS];
+                for (wheel, pin) in pins.iter_mut().enumerate() {
+                    *pin = (self.wheels.active_pin(wheel) + idx) % self.wheels.wheel_len(wheel);
+                }
+                pins
+            })
+            .collect::<Vec<_>>();
+
+        // the distance of each shift from its expected value, where shifts of 26 and 27
+        // are the same as 0 and 1
+        let score = |(wheels, lugs): &(PinWheels, LugCage)| {
+            -active_pins
+                .iter()
+                .zip(expected.iter())
+                .map(|(pins, &expected)| {
+                    let active = (0..WHEELS)
+                        .filter(|&wheel| wheels.pin(wheel, pins[wheel]))
+                        .fold(0, |mask, wheel| mask | 1 << wheel);
+                    let shift = lugs.shift(active);
+
+                    match expected + ALPHABET_LEN as i16 <= BARS as i16 {
+                        true => (shift - expected)
+                            .abs()
+                            .min((shift - expected - ALPHABET_LEN as i16).abs()),
+                        false => (shift - expected).abs(),
+                    }
+                })
+                .sum::<i16>() as f64
+        };
+
+        let mut best_key = (self.wheels.clone(), self.lugs.clone());
+        let mut best_score = f64::MIN;
+
+        for _ in 0..SOLVE_MAX_RESTARTS {
+            let mut key = (self.wheels.clone(), self.lugs.clone());
+            for wheel in 0..WHEELS {
+                for pin in 0..key.0.wheel_len(wheel) {
+                    key.0.set_pin(wheel, pin, fastrand::bool());
+                }
+            }
+            key.1.randomize(language);
+
+            let score = cipher::anneal(
+                &mut key,
+                SOLVE_START_TEMP,
+                SOLVE_TEMP_STEPS,
+                SOLVE_ITERATIONS,
+                score,
+                |(wheels, lugs)| {
+                    if fastrand::bool() {
+                        // flip a pin
+                        let wheel = fastrand::usize(0..WHEELS);
+                        let pin = fastrand::usize(0..wheels.wheel_len(wheel));
+                        wheels.set_pin(wheel, pin, !wheels.pin(wheel, pin));
+                    } else {
+                        // move the lugs of a bar
+                        let lug1 = fastrand::usize(0..=WHEELS);
+                        let lug2 = fastrand::usize(lug1 + 1..=WHEELS + 1) % (WHEELS + 1);
+                        lugs.set_bar(fastrand::usize(0..BARS), (lug1, lug2));
+                    }
+                },
+            );
+
+            if score > best_score {
+                best_score = score;
+                best_key = key;
+            }
+            if best_score == 0.0 {
+                break;
+            }
+        }
+
+        let (wheels, lugs) = best_key;
+        self.wheels = wheels;
+        self.lugs = lugs;
+
+        Ok(best_score == 0.0)
+    }
+}
+
+impl Symmetric for M209 {
+    fn run(&self, language: &mut Language, msg: &str) -> String {
+        let mut m209_mut = self.clone();
+        m209_mut.run_mut(language, msg)
+    }
+}
+
+impl Keyed for M209 {
+    fn new(language: &mut Language) -> M209 {
+        language.set_alph_len(ALPHABET_LEN);
+
+        let mut result = M209 {
+            wheels: PinWheels::identity(language),
+            lugs: LugCage::identity(language),
+        };
+
+        result.wheels.info_mut().set("Pin wheels", "pins");
+        result.lugs.info_mut().set("Lug cage", "lugs");
+
+        result
+    }
+    fn keys(&self) -> Vec<&dyn IoKey> {
+        vec![&self.wheels, &self.lugs]
+    }
+    fn keys_mut(&mut self) -> Vec<&mut dyn IoKey> {
+        vec![&mut self.wheels, &mut self.lugs]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::key::Key;
+
+    const PINS: &str =
+        "ABDHIKMNSTVWYZ:ACDEHJLMNRUVXZ:ABDGIKNOPTUV:CFGIJLNPQT:ABDEGHJKNQS:BCEHIKLMOP";
+    const LUGS: &str =
+        "10:10:20:20:20:30:30:40:40:40:40:50:50:50:60:60:60:60:15:26:34:36:45:56:14:23:25";
+
+    #[test]
+    fn run() {
+        let mut language = Language::from_file("examples/data/english.bin").unwrap();
+        let plaintext = "THE QUICK BROWN FOX JUMPS OVER THE LAZY DOG";
+
+        // with every lug neutral, A becomes Z
+        let mut m209 = M209::new(&mut language);
+        assert_eq!(m209.run(&mut language, "ABCXYZ"), "ZYXCBA");
+
+        m209.wheels
+            .set(&mut language, &format!("{}:{}", PINS, "GMRKAQ"))
+            .unwrap();
+        m209.lugs.set(&mut language, LUGS).unwrap();
+
+        let ciphertext = m209.run(&mut language, plaintext);
+        assert_ne!(ciphertext, plaintext);
+        assert_eq!(m209.run(&mut language, &ciphertext), plaintext);
+
+        // the key string is given back in the same form
+        assert_eq!(
+            m209.wheels.to_string(&mut language),
+            format!("{}:{}", PINS, "GMRKAQ")
+        );
+        assert_eq!(m209.lugs.to_string(&mut language), LUGS);
+        assert!(m209.wheels.set(&mut language, "A:B:C:D:E:W").is_err());
+        assert!(m209
+            .wheels
+            .set(&mut language, "A:B:C:D:E:F:AAAAAR")
+            .is_err());
+        assert!(m209.lugs.set(&mut language, "11").is_err());
+    }
+
+    #[test]
+    fn solve_crib() {
+        let mut language = Language::from_file("examples/data/english.bin").unwrap();
+        let plaintext = "ANARMYMARCHESONITSSTOMACHTHESUPPLYCOLUMNWILLREACHTHEFORWARDDEPOTATDAWNANDTHEBATTALIONWILLMOVEOUTWESTWARDSTOWARDSTHERIVERCROSSINGBEFORENOONAWAITFURTHERORDERS";
+        let crib = &plaintext[..100];
+
+        let mut m209 = M209::new(&mut language);
+        m209.wheels
+            .set(&mut language, &format!("{}:{}", PINS, "ADQLSF"))
+            .unwrap();
+        m209.lugs.set(&mut language, LUGS).unwrap();
+        let ciphertext = m209.run(&mut language, plaintext);
+
+        // the solver keeps the positions of the wheels from the message indicator
+        let mut solved = M209::new(&mut language);
+        solved.wheels.set(&mut language, "::::::ADQLSF").unwrap();
+        assert!(solved.solve_crib(&mut language, crib, &ciphertext).unwrap());
+        assert_eq!(solved.run(&mut language, &ciphertext), plaintext);
+    }
+}
@@ -1 +1,4 @@
+pub mod bombe;
 pub mod enigma;
+pub mod m209;
+pub mod typex;
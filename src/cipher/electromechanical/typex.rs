@@ -0,0 +1,213 @@
+use crate::key::{Key, Plugboard, Reflector, ReflectorType, Rotor, RotorType};
+use crate::lang::Language;
+use crate::{
+    cipher::{Keyed, Symmetric},
+    key::{IdentityKey, IoKey},
+};
+
+/// The number of rotors of the Typex, including the stators
+const ROTORS: usize = 5;
+/// The number of stators, which are the rightmost rotors
+const STATORS: usize = 2;
+
+/// The British Typex cipher machine, developed from the commercial Enigma. Each letter
+/// passes through the input plugboard, the five rotors, the output plugboard and the
+/// reflector, then back through the output plugboard, rotors and input plugboard.
+/// Since both plugboards are passed in each direction, the machine is
+/// self-reciprocal.
+///
+/// The two rightmost rotors are stators, which can be set to any position but never
+/// step. The other three step like the rotors of the Enigma, including the double
+/// step of the middle rotor, except that each rotor can have several notches. Any
+/// rotor can be inserted backwards. The rotors default to Enigma rotors I to V, and
+/// other rotors can be set with custom wirings and notches (See
+/// [`RotorDefinition`](crate::key::RotorDefinition)).
+///
+#[derive(Clone)]
+pub struct Typex {
+    /// The plugboard between the keyboard and the rotors
+    pub input_plugboard: Plugboard,
+    /// The plugboard between the rotors and the reflector
+    pub output_plugboard: Plugboard,
+    // 0=Right stator, 1=Left stator, 2=Rightmost ... 4=Leftmost rotor
+    pub rotors: Vec<Rotor>,
+    pub reflector: Reflector,
+}
+
+impl Typex {
+    pub fn run_mut(&mut self, language: &Language, msg: &str) -> String {
+        msg.chars()
+            .map(|c| {
+                if language.is_letter(&c) {
+                    let mut cp = language.get_cp(&c);
+                    self.step_rotors();
+                    cp = self.input_plugboard.input(cp);
+                    cp = self.rotor_pass(cp, false);
+                    cp = self.output_plugboard.input(cp);
+                    cp = self.reflector.input(cp);
+                    cp = self.output_plugboard.output(cp);
+                    cp = self.rotor_pass(cp, true);
+                    cp = self.input_plugboard.output(cp);
+                    language.update_cp(&c, cp)
+                } else {
+                    c
+                }
+            })
+            .collect()
+    }
+
+    /// Steps the three rotors to the left of the stators, including the double step
+    /// of the middle rotor
+    ///
+    fn step_rotors(&mut self) {
+        let (right, middle, left) = (STATORS, STATORS + 1, STATORS + 2);
+
+        if self.rotors[middle].is_on_notch() {
+            self.rotors[middle].step();
+            self.rotors[left].step();
+        } else if self.rotors[right].is_on_notch() {
+            self.rotors[middle].step();
+        }
+        self.rotors[right].step();
+    }
+
+    fn rotor_pass(&self, letter: i16, reverse: bool) -> i16 {
+        if reverse {
+            self.rotors
+                .iter()
+                .rev()
+                .fold(letter, |cp, rotor| rotor.input(cp, true))
+        } else {
+            self.rotors
+                .iter()
+                .fold(letter, |cp, rotor| rotor.input(cp, false))
+        }
+    }
+}
+
+impl Symmetric for Typex {
+    fn run(&self, language: &mut Language, msg: &str) -> String {
+        let mut typex_mut = self.clone();
+        typex_mut.run_mut(language, msg)
+    }
+}
+
+impl Keyed for Typex {
+    fn new(language: &mut Language) -> Typex {
+        let mut result = Typex {
+            input_plugboard: Plugboard::identity(language),
+            output_plugboard: Plugboard::identity(language),
+            rotors: (0..ROTORS)
+                .rev()
+                .map(|rotor| *Rotor::new(language, RotorType::from(rotor)).unwrap())
+                .collect(),
+            reflector: *Reflector::new(language, ReflectorType::B).unwrap(),
+        };
+
+        result
+            .rotors
+            .iter_mut()
+            .for_each(|rotor| rotor.set_reversible(true));
+
+        result
+            .input_plugboard
+            .info_mut()
+            .set("Input plugboard", "in");
+        result
+            .output_plugboard
+            .info_mut()
+            .set("Output plugboard", "out");
+        result.rotors[0].info_mut().set("Right stator", "r0");
+        result.rotors[1].info_mut().set("Left stator", "r1");
+        result.rotors[2].info_mut().set("Right rotor", "r2");
+        result.rotors[3].info_mut().set("Middle rotor", "r3");
+        result.rotors[4].info_mut().set("Left rotor", "r4");
+        result.reflector.info_mut().set("Reflector", "ref");
+
+        result
+    }
+    fn keys(&self) -> Vec<&dyn IoKey> {
+        let mut result: Vec<&dyn IoKey> = vec![
+            &self.input_plugboard,
+            &self.output_plugboard,
+            &self.reflector,
+        ];
+
+        self.rotors.iter().for_each(|rotor| result.push(rotor));
+
+        result
+    }
+    fn keys_mut(&mut self) -> Vec<&mut dyn IoKey> {
+        let mut result: Vec<&mut dyn IoKey> = vec![
+            &mut self.input_plugboard,
+            &mut self.output_plugboard,
+            &mut self.reflector,
+        ];
+
+        self.rotors.iter_mut().for_each(|rotor| result.push(rotor));
+
+        result
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::key::RotorDefinition;
+
+    #[test]
+    fn run() {
+        let mut language = Language::from_file("examples/data/english.bin").unwrap();
+        let plaintext = "THEQUICKBROWNFOXJUMPSOVERTHELAZYDOG";
+
+        let mut typex = Typex::new(&mut language);
+        typex
+            .input_plugboard
+            .set(&mut language, "ab:cd:ef")
+            .unwrap();
+        typex
+            .output_plugboard
+            .set(&mut language, "uhr:7:gh:ij:kl:mn:op:qr:st:uv:wx:yz")
+            .unwrap();
+        typex.rotors[1]
+            .set_key_str(&mut language, "ii:4:0:r")
+            .unwrap();
+        typex.rotors[3]
+            .set_key_str(&mut language, "QWERTYUIOPASDFGHJKLZXCVBNM/AEIMQUY:3:5:r")
+            .unwrap();
+        typex.rotors[4]
+            .set_key_str(&mut language, "v:20:11")
+            .unwrap();
+
+        let ciphertext = typex.run(&mut language, plaintext);
+        assert!(ciphertext
+            .chars()
+            .zip(plaintext.chars())
+            .all(|(a, b)| a != b));
+        assert_eq!(typex.run(&mut language, &ciphertext), plaintext);
+
+        // a reversed rotor is the mirror image of the rotor inserted forwards
+        let definition = typex.rotors[3].definition().clone();
+        assert_eq!(definition.reversed().reversed(), definition);
+        assert_ne!(definition.reversed(), definition);
+    }
+
+    #[test]
+    fn stepping() {
+        let mut language = Language::from_file("examples/data/english.bin").unwrap();
+        let mut typex = Typex::new(&mut language);
+        let definition =
+            RotorDefinition::parse(&language, "EKMFLGDQVZNTOWYHXUSPAIBRCJ", "AGMS").unwrap();
+        typex.rotors[2].set(&mut language, definition).unwrap();
+        typex.rotors[3].set(&mut language, RotorType::I).unwrap();
+
+        // the stators never step, and the rightmost rotor turns the middle rotor at
+        // each of its notches
+        typex.run_mut(&language, &"A".repeat(26));
+        assert_eq!(typex.rotors[0].grund, 0);
+        assert_eq!(typex.rotors[1].grund, 0);
+        assert_eq!(typex.rotors[2].grund, 0);
+        assert_eq!(typex.rotors[3].grund, 4);
+        assert_eq!(typex.rotors[4].grund, 0);
+    }
+}
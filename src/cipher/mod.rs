@@ -120,3 +120,61 @@ pub trait Solve {
     ///
     fn solve(&mut self, language: &mut Language, msg: &str);
 }
+
+/// Searches for the key which maximises `score` using simulated annealing. At each
+/// iteration a copy of the current key is changed by `modify`, and is kept if it scores
+/// better than the current key, or otherwise with probability `exp(diff / temp)`. The
+/// temperature falls linearly from `start_temp` to zero over `steps` steps. `key` is
+/// updated with the best key seen, and the best score is returned.
+///
+/// # Arguments
+///
+/// * `key` The key to start searching from, which is set to the best key found
+/// * `start_temp` The initial temperature, in the same units as the score
+/// * `steps` The number of temperature steps
+/// * `iterations` The number of candidate keys to try at each temperature
+/// * `score` A function of type: (key) -> score, where a greater score is better
+/// * `modify` A function which makes a small random change to a key
+///
+pub fn anneal<K, F, G>(
+    key: &mut K,
+    start_temp: f64,
+    steps: usize,
+    iterations: usize,
+    mut score: F,
+    mut modify: G,
+) -> f64
+where
+    K: Clone,
+    F: FnMut(&K) -> f64,
+    G: FnMut(&mut K),
+{
+    let mut parent = key.clone();
+    let mut parent_score = score(&parent);
+    let mut best_score = parent_score;
+    let mut child = parent.clone();
+
+    for step in 0..steps {
+        let temp = start_temp * (1.0 - step as f64 / steps as f64);
+
+        for _ in 0..iterations {
+            child.clone_from(&parent);
+            modify(&mut child);
+
+            let child_score = score(&child);
+            let diff = child_score - parent_score;
+
+            if diff >= 0.0 || fastrand::f64() < (diff / temp).exp() {
+                std::mem::swap(&mut parent, &mut child);
+                parent_score = child_score;
+
+                if parent_score > best_score {
+                    best_score = parent_score;
+                    key.clone_from(&parent);
+                }
+            }
+        }
+    }
+
+    best_score
+}
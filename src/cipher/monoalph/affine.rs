@@ -40,7 +40,7 @@ impl Affine {
 
     fn valid_a_values(language: &Language) -> Vec<i16> {
         (1..language.cp_count())
-            .filter(|n| util::mmi(*n, language.cp_count()) != None)
+            .filter(|n| util::mmi(*n, language.cp_count()).is_some())
             .collect()
     }
 
@@ -1 +1,323 @@
+use crate::{
+    cipher::{self, Asymmetric, Keyed, Solve},
+    key::{Homophones, IdentityKey, IoKey, Key},
+    lang::{Language, ScoreSize},
+};
 
+/// Homophonic substitution uses the 26 letter alphabet
+const ALPHABET_LEN: usize = 26;
+
+/// Homophonic substitution. Each plaintext letter is replaced by one of its symbols
+/// (homophones), so that common letters are spread over several symbols and the
+/// ciphertext has a flat frequency distribution. The symbols of each letter are used in
+/// turn. Letters without a symbol are left out.
+///
+/// By default symbols are written as numbers separated by spaces, padded with zeros to
+/// the same width. If `symbols` has a char for every homophone, each symbol is
+/// written as its char instead.
+///
+/// The solver gives each letter the same number of symbols as encryption keys made
+/// from the letter probabilities (see [`Homophones::counts`]). Starting from the most
+/// frequent symbols given to the most common letters, it anneals over which symbols
+/// belong to each letter by swapping the letters of two symbols. Keys are scored using
+/// quadgrams along with the letter frequencies. Ciphertexts where the number of symbols
+/// of each letter is far from its probability are unlikely to be solved.
+///
+pub struct Homophonic {
+    pub homophones: Homophones,
+    /// The char used to write each symbol
+    pub symbols: Option<Vec<char>>,
+}
+
+impl Homophonic {
+    /// Gets the chars used to write each symbol, or `None` if symbols are written as
+    /// numbers
+    ///
+    fn symbol_chars(&self) -> Option<&[char]> {
+        self.symbols
+            .as_deref()
+            .filter(|symbols| symbols.len() >= self.homophones.len())
+    }
+
+    /// Splits a ciphertext into symbols, ignoring any chars which are not symbols. The
+    /// symbols are read in the same form as they are written by encryption.
+    ///
+    /// # Arguments
+    ///
+    /// * `msg` The ciphertext
+    ///
+    fn parse_symbols(&self, msg: &str) -> Vec<usize> {
+        match self.symbol_chars() {
+            Some(symbols) => msg
+                .chars()
+                .filter_map(|ch| symbols.iter().position(|&x| x == ch))
+                .collect(),
+            None => msg
+                .split(|ch: char| !ch.is_ascii_digit())
+                .filter_map(|token| token.parse().ok())
+                .collect(),
+        }
+    }
+
+    /// Decrypts the ciphertext symbols with `key`, storing the result in `dest`
+    ///
+    /// # Arguments
+    ///
+    /// * `key` The plaintext letter of each symbol
+    /// * `ciphertext` The ciphertext symbols
+    /// * `dest` Where to store the plaintext code points
+    ///
+    fn decrypt_symbols(key: &[i16], ciphertext: &[usize], dest: &mut [i16]) {
+        for (cp, &symbol) in dest.iter_mut().zip(ciphertext) {
+            *cp = key[symbol];
+        }
+    }
+}
+
+impl Asymmetric for Homophonic {
+    fn encrypt(&self, language: &mut Language, msg: &str) -> String {
+        language.set_alph_len(ALPHABET_LEN);
+
+        let homophones = self.homophones.symbols(ALPHABET_LEN);
+        let mut next = vec![0; ALPHABET_LEN];
+
+        let symbols = language
+            .string_to_vec(msg)
+            .into_iter()
+            .filter_map(|cp| {
+                let letter_symbols = &homophones[cp as usize];
+                let symbol =
+                    *letter_symbols.get(next[cp as usize] % letter_symbols.len().max(1))?;
+                next[cp as usize] += 1;
+                Some(symbol)
+            })
+            .collect::<Vec<_>>();
+
+        match self.symbol_chars() {
+            Some(chars) => symbols.into_iter().map(|symbol| chars[symbol]).collect(),
+            None => {
+                let width = (self.homophones.len() - 1).to_string().len();
+                symbols
+                    .into_iter()
+                    .map(|symbol| format!("{:0width$}", symbol, width = width))
+                    .collect::<Vec<_>>()
+                    .join(" ")
+            }
+        }
+    }
+    fn decrypt(&self, language: &mut Language, msg: &str) -> String {
+        language.set_alph_len(ALPHABET_LEN);
+
+        let key = self.homophones.as_slice();
+        let plaintext = self
+            .parse_symbols(msg)
+            .into_iter()
+            .filter_map(|symbol| key.get(symbol).copied())
+            .collect::<Vec<_>>();
+
+        language.vec_to_string(&plaintext)
+    }
+}
+
+impl Keyed for Homophonic {
+    fn new(language: &mut Language) -> Homophonic {
+        language.set_alph_len(ALPHABET_LEN);
+
+        let mut result = Homophonic {
+            homophones: Homophones::identity(language),
+            symbols: None,
+        };
+
+        result.homophones.info_mut().set("Homophones", "homophones");
+
+        result
+    }
+    fn keys(&self) -> Vec<&dyn IoKey> {
+        vec![&self.homophones]
+    }
+    fn keys_mut(&mut self) -> Vec<&mut dyn IoKey> {
+        vec![&mut self.homophones]
+    }
+}
+
+const TEMP_STEPS: usize = 100;
+const ITERATIONS: usize = 20000;
+const MAX_RESTARTS: usize = 20;
+/// The weight of the chi-squared statistic of the letter frequencies in the score
+const CHI_SQUARED_WEIGHT: f64 = 2.0;
+
+impl Solve for Homophonic {
+    fn solve(&mut self, language: &mut Language, msg: &str) {
+        language.set_alph_len(ALPHABET_LEN);
+
+        let ciphertext = self.parse_symbols(msg);
+        let num_symbols = match self.symbol_chars() {
+            Some(symbols) => symbols.len(),
+            None => ciphertext.iter().max().map_or(0, |&x| x + 1),
+        }
+        .max(ALPHABET_LEN);
+
+        let mut freqs = vec![0; num_symbols];
+        for &symbol in &ciphertext {
+            freqs[symbol] += 1;
+        }
+
+        // start by giving the most frequent symbols to the letters with the greatest
+        // probability per symbol
+        let probabilities = &language.unigram_probabilities;
+        let counts = Homophones::counts(language, num_symbols).unwrap();
+        let mut letters = counts
+            .iter()
+            .enumerate()
+            .flat_map(|(cp, &count)| vec![cp; count])
+            .collect::<Vec<_>>();
+        letters.sort_by(|&a, &b| {
+            let share = |cp: usize| probabilities[cp] / counts[cp] as f64;
+            share(b).total_cmp(&share(a))
+        });
+        let mut by_freq = (0..num_symbols).collect::<Vec<_>>();
+        by_freq.sort_by_key(|&symbol| std::cmp::Reverse(freqs[symbol]));
+
+        let mut start_key = vec![0; num_symbols];
+        for (&symbol, &cp) in by_freq.iter().zip(&letters) {
+            start_key[symbol] = cp as i16;
+        }
+
+        let mut plaintext = vec![0; ciphertext.len()];
+        // the difference in score between keys grows with the message length
+        let start_temp = (ciphertext.len() as f64 / 15.0).max(5.0);
+
+        let mut best_key = start_key.clone();
+        let mut best_score = f64::MIN;
+
+        for _ in 0..MAX_RESTARTS {
+            let mut key = start_key.clone();
+
+            let score = cipher::anneal(
+                &mut key,
+                start_temp,
+                TEMP_STEPS,
+                ITERATIONS,
+                |key| {
+                    Homophonic::decrypt_symbols(key, &ciphertext, &mut plaintext);
+
+                    // without the letter frequencies, giving too many symbols to common
+                    // letters produces text such as "THETHE" which scores well
+                    language.score(&plaintext, ScoreSize::Quadgrams)
+                        - CHI_SQUARED_WEIGHT * language.chi_squared(&plaintext)
+                },
+                |key| {
+                    key.swap(
+                        fastrand::usize(0..num_symbols),
+                        fastrand::usize(0..num_symbols),
+                    )
+                },
+            );
+
+            // the same best score from separate runs is likely to be the solution
+            if (score - best_score).abs() < 0.1 {
+                break;
+            }
+
+            if score > best_score {
+                best_score = score;
+                best_key = key;
+            }
+        }
+
+        self.homophones.set(language, best_key.as_slice()).unwrap();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const KEY: &str = "eeeeettttaaaooiiinnsshhrdlcumwfgypbvkjxqz";
+
+    #[test]
+    fn encrypt() {
+        let mut language = Language::from_file("examples/data/english.bin").unwrap();
+        let mut cipher = Homophonic::new(&mut language);
+        cipher.homophones.set(&mut language, KEY).unwrap();
+
+        let ciphertext = cipher.encrypt(&mut language, "Meet me at the tree");
+
+        assert_eq!(ciphertext, "28 00 01 05 28 02 09 06 07 21 03 08 23 04 00");
+    }
+
+    #[test]
+    fn decrypt() {
+        let mut language = Language::from_file("examples/data/english.bin").unwrap();
+        let mut cipher = Homophonic::new(&mut language);
+        cipher.homophones.set(&mut language, KEY).unwrap();
+
+        let plaintext = cipher.decrypt(
+            &mut language,
+            "28 00 01 05 28 02 09 06 07 21 03 08 23 04 00",
+        );
+
+        assert_eq!(plaintext, "MEETMEATTHETREE");
+    }
+
+    #[test]
+    fn encrypt_chars() {
+        let mut language = Language::from_file("examples/data/english.bin").unwrap();
+        let mut cipher = Homophonic::new(&mut language);
+        cipher.homophones.set(&mut language, KEY).unwrap();
+        cipher.symbols = Some(SYMBOLS.chars().collect());
+
+        let ciphertext = cipher.encrypt(&mut language, "Meet me at the tree");
+
+        assert_eq!(ciphertext, "CabfCcjghvdixea");
+    }
+
+    #[test]
+    fn decrypt_chars() {
+        let mut language = Language::from_file("examples/data/english.bin").unwrap();
+        let mut cipher = Homophonic::new(&mut language);
+        cipher.homophones.set(&mut language, KEY).unwrap();
+        cipher.symbols = Some(SYMBOLS.chars().collect());
+
+        let plaintext = cipher.decrypt(&mut language, "CabfCcjghvdixea");
+
+        assert_eq!(plaintext, "MEETMEATTHETREE");
+    }
+
+    #[test]
+    fn solve() {
+        let mut language = Language::from_file("examples/data/english.bin").unwrap();
+        let mut cipher = Homophonic::new(&mut language);
+        fastrand::seed(1);
+        cipher.randomize(&mut language);
+
+        let ciphertext = cipher.encrypt(&mut language, PLAINTEXT);
+        let plaintext = cipher.decrypt(&mut language, &ciphertext);
+
+        let mut solved = Homophonic::new(&mut language);
+        solved.solve(&mut language, &ciphertext);
+        let solution = solved.decrypt(&mut language, &ciphertext);
+
+        // the letters of symbols which appear only once or twice can't always be told
+        // apart, such as the single symbols of "J" and "Z"
+        let wrong = plaintext
+            .chars()
+            .zip(solution.chars())
+            .filter(|(a, b)| a != b)
+            .count();
+        assert!(wrong <= plaintext.len() / 50);
+    }
+
+    const SYMBOLS: &str = "abcdefghijklmnopqrstuvwxyzABCDEFGHIJKLMNO";
+
+    const PLAINTEXT: &str = "Call me Ishmael. Some years ago - never mind how long \
+        precisely - having little or no money in my purse, and nothing particular to \
+        interest me on shore, I thought I would sail about a little and see the watery \
+        part of the world. It is a way I have of driving off the spleen and regulating \
+        the circulation. Whenever I find myself growing grim about the mouth; whenever \
+        it is a damp, drizzly November in my soul; whenever I find myself involuntarily \
+        pausing before coffin warehouses, and bringing up the rear of every funeral I \
+        meet; and especially whenever my hypos get such an upper hand of me, that it \
+        requires a strong moral principle to prevent me from deliberately stepping into \
+        the street, and methodically knocking people's hats off.";
+}
@@ -1,3 +1,8 @@
+use crate::{
+    lang::{Language, ScoreSize},
+    util,
+};
+
 pub mod affine;
 pub mod atbash;
 pub mod baconian;
@@ -9,3 +14,130 @@ pub mod polybius_square;
 pub mod rot13;
 pub mod simple_sub;
 pub mod straddle_checkerboard;
+
+const MAX_ITERATIONS: usize = 1000;
+const MAX_STALE: usize = 100;
+const PERTURBATIONS: usize = 4;
+
+/// Scores the plaintext formed by decrypting `ciphertext` with `inv_key`, using quadgrams.
+/// Symbols that decrypt to a code point outside of the current alphabet (such as digits)
+/// are skipped, each adding `penalty` to the score, so that the solver cannot improve the
+/// score by hiding common letters.
+///
+/// # Arguments
+///
+/// * `ciphertext` A slice of ciphertext symbols
+/// * `inv_key` Maps each ciphertext symbol to a plaintext code point
+/// * `penalty` The score of each skipped symbol
+/// * `language` The current language instance
+///
+pub fn substitution_score(
+    ciphertext: &[i16],
+    inv_key: &[i16],
+    penalty: f64,
+    language: &Language,
+) -> f64 {
+    let cp_count = language.cp_count();
+    let mut skipped = 0;
+    let score = language.score_iter(
+        ciphertext
+            .iter()
+            .map(|&x| inv_key[x as usize])
+            .filter(|&cp| {
+                let valid = cp < cp_count;
+                if !valid {
+                    skipped += 1;
+                }
+                valid
+            }),
+        ScoreSize::Quadgrams,
+    );
+
+    score + skipped as f64 * penalty
+}
+
+/// The penalty used for symbols outside of the alphabet: the score of an unseen quadgram
+///
+/// # Arguments
+///
+/// * `language` The current language instance
+///
+pub fn unknown_symbol_penalty(language: &Language) -> f64 {
+    language.quadgrams.iter().cloned().fold(f64::MAX, f64::min)
+        * ScoreSize::Quadgrams.length() as f64
+}
+
+/// Solves a monoalphabetic substitution by hill climbing over swaps of the key, starting
+/// from a random key, then from small random changes to the best key found, until the
+/// score stops improving. Symbols that decrypt to a code point outside of
+/// the current alphabet (such as digits) are penalised when scoring. Returns the inverse
+/// key, which maps each ciphertext symbol to a plaintext code point.
+///
+/// # Arguments
+///
+/// * `ciphertext` A slice of ciphertext symbols, each in the range `0..num_symbols`
+/// * `num_symbols` The number of distinct symbols (at least the alphabet length)
+/// * `language` The current language instance
+///
+pub fn substitution_solve(ciphertext: &[i16], num_symbols: usize, language: &Language) -> Vec<i16> {
+    let mut inv_key = (0..num_symbols as i16).collect::<Vec<i16>>();
+    let mut best_key = inv_key.clone();
+    let mut best_score: f64 = f64::MIN;
+    let mut stale: usize = 0;
+    let penalty = unknown_symbol_penalty(language);
+
+    for iteration in 0..MAX_ITERATIONS {
+        let mut local_best_score: f64 = f64::MIN;
+
+        // start from a random key, then from small random changes to the best key
+        if iteration == 0 {
+            util::shuffle(&mut inv_key);
+        } else {
+            inv_key.clone_from(&best_key);
+            for _ in 0..PERTURBATIONS {
+                inv_key.swap(
+                    fastrand::usize(0..num_symbols),
+                    fastrand::usize(0..num_symbols),
+                );
+            }
+        }
+
+        // keep trying all possible swaps until there is no further improvement
+        let mut improved = true;
+        while improved {
+            improved = false;
+
+            for i in 0..num_symbols - 1 {
+                for j in i + 1..num_symbols {
+                    inv_key.swap(i, j);
+
+                    let score = substitution_score(ciphertext, &inv_key, penalty, language);
+
+                    if score > local_best_score {
+                        local_best_score = score;
+                        improved = true;
+                    } else {
+                        inv_key.swap(i, j);
+                    }
+                }
+            }
+        }
+
+        if local_best_score > best_score + 0.1 {
+            stale = 0;
+        } else {
+            stale += 1;
+        }
+
+        if local_best_score > best_score {
+            best_score = local_best_score;
+            best_key.clone_from(&inv_key);
+        }
+
+        if stale >= MAX_STALE {
+            break;
+        }
+    }
+
+    best_key
+}
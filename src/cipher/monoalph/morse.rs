@@ -1,11 +1,11 @@
 use crate::cipher::Asymmetric;
 use crate::lang::Language;
 
-const LETTERS: [char; 37] = [
+pub(crate) const LETTERS: [char; 37] = [
     ' ', 'a', 'b', 'c', 'd', 'e', 'f', 'g', 'h', 'i', 'j', 'k', 'l', 'm', 'n', 'o', 'p', 'q', 'r',
     's', 't', 'u', 'v', 'w', 'x', 'y', 'z', '1', '2', '3', '4', '5', '6', '7', '8', '9', '0',
 ];
-const MORSE: [&str; 37] = [
+pub(crate) const MORSE: [&str; 37] = [
     "/", ".-", "-...", "-.-.", "-..", ".", "..-.", "--.", "....", "..", ".---", "-.-", ".-..",
     "--", "-.", "---", ".--.", "--.-", ".-.", "...", "-", "..-", "...-", ".--", "-..-", "-.--",
     "--..", ".----", "..---", "...--", "....-", ".....", "-....", "--...", "---..", "----.",
@@ -34,9 +34,8 @@ impl Asymmetric for Morse {
                 word.replace('_', "-")
                     .trim()
                     .split(' ')
-                    .map(|letter| MORSE.iter().position(|&x| x == letter))
-                    .filter(|&x| x != None)
-                    .map(|pos| LETTERS[pos.unwrap()])
+                    .filter_map(|letter| MORSE.iter().position(|&x| x == letter))
+                    .map(|pos| LETTERS[pos])
                     .fold(String::new(), |mut acc, x| {
                         acc.push(x);
                         acc
@@ -1 +1,341 @@
+use crate::{
+    cipher::{self, Asymmetric, Keyed, Solve},
+    key::{IdentityKey, IoKey, Key, PolybiusSquare},
+    lang::{Language, ScoreSize},
+    util,
+};
 
+/// The Playfair square is 5x5, so uses the 25 letter alphabet
+const ALPHABET_LEN: usize = 25;
+const DIM_SIZE: usize = 5;
+
+/// How to split a plaintext digraph consisting of the same letter twice
+///
+#[derive(Clone, Copy)]
+pub enum DoubleLetters {
+    /// Insert the padding letter between the doubled letters ("LL" -> "LX L")
+    Insert,
+    /// Replace the second of the doubled letters with the padding letter ("LL" -> "LX")
+    Replace,
+}
+
+pub struct Playfair {
+    pub square: PolybiusSquare,
+    /// Separates doubled letters and pads messages of odd length
+    pub padding: char,
+    /// Used instead of `padding` when the letter to separate is the padding letter
+    pub alt_padding: char,
+    pub double_letters: DoubleLetters,
+}
+
+impl Playfair {
+    /// Gets the padding code point to place after `cp`
+    ///
+    /// # Arguments
+    ///
+    /// * `language` A [`Language`] instance
+    /// * `cp` The letter that will precede the padding
+    ///
+    fn padding_for(&self, language: &Language, cp: i16) -> i16 {
+        let padding = language.get_cp(&self.padding);
+        if cp == padding {
+            language.get_cp(&self.alt_padding)
+        } else {
+            padding
+        }
+    }
+
+    /// Splits the plaintext into digraphs, separating doubled letters and padding
+    /// the final digraph if needed
+    ///
+    /// # Arguments
+    ///
+    /// * `language` A [`Language`] instance
+    /// * `plaintext` The plaintext code points
+    ///
+    fn digraphs(&self, language: &Language, plaintext: &[i16]) -> Vec<i16> {
+        let mut result = Vec::with_capacity(plaintext.len() + plaintext.len() / 2 + 1);
+
+        let mut i = 0;
+        while i < plaintext.len() {
+            let first = plaintext[i];
+            result.push(first);
+
+            match plaintext.get(i + 1) {
+                Some(&second) if second != first => {
+                    result.push(second);
+                    i += 2;
+                }
+                Some(_) => {
+                    result.push(self.padding_for(language, first));
+                    i += match self.double_letters {
+                        DoubleLetters::Insert => 1,
+                        DoubleLetters::Replace => 2,
+                    };
+                }
+                None => {
+                    result.push(self.padding_for(language, first));
+                    i += 1;
+                }
+            }
+        }
+
+        result
+    }
+
+    /// Substitutes each digraph of `src` into `dest`. A `shift` of 1 encrypts,
+    /// and a shift of `DIM_SIZE - 1` decrypts.
+    ///
+    /// # Arguments
+    ///
+    /// * `square` The square, row by row
+    /// * `inverse` The inverse of `square`, mapping letter -> position
+    /// * `src` The digraphs to substitute
+    /// * `dest` Where to store the result, with the same length as `src`
+    /// * `shift` How far to move along a row or column
+    ///
+    fn run_digraphs(square: &[i16], inverse: &[i16], src: &[i16], dest: &mut [i16], shift: usize) {
+        for i in (0..src.len() - src.len() % 2).step_by(2) {
+            let pos1 = inverse[src[i] as usize] as usize;
+            let pos2 = inverse[src[i + 1] as usize] as usize;
+
+            let (row1, col1) = (pos1 / DIM_SIZE, pos1 % DIM_SIZE);
+            let (row2, col2) = (pos2 / DIM_SIZE, pos2 % DIM_SIZE);
+
+            let (pos1, pos2) = if row1 == row2 {
+                (
+                    row1 * DIM_SIZE + (col1 + shift) % DIM_SIZE,
+                    row2 * DIM_SIZE + (col2 + shift) % DIM_SIZE,
+                )
+            } else if col1 == col2 {
+                (
+                    ((row1 + shift) % DIM_SIZE) * DIM_SIZE + col1,
+                    ((row2 + shift) % DIM_SIZE) * DIM_SIZE + col2,
+                )
+            } else {
+                (row1 * DIM_SIZE + col2, row2 * DIM_SIZE + col1)
+            };
+
+            dest[i] = square[pos1];
+            dest[i + 1] = square[pos2];
+        }
+    }
+
+    /// Makes a random change to a square, used by the solver. Mostly swaps two
+    /// letters, but occasionally swaps or reverses rows and columns.
+    ///
+    /// # Arguments
+    ///
+    /// * `square` The square to modify
+    ///
+    fn modify_square(square: &mut [i16]) {
+        let a = fastrand::usize(0..DIM_SIZE);
+        let b = fastrand::usize(0..DIM_SIZE);
+
+        match fastrand::usize(0..50) {
+            0 => (0..DIM_SIZE).for_each(|col| square.swap(a * DIM_SIZE + col, b * DIM_SIZE + col)),
+            1 => (0..DIM_SIZE).for_each(|row| square.swap(row * DIM_SIZE + a, row * DIM_SIZE + b)),
+            2 => square.reverse(),
+            3 => square.chunks_mut(DIM_SIZE).for_each(|row| row.reverse()),
+            4 => (0..DIM_SIZE / 2).for_each(|row| {
+                (0..DIM_SIZE).for_each(|col| {
+                    square.swap(row * DIM_SIZE + col, (DIM_SIZE - 1 - row) * DIM_SIZE + col)
+                })
+            }),
+            _ => square.swap(
+                fastrand::usize(0..ALPHABET_LEN),
+                fastrand::usize(0..ALPHABET_LEN),
+            ),
+        }
+    }
+}
+
+impl Asymmetric for Playfair {
+    fn encrypt(&self, language: &mut Language, msg: &str) -> String {
+        language.set_alph_len(ALPHABET_LEN);
+
+        let plaintext = self.digraphs(language, &language.string_to_vec(msg));
+        let mut ciphertext = vec![0; plaintext.len()];
+
+        Playfair::run_digraphs(
+            self.square.as_slice(),
+            &util::invert(self.square.as_slice()),
+            &plaintext,
+            &mut ciphertext,
+            1,
+        );
+
+        language.vec_to_string(&ciphertext)
+    }
+    fn decrypt(&self, language: &mut Language, msg: &str) -> String {
+        language.set_alph_len(ALPHABET_LEN);
+
+        let mut ciphertext = language.string_to_vec(msg);
+        if ciphertext.len() % 2 == 1 {
+            ciphertext.push(language.get_cp(&self.padding));
+        }
+        let mut plaintext = vec![0; ciphertext.len()];
+
+        Playfair::run_digraphs(
+            self.square.as_slice(),
+            &util::invert(self.square.as_slice()),
+            &ciphertext,
+            &mut plaintext,
+            DIM_SIZE - 1,
+        );
+
+        language.vec_to_string(&plaintext)
+    }
+}
+
+impl Keyed for Playfair {
+    fn new(language: &mut Language) -> Playfair {
+        language.set_alph_len(ALPHABET_LEN);
+
+        let mut result = Playfair {
+            square: PolybiusSquare::identity(language),
+            padding: 'X',
+            alt_padding: 'Q',
+            double_letters: DoubleLetters::Insert,
+        };
+
+        result.square.info_mut().set("Square", "sq");
+
+        result
+    }
+    fn keys(&self) -> Vec<&dyn IoKey> {
+        vec![&self.square]
+    }
+    fn keys_mut(&mut self) -> Vec<&mut dyn IoKey> {
+        vec![&mut self.square]
+    }
+}
+
+const TEMP_STEPS: usize = 100;
+const ITERATIONS: usize = 20000;
+const MAX_RESTARTS: usize = 20;
+const MAX_REPETITIONS: usize = 2;
+
+impl Solve for Playfair {
+    fn solve(&mut self, language: &mut Language, msg: &str) {
+        language.set_alph_len(ALPHABET_LEN);
+
+        let ciphertext = language.string_to_vec(msg);
+        let mut plaintext = vec![0; ciphertext.len()];
+
+        // the starting temperature grows with the message length, since the
+        // difference in score between keys does too
+        let start_temp =
+            (10.0 + 0.087 * (ciphertext.len() as f64 - 84.0)).max(5.0) * std::f64::consts::LN_10;
+
+        let mut best_square = Vec::new();
+        let mut best_score = f64::MIN;
+        let mut repetitions = 0;
+
+        for _ in 0..MAX_RESTARTS {
+            let mut square = (0..ALPHABET_LEN as i16).collect::<Vec<_>>();
+            util::shuffle(&mut square);
+
+            let score = cipher::anneal(
+                &mut square,
+                start_temp,
+                TEMP_STEPS,
+                ITERATIONS,
+                |square| {
+                    Playfair::run_digraphs(
+                        square,
+                        &util::invert(square),
+                        &ciphertext,
+                        &mut plaintext,
+                        DIM_SIZE - 1,
+                    );
+                    // trigrams smooth out the score of keys which are close to the
+                    // solution, so that the search is less likely to get stuck
+                    language.score(&plaintext, ScoreSize::Quadgrams)
+                        + language.score(&plaintext, ScoreSize::Trigrams)
+                },
+                |square| Playfair::modify_square(square),
+            );
+
+            // the same best score from separate runs is likely to be the solution
+            if (score - best_score).abs() < 0.1 {
+                repetitions += 1;
+            } else if score > best_score {
+                repetitions = 0;
+            }
+
+            if score > best_score {
+                best_score = score;
+                best_square = square;
+            }
+
+            if repetitions >= MAX_REPETITIONS {
+                break;
+            }
+        }
+
+        self.square.set(language, best_square.as_slice()).unwrap();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn encrypt() {
+        let mut language = Language::from_file("examples/data/english.bin").unwrap();
+        let mut cipher = Playfair::new(&mut language);
+        cipher
+            .square
+            .set(&mut language, "playfair example")
+            .unwrap();
+
+        let ciphertext = cipher.encrypt(&mut language, "Hide the gold in the tree stump");
+
+        assert_eq!(ciphertext, "BMODZBXDNABEKUDMUIXMMOUVIF");
+    }
+
+    #[test]
+    fn decrypt() {
+        let mut language = Language::from_file("examples/data/english.bin").unwrap();
+        let mut cipher = Playfair::new(&mut language);
+        cipher
+            .square
+            .set(&mut language, "playfair example")
+            .unwrap();
+
+        let plaintext = cipher.decrypt(&mut language, "BMODZBXDNABEKUDMUIXMMOUVIF");
+
+        assert_eq!(plaintext, "HIDETHEGOLDINTHETREXESTUMP");
+    }
+
+    #[test]
+    fn solve() {
+        let mut language = Language::from_file("examples/data/english.bin").unwrap();
+        let mut cipher = Playfair::new(&mut language);
+        cipher
+            .square
+            .set(&mut language, "playfair example")
+            .unwrap();
+
+        let ciphertext = cipher.encrypt(&mut language, PLAINTEXT);
+        let plaintext = cipher.decrypt(&mut language, &ciphertext);
+
+        fastrand::seed(1);
+        let mut solved = Playfair::new(&mut language);
+        solved.solve(&mut language, &ciphertext);
+
+        assert_eq!(solved.decrypt(&mut language, &ciphertext), plaintext);
+    }
+
+    const PLAINTEXT: &str = "It was the best of times, it was the worst of times, it was \
+        the age of wisdom, it was the age of foolishness, it was the epoch of belief, it \
+        was the epoch of incredulity, it was the season of Light, it was the season of \
+        Darkness, it was the spring of hope, it was the winter of despair, we had \
+        everything before us, we had nothing before us, we were all going direct to \
+        Heaven, we were all going direct the other way - in short, the period was so far \
+        like the present period, that some of its noisiest authorities insisted on its \
+        being received, for good or for evil, in the superlative degree of comparison \
+        only.";
+}
@@ -1,6 +1,150 @@
-// use crate::{Asymmetric, Keyed, Solve};
-// use crate::key::PolybiusSquare;
+use crate::{
+    cipher::{Asymmetric, Keyed, Solve},
+    key::{IdentityKey, IoKey, Key, PolybiusSquare},
+    lang::Language,
+};
 
-// pub struct PolybiusSquare {
-//     pub square:
-// }
+/// The Polybius square uses the 25 letter alphabet by default
+const ALPHABET_LEN: usize = 25;
+
+/// The Polybius square cipher. Each letter is replaced by the labels of its row and
+/// column in the square, written as a pair separated by spaces from the next pair. A
+/// 6x6 square (See [`PolybiusSquare::resize`]) also holds the digits `0-9`.
+///
+/// Since each pair of labels always stands for the same letter, the solver treats the
+/// pairs as the symbols of a monoalphabetic substitution. The labels of the square
+/// should be set to those used by the ciphertext before solving.
+///
+pub struct PolybiusSquareCipher {
+    pub square: PolybiusSquare,
+}
+
+impl PolybiusSquareCipher {
+    /// Converts the label pairs of a message to the index of their cell in the square,
+    /// ignoring any chars which are not labels. An unknown pair gives `None`.
+    ///
+    /// # Arguments
+    ///
+    /// * `msg` The message to convert
+    ///
+    fn msg_to_cells(&self, msg: &str) -> Vec<Option<usize>> {
+        let labels = msg
+            .chars()
+            .filter(|ch| {
+                self.square.row_labels().contains(ch) || self.square.col_labels().contains(ch)
+            })
+            .collect::<Vec<_>>();
+
+        labels
+            .chunks_exact(2)
+            .map(|pair| {
+                let row = self
+                    .square
+                    .row_labels()
+                    .iter()
+                    .position(|&x| x == pair[0])?;
+                let col = self
+                    .square
+                    .col_labels()
+                    .iter()
+                    .position(|&x| x == pair[1])?;
+                Some(row * self.square.dim_size() + col)
+            })
+            .collect()
+    }
+}
+
+impl Asymmetric for PolybiusSquareCipher {
+    fn encrypt(&self, language: &mut Language, msg: &str) -> String {
+        language.set_alph_len(self.square.num_letters());
+
+        msg.chars()
+            .filter_map(|ch| self.square.char_to_cp(language, ch))
+            .map(|cp| {
+                let (row, col) = self.square.encrypt(cp);
+                format!("{}{}", row, col)
+            })
+            .collect::<Vec<_>>()
+            .join(" ")
+    }
+    fn decrypt(&self, language: &mut Language, msg: &str) -> String {
+        language.set_alph_len(self.square.num_letters());
+
+        let square = self.square.as_slice();
+        self.msg_to_cells(msg)
+            .into_iter()
+            .flatten()
+            .map(|cell| self.square.cp_to_char(language, square[cell]))
+            .collect()
+    }
+}
+
+impl Keyed for PolybiusSquareCipher {
+    fn new(language: &mut Language) -> PolybiusSquareCipher {
+        language.set_alph_len(ALPHABET_LEN);
+
+        let mut result = PolybiusSquareCipher {
+            square: PolybiusSquare::identity(language),
+        };
+
+        result.square.info_mut().set("Square", "sq");
+
+        result
+    }
+    fn keys(&self) -> Vec<&dyn IoKey> {
+        vec![&self.square]
+    }
+    fn keys_mut(&mut self) -> Vec<&mut dyn IoKey> {
+        vec![&mut self.square]
+    }
+}
+
+impl Solve for PolybiusSquareCipher {
+    fn solve(&mut self, language: &mut Language, msg: &str) {
+        language.set_alph_len(self.square.num_letters());
+
+        let cells = self
+            .msg_to_cells(msg)
+            .into_iter()
+            .flatten()
+            .map(|cell| cell as i16)
+            .collect::<Vec<_>>();
+        let num_cells = self.square.as_slice().len();
+
+        // maps each cell to a code point, where the code points after the letters are
+        // digits, so the inverse key is the square itself
+        let square = super::substitution_solve(&cells, num_cells, language);
+
+        self.square.set(language, square.as_slice()).unwrap();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn encrypt() {
+        let mut language = Language::from_file("examples/data/english.bin").unwrap();
+        let mut cipher = PolybiusSquareCipher::new(&mut language);
+        cipher.square.set(&mut language, "zebras").unwrap();
+
+        let ciphertext = cipher.encrypt(&mut language, "Flee at once");
+
+        assert_eq!(ciphertext, "24 34 12 12 15 45 42 41 22 12");
+    }
+
+    #[test]
+    fn decrypt() {
+        let mut language = Language::from_file("examples/data/english.bin").unwrap();
+        let mut cipher = PolybiusSquareCipher::new(&mut language);
+        cipher
+            .square
+            .set_key_str(&mut language, "zebras:ABCDE:VWXYZ")
+            .unwrap();
+
+        let plaintext = cipher.decrypt(&mut language, "BY CY AW AW AZ DZ DW DV BW AW");
+
+        assert_eq!(plaintext, "FLEEATONCE");
+    }
+}
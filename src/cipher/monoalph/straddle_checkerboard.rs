@@ -1 +1,116 @@
+use crate::{
+    cipher::{Asymmetric, Keyed},
+    key::{IdentityKey, IoKey, StraddleCheckerboard},
+    lang::Language,
+    util,
+};
 
+/// The straddling checkerboard uses the 26 letter alphabet
+const ALPHABET_LEN: usize = 26;
+/// The ciphertext is written in groups of 5 digits
+const GROUP_LEN: usize = 5;
+/// The number of times each digit of a number is written
+const FIGURE_REPEATS: usize = 3;
+
+/// The straddling checkerboard cipher. Each letter in the top row of the checkerboard
+/// is replaced by the digit labelling its column, and each letter in the rows beneath
+/// by the digit labelling its row followed by the digit labelling its column. Full
+/// stops are kept, and numbers are written between two figure shifts with each digit
+/// repeated three times. The ciphertext is written in groups of 5 digits.
+///
+pub struct StraddleCheckerboardCipher {
+    pub checkerboard: StraddleCheckerboard,
+}
+
+impl Asymmetric for StraddleCheckerboardCipher {
+    fn encrypt(&self, language: &mut Language, msg: &str) -> String {
+        language.set_alph_len(ALPHABET_LEN);
+
+        let digits = self
+            .checkerboard
+            .encode_msg(language, msg, FIGURE_REPEATS)
+            .into_iter()
+            .map(|digit| digit.to_string())
+            .collect::<String>();
+
+        util::blocks(&digits, GROUP_LEN, " ")
+    }
+    fn decrypt(&self, language: &mut Language, msg: &str) -> String {
+        language.set_alph_len(ALPHABET_LEN);
+
+        let digits = msg
+            .chars()
+            .filter_map(|ch| ch.to_digit(10))
+            .map(|digit| digit as i16)
+            .collect::<Vec<_>>();
+
+        self.checkerboard
+            .decode_msg(language, &digits, FIGURE_REPEATS)
+    }
+}
+
+impl Keyed for StraddleCheckerboardCipher {
+    fn new(language: &mut Language) -> StraddleCheckerboardCipher {
+        language.set_alph_len(ALPHABET_LEN);
+
+        let mut result = StraddleCheckerboardCipher {
+            checkerboard: StraddleCheckerboard::identity(language),
+        };
+
+        result.checkerboard.info_mut().set("Checkerboard", "cb");
+
+        result
+    }
+    fn keys(&self) -> Vec<&dyn IoKey> {
+        vec![&self.checkerboard]
+    }
+    fn keys_mut(&mut self) -> Vec<&mut dyn IoKey> {
+        vec![&mut self.checkerboard]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::key::Key;
+
+    const CHECKERBOARD: &str = "ET AON RISBCDFGHJKLMPQ/UVWXYZ.";
+
+    #[test]
+    fn encrypt() {
+        let mut language = Language::from_file("examples/data/english.bin").unwrap();
+        let mut cipher = StraddleCheckerboardCipher::new(&mut language);
+        cipher
+            .checkerboard
+            .set(&mut language, CHECKERBOARD)
+            .unwrap();
+
+        assert_eq!(
+            cipher.encrypt(&mut language, "Attack at dawn"),
+            "31132 12731 22365 5"
+        );
+        assert_eq!(
+            cipher.encrypt(&mut language, "Attack at 0600"),
+            "31132 12731 62000 66600 00006 2"
+        );
+    }
+
+    #[test]
+    fn decrypt() {
+        let mut language = Language::from_file("examples/data/english.bin").unwrap();
+        let mut cipher = StraddleCheckerboardCipher::new(&mut language);
+        cipher
+            .checkerboard
+            .set(&mut language, CHECKERBOARD)
+            .unwrap();
+
+        assert_eq!(
+            cipher.decrypt(&mut language, "3113212731223655"),
+            "ATTACKATDAWN"
+        );
+        assert_eq!(
+            cipher.decrypt(&mut language, "31132 12731 62000 66600 00006 2"),
+            "ATTACKAT0600"
+        );
+    }
+}
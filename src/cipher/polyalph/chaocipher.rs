@@ -1 +1,371 @@
+use crate::{
+    cipher::{Asymmetric, Keyed},
+    error::{Error, Result},
+    key::{Alphabet, IdentityKey, IoKey, Key},
+    lang::Language,
+};
 
+/// Chaocipher uses the 26 letter alphabet
+const ALPHABET_LEN: usize = 26;
+/// The position opposite the zenith (position 0) of each alphabet
+const NADIR: usize = ALPHABET_LEN / 2;
+
+/// Chaocipher, as invented by John F. Byrne. Each plaintext letter is found in the
+/// right alphabet, and the ciphertext letter is the letter in the same position of the
+/// left alphabet. Both alphabets are then permuted:
+///
+/// * The left alphabet is rotated to bring the ciphertext letter to the zenith. The
+///   letter after the zenith is removed, the letters up to the nadir are shifted along,
+///   and the removed letter is placed at the nadir.
+/// * The right alphabet is rotated to bring the letter after the plaintext letter to
+///   the zenith. The letter two places after the zenith is removed, the letters up to
+///   the nadir are shifted along, and the removed letter is placed at the nadir.
+///
+/// Since the alphabets change with each letter, [`Chaocipher::encrypt_mut`] and
+/// [`Chaocipher::decrypt_mut`] keep the alphabets reached at the end of the message.
+///
+pub struct Chaocipher {
+    /// The ciphertext alphabet
+    pub left: Alphabet,
+    /// The plaintext alphabet
+    pub right: Alphabet,
+}
+
+impl Chaocipher {
+    /// Permutes the left alphabet after a letter
+    ///
+    /// # Arguments
+    ///
+    /// * `left` The left alphabet
+    /// * `pos` The position of the ciphertext letter
+    ///
+    fn permute_left<T: Copy>(left: &mut [T], pos: usize) {
+        left.rotate_left(pos);
+
+        let removed = left[1];
+        left.copy_within(2..=NADIR, 1);
+        left[NADIR] = removed;
+    }
+
+    /// Permutes the right alphabet after a letter
+    ///
+    /// # Arguments
+    ///
+    /// * `right` The right alphabet
+    /// * `pos` The position of the plaintext letter
+    ///
+    fn permute_right<T: Copy>(right: &mut [T], pos: usize) {
+        right.rotate_left((pos + 1) % ALPHABET_LEN);
+
+        let removed = right[2];
+        right.copy_within(3..=NADIR, 2);
+        right[NADIR] = removed;
+    }
+
+    /// Encrypts or decrypts the letters of `msg`, permuting the alphabets after each
+    /// letter. Any other chars are unchanged.
+    ///
+    /// # Arguments
+    ///
+    /// * `language` A [`Language`] instance
+    /// * `left` The left alphabet
+    /// * `right` The right alphabet
+    /// * `msg` The message to encrypt or decrypt
+    /// * `decrypt` Whether to decrypt
+    ///
+    fn run(
+        language: &Language,
+        left: &mut [i16],
+        right: &mut [i16],
+        msg: &str,
+        decrypt: bool,
+    ) -> String {
+        msg.chars()
+            .map(|ch| {
+                if language.is_letter(&ch) {
+                    let cp = language.get_cp(&ch);
+                    let (src, dest) = if decrypt {
+                        (&*left, &*right)
+                    } else {
+                        (&*right, &*left)
+                    };
+                    let pos = src.iter().position(|&x| x == cp).unwrap();
+                    let result = dest[pos];
+
+                    Chaocipher::permute_left(left, pos);
+                    Chaocipher::permute_right(right, pos);
+
+                    language.update_cp(&ch, result)
+                } else {
+                    ch
+                }
+            })
+            .collect()
+    }
+
+    /// Encrypts `msg`, leaving the alphabets as they are after the final letter
+    ///
+    /// # Arguments
+    ///
+    /// * `language` A [`Language`] instance
+    /// * `msg` The message to encrypt
+    ///
+    pub fn encrypt_mut(&mut self, language: &mut Language, msg: &str) -> String {
+        self.run_mut(language, msg, false)
+    }
+
+    /// Decrypts `msg`, leaving the alphabets as they are after the final letter
+    ///
+    /// # Arguments
+    ///
+    /// * `language` A [`Language`] instance
+    /// * `msg` The message to decrypt
+    ///
+    pub fn decrypt_mut(&mut self, language: &mut Language, msg: &str) -> String {
+        self.run_mut(language, msg, true)
+    }
+
+    fn run_mut(&mut self, language: &mut Language, msg: &str, decrypt: bool) -> String {
+        language.set_alph_len(ALPHABET_LEN);
+
+        let mut left = self.left.as_slice().to_vec();
+        let mut right = self.right.as_slice().to_vec();
+        let result = Chaocipher::run(language, &mut left, &mut right, msg, decrypt);
+
+        self.left.set(language, left.as_slice()).unwrap();
+        self.right.set(language, right.as_slice()).unwrap();
+
+        result
+    }
+
+    /// Finds the starting alphabets from a known plaintext and its ciphertext, by
+    /// placing each pair of letters in the alphabets and backtracking when a pair
+    /// contradicts the letters already placed. Rotating both alphabets by the same
+    /// amount gives the same ciphertext, so the first pair is placed at the zenith.
+    /// Letters which are not needed to produce the ciphertext are placed in
+    /// alphabetical order.
+    ///
+    /// # Arguments
+    ///
+    /// * `language` A [`Language`] instance
+    /// * `plaintext` The known plaintext
+    /// * `ciphertext` The ciphertext of `plaintext`
+    ///
+    pub fn recover_keys(
+        &mut self,
+        language: &mut Language,
+        plaintext: &str,
+        ciphertext: &str,
+    ) -> Result<()> {
+        language.set_alph_len(ALPHABET_LEN);
+
+        let plaintext = language.string_to_vec(plaintext);
+        let ciphertext = language.string_to_vec(ciphertext);
+
+        if plaintext.len() != ciphertext.len() {
+            return Err(Error::InsufficientInputLen {
+                expected: plaintext.len(),
+                actual: ciphertext.len(),
+            });
+        }
+
+        let (left, right) = Chaocipher::place_pairs(
+            &plaintext,
+            &ciphertext,
+            vec![None; ALPHABET_LEN],
+            vec![None; ALPHABET_LEN],
+        )
+        .ok_or_else(|| Error::InvalidKeyFmt {
+            expected: "A plaintext and the ciphertext it encrypts to".to_string(),
+            actual: "No alphabets produce the ciphertext".to_string(),
+        })?;
+
+        self.left
+            .set(language, Chaocipher::fill_unknown(&left).as_slice())
+            .unwrap();
+        self.right
+            .set(language, Chaocipher::fill_unknown(&right).as_slice())
+            .unwrap();
+
+        Ok(())
+    }
+
+    /// Places the first pair of letters in the partial alphabets, then recurses on the
+    /// rest of the message. Returns the partial alphabets at the start of the message,
+    /// or `None` if the pairs cannot be placed.
+    ///
+    /// # Arguments
+    ///
+    /// * `plaintext` The remaining plaintext
+    /// * `ciphertext` The remaining ciphertext
+    /// * `left` The partial left alphabet
+    /// * `right` The partial right alphabet
+    ///
+    #[allow(clippy::type_complexity)]
+    fn place_pairs(
+        plaintext: &[i16],
+        ciphertext: &[i16],
+        left: Vec<Option<i16>>,
+        right: Vec<Option<i16>>,
+    ) -> Option<(Vec<Option<i16>>, Vec<Option<i16>>)> {
+        let (pt, ct) = match (plaintext.first(), ciphertext.first()) {
+            (Some(&pt), Some(&ct)) => (pt, ct),
+            _ => return Some((left, right)),
+        };
+
+        let pt_pos = right.iter().position(|&x| x == Some(pt));
+        let ct_pos = left.iter().position(|&x| x == Some(ct));
+
+        let candidates = match (pt_pos, ct_pos) {
+            (Some(pt_pos), Some(ct_pos)) if pt_pos == ct_pos => vec![pt_pos],
+            (Some(pos), None) if left[pos].is_none() => vec![pos],
+            (None, Some(pos)) if right[pos].is_none() => vec![pos],
+            (None, None) if left.iter().all(Option::is_none) => vec![0],
+            (None, None) => (0..ALPHABET_LEN)
+                .filter(|&pos| left[pos].is_none() && right[pos].is_none())
+                .collect(),
+            _ => vec![],
+        };
+
+        candidates.into_iter().find_map(|pos| {
+            let mut next_left = left.clone();
+            let mut next_right = right.clone();
+            next_left[pos] = Some(ct);
+            next_right[pos] = Some(pt);
+
+            Chaocipher::permute_left(&mut next_left, pos);
+            Chaocipher::permute_right(&mut next_right, pos);
+
+            // undo the permutations of this letter on the alphabets found for the rest
+            // of the message, by tracking where each position moves to
+            let mut moves = (0..ALPHABET_LEN).collect::<Vec<_>>();
+            let mut left_moves = moves.clone();
+            Chaocipher::permute_left(&mut left_moves, pos);
+            Chaocipher::permute_right(&mut moves, pos);
+            let right_moves = moves;
+
+            let (rest_left, rest_right) =
+                Chaocipher::place_pairs(&plaintext[1..], &ciphertext[1..], next_left, next_right)?;
+
+            let mut start_left = vec![None; ALPHABET_LEN];
+            let mut start_right = vec![None; ALPHABET_LEN];
+            for i in 0..ALPHABET_LEN {
+                start_left[left_moves[i]] = rest_left[i];
+                start_right[right_moves[i]] = rest_right[i];
+            }
+
+            Some((start_left, start_right))
+        })
+    }
+
+    /// Fills the unknown positions of a partial alphabet with the missing letters, in
+    /// alphabetical order
+    ///
+    /// # Arguments
+    ///
+    /// * `partial` The partial alphabet
+    ///
+    fn fill_unknown(partial: &[Option<i16>]) -> Vec<i16> {
+        let mut missing = (0..ALPHABET_LEN as i16).filter(|cp| !partial.contains(&Some(*cp)));
+        partial
+            .iter()
+            .map(|x| x.unwrap_or_else(|| missing.next().unwrap()))
+            .collect()
+    }
+}
+
+impl Asymmetric for Chaocipher {
+    fn encrypt(&self, language: &mut Language, msg: &str) -> String {
+        language.set_alph_len(ALPHABET_LEN);
+        Chaocipher::run(
+            language,
+            &mut self.left.as_slice().to_vec(),
+            &mut self.right.as_slice().to_vec(),
+            msg,
+            false,
+        )
+    }
+    fn decrypt(&self, language: &mut Language, msg: &str) -> String {
+        language.set_alph_len(ALPHABET_LEN);
+        Chaocipher::run(
+            language,
+            &mut self.left.as_slice().to_vec(),
+            &mut self.right.as_slice().to_vec(),
+            msg,
+            true,
+        )
+    }
+}
+
+impl Keyed for Chaocipher {
+    fn new(language: &mut Language) -> Chaocipher {
+        language.set_alph_len(ALPHABET_LEN);
+
+        let mut result = Chaocipher {
+            left: Alphabet::identity(language),
+            right: Alphabet::identity(language),
+        };
+
+        result.left.info_mut().set("Left alphabet", "left");
+        result.right.info_mut().set("Right alphabet", "right");
+
+        result
+    }
+    fn keys(&self) -> Vec<&dyn IoKey> {
+        vec![&self.left, &self.right]
+    }
+    fn keys_mut(&mut self) -> Vec<&mut dyn IoKey> {
+        vec![&mut self.left, &mut self.right]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const LEFT: &str = "HXUCZVAMDSLKPEFJRIGTWOBNYQ";
+    const RIGHT: &str = "PTLNBQDEOYSFAVZKGJRIHWXUMC";
+
+    #[test]
+    fn encrypt() {
+        let mut language = Language::from_file("examples/data/english.bin").unwrap();
+        let mut cipher = Chaocipher::new(&mut language);
+        cipher.left.set(&mut language, LEFT).unwrap();
+        cipher.right.set(&mut language, RIGHT).unwrap();
+
+        let ciphertext = cipher.encrypt(&mut language, "WELLDONEISBETTERTHANWELLSAID");
+
+        assert_eq!(ciphertext, "OAHQHCNYNXTSZJRRHJBYHQKSOUJY");
+    }
+
+    #[test]
+    fn decrypt() {
+        let mut language = Language::from_file("examples/data/english.bin").unwrap();
+        let mut cipher = Chaocipher::new(&mut language);
+        cipher.left.set(&mut language, LEFT).unwrap();
+        cipher.right.set(&mut language, RIGHT).unwrap();
+
+        let plaintext = cipher.decrypt(&mut language, "OAHQHCNYNXTSZJRRHJBYHQKSOUJY");
+
+        assert_eq!(plaintext, "WELLDONEISBETTERTHANWELLSAID");
+    }
+
+    #[test]
+    fn recover_keys() {
+        let mut language = Language::from_file("examples/data/english.bin").unwrap();
+        let mut cipher = Chaocipher::new(&mut language);
+        cipher.left.set(&mut language, LEFT).unwrap();
+        cipher.right.set(&mut language, RIGHT).unwrap();
+
+        let plaintext = "ALLGOODQQUICKBROWNFOXESJUMPOVERLAZYDOGTOSAVETHEIRPARTYWITHOUTREGARD\
+            FORTHENUMBEROFMISPRINTSORERRORSINTHEMESSAGETHATTHEYAREREQUIREDTOSEND";
+        let ciphertext = cipher.encrypt(&mut language, plaintext);
+
+        let mut recovered = Chaocipher::new(&mut language);
+        recovered
+            .recover_keys(&mut language, plaintext, &ciphertext)
+            .unwrap();
+
+        assert_eq!(recovered.encrypt(&mut language, plaintext), ciphertext);
+    }
+}
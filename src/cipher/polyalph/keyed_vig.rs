@@ -1,14 +1,29 @@
 use crate::{
-    cipher::{Asymmetric, Keyed},
-    key::{Alphabet, IdentityKey, IoKey, KeyedVigSquare, Keyword, VigSquare},
+    cipher::{Asymmetric, Keyed, Solve},
+    key::{Alphabet, IdentityKey, IoKey, Key, KeyedVigSquare, Keyword, VigSquare},
     lang::Language,
+    util,
 };
 
+/// The keyed vigenere square uses the 26 letter alphabet
+const ALPHABET_LEN: usize = 26;
+
+/// The Keyed Vigenere cipher (Quagmire III). Each letter is shifted by the keyword as in
+/// the Vigenere cipher, then substituted using the keyed alphabet.
+///
+/// The solver finds the period from the periodic index of coincedence, then anneals
+/// over the keyed alphabet. Undoing the alphabet leaves a Vigenere cipher, so for each
+/// alphabet the shift of every column is chosen to best match the letter frequencies
+/// (See [`super::quagmire_solve`]).
+///
 pub struct KeyedVigenere {
     pub keyword: Keyword,
     pub alphabet: Alphabet,
 }
 
+/// The Quagmire III cipher is the Keyed Vigenere cipher
+pub type QuagmireIII = KeyedVigenere;
+
 impl Asymmetric for KeyedVigenere {
     fn encrypt(&self, language: &mut Language, msg: &str) -> String {
         let mut square = KeyedVigSquare::identity(language);
@@ -71,3 +86,50 @@ impl Keyed for KeyedVigenere {
         vec![&mut self.keyword, &mut self.alphabet]
     }
 }
+
+impl Solve for KeyedVigenere {
+    fn solve(&mut self, language: &mut Language, msg: &str) {
+        language.set_alph_len(ALPHABET_LEN);
+
+        let ciphertext = language.string_to_vec(msg);
+        let period = super::find_period(&ciphertext, language);
+
+        let (inverse, shifts) = super::quagmire_solve(&ciphertext, period, language);
+
+        self.alphabet
+            .set(language, util::invert(&inverse).as_slice())
+            .unwrap();
+        self.keyword.set(language, shifts.as_slice()).unwrap();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn solve() {
+        let mut language = Language::from_file("examples/data/english.bin").unwrap();
+        let mut cipher = KeyedVigenere::new(&mut language);
+        cipher.alphabet.set(&mut language, "kryptos").unwrap();
+        cipher.keyword.set(&mut language, "palimpsest").unwrap();
+
+        let ciphertext = cipher.encrypt(&mut language, PLAINTEXT);
+        let plaintext = cipher.decrypt(&mut language, &ciphertext);
+
+        fastrand::seed(1);
+        let mut solved = KeyedVigenere::new(&mut language);
+        solved.solve(&mut language, &ciphertext);
+
+        assert_eq!(solved.decrypt(&mut language, &ciphertext), plaintext);
+    }
+
+    const PLAINTEXT: &str = "In my younger and more vulnerable years my father gave me some \
+        advice that I've been turning over in my mind ever since. Whenever you feel like \
+        criticizing any one, he told me, just remember that all the people in this world \
+        haven't had the advantages that you've had. He didn't say any more, but we've always \
+        been unusually communicative in a reserved way, and I understood that he meant a \
+        great deal more than that. In consequence, I'm inclined to reserve all judgments, a \
+        habit that has opened up many curious natures to me and also made me the victim of \
+        not a few veteran bores.";
+}
@@ -1,4 +1,8 @@
-use crate::lang::{Language, ScoreSize};
+use crate::{
+    cipher::{self, monoalph},
+    lang::{Language, ScoreSize},
+    util,
+};
 
 pub mod autokey;
 pub mod beaufort;
@@ -7,8 +11,49 @@ pub mod chaocipher;
 pub mod classic_vig;
 pub mod keyed_vig;
 pub mod porta;
+pub mod quagmire1;
+pub mod quagmire2;
+pub mod quagmire4;
 
 const KEY_LEN_MAX_TEST: usize = 30;
+/// The fewest letters in each column when finding the period
+const MIN_COLUMN_LEN: usize = 15;
+/// The proportion of the best periodic IOC needed for a period to be chosen
+const PERIOD_IOC_RATIO: f64 = 0.9;
+
+/// The Quagmire ciphers use the 26 letter alphabet
+const QUAGMIRE_ALPHABET_LEN: usize = 26;
+/// The number of passes over the columns when aligning them
+const ALIGN_PASSES: usize = 2;
+
+/// Finds the most likely period of a periodic polyalphabetic cipher. Each column of a
+/// period is enciphered with a single alphabet, so has a high index of coincedence.
+/// Multiples of the period score just as well, so the shortest period scoring close to
+/// the best is chosen.
+///
+/// # Arguments
+///
+/// * `ciphertext` A slice of ciphertext code points
+/// * `language` The current language instance
+///
+pub fn find_period(ciphertext: &[i16], language: &Language) -> usize {
+    let max_period = KEY_LEN_MAX_TEST
+        .min(ciphertext.len() / MIN_COLUMN_LEN)
+        .max(1);
+    if ciphertext.len() < 2 {
+        return 1;
+    }
+
+    let iocs = (1..=max_period)
+        .map(|period| language.periodic_ioc(ciphertext, period))
+        .collect::<Vec<_>>();
+    let best_ioc = iocs.iter().copied().fold(f64::MIN, f64::max);
+
+    iocs.iter()
+        .position(|&ioc| ioc >= PERIOD_IOC_RATIO * best_ioc)
+        .unwrap()
+        + 1
+}
 
 /// Solve a vigenere cipher
 ///
@@ -94,3 +139,318 @@ where
 
     best_key
 }
+
+/// Counts each letter in each column of a periodic ciphertext
+///
+/// # Arguments
+///
+/// * `ciphertext` A slice of ciphertext code points
+/// * `period` The number of columns
+///
+pub fn column_counts(ciphertext: &[i16], period: usize) -> Vec<[f64; QUAGMIRE_ALPHABET_LEN]> {
+    let mut counts = vec![[0.0; QUAGMIRE_ALPHABET_LEN]; period];
+    for (idx, &cp) in ciphertext.iter().enumerate() {
+        counts[idx % period][cp as usize] += 1.0;
+    }
+    counts
+}
+
+/// Finds the shift of each column which best lines up its letter counts with those of
+/// the other columns, for a cipher where each column is a shifted copy of a single
+/// substitution. Subtracting the shift of a column from its letters gives the letters
+/// of the single substitution. Returns the shifts and the index of coincedence of the
+/// aligned letters.
+///
+/// # Arguments
+///
+/// * `counts` The count of each letter in each column
+///
+pub fn align_columns(counts: &[[f64; QUAGMIRE_ALPHABET_LEN]]) -> (Vec<i16>, f64) {
+    let mut shifts = vec![0; counts.len()];
+    let mut total = [0.0; QUAGMIRE_ALPHABET_LEN];
+
+    let shifted = |column: &[f64; QUAGMIRE_ALPHABET_LEN], shift: usize, letter: usize| {
+        column[(letter + shift) % QUAGMIRE_ALPHABET_LEN]
+    };
+
+    // each pass after the first aligns a column with all of the other columns
+    for pass in 0..ALIGN_PASSES {
+        for (column, shift) in counts.iter().zip(shifts.iter_mut()) {
+            if pass > 0 {
+                for (letter, count) in total.iter_mut().enumerate() {
+                    *count -= shifted(column, *shift as usize, letter);
+                }
+            }
+
+            let mut best_overlap = 0.0;
+            *shift = 0;
+            for candidate in 0..QUAGMIRE_ALPHABET_LEN {
+                let overlap = total
+                    .iter()
+                    .enumerate()
+                    .map(|(letter, count)| count * shifted(column, candidate, letter))
+                    .sum::<f64>();
+                if overlap > best_overlap {
+                    best_overlap = overlap;
+                    *shift = candidate as i16;
+                }
+            }
+
+            for (letter, count) in total.iter_mut().enumerate() {
+                *count += shifted(column, *shift as usize, letter);
+            }
+        }
+    }
+
+    let len = total.iter().sum::<f64>();
+    let ioc = total.iter().map(|count| count * (count - 1.0)).sum::<f64>() / (len * (len - 1.0));
+
+    (shifts, ioc)
+}
+
+/// Undoes the ciphertext alphabet of a periodic ciphertext, then lines up its columns
+/// (See [`align_columns`]), leaving the letters of a single substitution. Returns the
+/// shift of each column and the substituted letters.
+///
+/// # Arguments
+///
+/// * `ciphertext` A slice of ciphertext code points
+/// * `period` The number of columns
+/// * `inverse` The inverse of the ciphertext alphabet
+///
+pub fn align_text(ciphertext: &[i16], period: usize, inverse: &[i16]) -> (Vec<i16>, Vec<i16>) {
+    let substituted = ciphertext
+        .iter()
+        .map(|&cp| inverse[cp as usize])
+        .collect::<Vec<_>>();
+    let (shifts, _) = align_columns(&column_counts(&substituted, period));
+
+    let text = substituted
+        .iter()
+        .enumerate()
+        .map(|(idx, &cp)| util::modulo(cp - shifts[idx % period], QUAGMIRE_ALPHABET_LEN as i16))
+        .collect();
+
+    (shifts, text)
+}
+
+/// Solves a periodic cipher whose columns are shifted copies of a single substitution,
+/// once the ciphertext alphabet is known (as in the Quagmire I and IV ciphers). The
+/// columns are lined up (See [`align_text`]) and the substitution is solved, giving the
+/// plaintext alphabet. A column with few letters can be lined up wrongly by its letter
+/// counts, leaving its letters garbled, so every shift of each column is then tried with
+/// that alphabet using quadgrams, and the substitution is solved again if any shift
+/// changed.
+///
+/// Returns the shift of each column and the plaintext alphabet.
+///
+/// # Arguments
+///
+/// * `ciphertext` A slice of ciphertext code points
+/// * `period` The number of columns
+/// * `inverse` The inverse of the ciphertext alphabet
+/// * `language` The current language instance
+///
+pub fn solve_aligned(
+    ciphertext: &[i16],
+    period: usize,
+    inverse: &[i16],
+    language: &Language,
+) -> (Vec<i16>, Vec<i16>) {
+    let (mut shifts, mut text) = align_text(ciphertext, period, inverse);
+    let mut pt_alphabet = monoalph::substitution_solve(&text, QUAGMIRE_ALPHABET_LEN, language);
+
+    let substituted = ciphertext
+        .iter()
+        .map(|&cp| inverse[cp as usize])
+        .collect::<Vec<_>>();
+    let mut plaintext = vec![0; ciphertext.len()];
+    let mut realigned = false;
+
+    for col in 0..period {
+        let mut best_score = f64::MIN;
+        let mut best_shift = shifts[col];
+
+        for shift in 0..QUAGMIRE_ALPHABET_LEN as i16 {
+            shifts[col] = shift;
+            for (idx, (cp, &ct)) in plaintext.iter_mut().zip(&substituted).enumerate() {
+                *cp = pt_alphabet[util::modulo(
+                    ct - shifts[idx % period],
+                    QUAGMIRE_ALPHABET_LEN as i16,
+                ) as usize];
+            }
+
+            let score = language.score(&plaintext, ScoreSize::Quadgrams);
+            if score > best_score {
+                best_score = score;
+                best_shift = shift;
+            }
+        }
+
+        realigned |= shifts[col] != best_shift;
+        shifts[col] = best_shift;
+    }
+
+    if realigned {
+        for (idx, (cp, &ct)) in text.iter_mut().zip(&substituted).enumerate() {
+            *cp = util::modulo(ct - shifts[idx % period], QUAGMIRE_ALPHABET_LEN as i16);
+        }
+        pt_alphabet = monoalph::substitution_solve(&text, QUAGMIRE_ALPHABET_LEN, language);
+    }
+
+    (shifts, pt_alphabet)
+}
+
+/// Finds the shift of each column which best matches the letter frequencies of the
+/// language, once the ciphertext has been substituted using `ct_inverse`. Returns the
+/// shifts and the total log likelihood of the columns using those shifts.
+///
+/// # Arguments
+///
+/// * `counts` The count of each ciphertext letter in each column
+/// * `ct_inverse` The inverse of the ciphertext alphabet
+/// * `log_probabilities` The log probability of the letter at each position of the
+///   plaintext alphabet, repeated twice
+///
+fn best_shifts(
+    counts: &[[f64; QUAGMIRE_ALPHABET_LEN]],
+    ct_inverse: &[i16],
+    log_probabilities: &[f64],
+) -> (Vec<i16>, f64) {
+    let mut total = 0.0;
+    let shifts = counts
+        .iter()
+        .map(|column| {
+            let mut substituted = [0.0; QUAGMIRE_ALPHABET_LEN];
+            for (cp, &count) in column.iter().enumerate() {
+                substituted[ct_inverse[cp] as usize] = count;
+            }
+
+            let likelihoods = (0..QUAGMIRE_ALPHABET_LEN).map(|shift| {
+                log_probabilities[QUAGMIRE_ALPHABET_LEN - shift..]
+                    .iter()
+                    .zip(&substituted)
+                    .map(|(log_probability, count)| log_probability * count)
+                    .sum::<f64>()
+            });
+
+            let (shift, likelihood) = likelihoods
+                .enumerate()
+                .max_by(|a, b| a.1.total_cmp(&b.1))
+                .unwrap();
+            total += likelihood;
+            shift as i16
+        })
+        .collect();
+
+    (shifts, total)
+}
+
+const QUAGMIRE_TEMP_STEPS: usize = 100;
+const QUAGMIRE_ITERATIONS: usize = 5000;
+const QUAGMIRE_MAX_RESTARTS: usize = 5;
+/// The weight of the log likelihood of the letter frequencies in the score
+const UNIGRAM_WEIGHT: f64 = 4.0;
+
+/// Solves a periodic cipher where the plaintext is found by undoing a keyed ciphertext
+/// alphabet, then subtracting the shift of each column (as in the Keyed Vigenere and
+/// Quagmire II ciphers). Anneals over the ciphertext alphabet, choosing the shifts to
+/// best match the letter frequencies, and scores the decryption using quadgrams along
+/// with how well the columns match the letter frequencies, since quadgrams alone vary
+/// too sharply as the shifts change. Finally, each pair of letters of the best alphabet
+/// is swapped while the score improves.
+///
+/// Returns the inverse of the ciphertext alphabet and the shift of each column.
+///
+/// # Arguments
+///
+/// * `ciphertext` A slice of ciphertext code points
+/// * `period` The number of columns
+/// * `language` The current language instance
+///
+pub fn quagmire_solve(
+    ciphertext: &[i16],
+    period: usize,
+    language: &Language,
+) -> (Vec<i16>, Vec<i16>) {
+    let counts = column_counts(ciphertext, period);
+    let letter_log_probabilities = language.unigram_probabilities[..QUAGMIRE_ALPHABET_LEN]
+        .iter()
+        .map(|p| p.ln())
+        .collect::<Vec<_>>();
+    let log_probabilities = [letter_log_probabilities.as_slice(); 2].concat();
+
+    let mut plaintext = vec![0; ciphertext.len()];
+    // the difference in score between keys grows with the message length
+    let start_temp = (ciphertext.len() as f64 / 15.0).max(5.0);
+
+    let mut best_key = Vec::new();
+    let mut best_score = f64::MIN;
+
+    let mut score = |ct_inverse: &[i16]| {
+        let (shifts, likelihood) = best_shifts(&counts, ct_inverse, &log_probabilities);
+        for (idx, (cp, &ct)) in plaintext.iter_mut().zip(ciphertext).enumerate() {
+            *cp = util::modulo(
+                ct_inverse[ct as usize] - shifts[idx % period],
+                QUAGMIRE_ALPHABET_LEN as i16,
+            );
+        }
+
+        language.score(&plaintext, ScoreSize::Quadgrams) + UNIGRAM_WEIGHT * likelihood
+    };
+
+    for _ in 0..QUAGMIRE_MAX_RESTARTS {
+        // the inverse of the ciphertext alphabet
+        let mut key = (0..QUAGMIRE_ALPHABET_LEN as i16).collect::<Vec<_>>();
+        util::shuffle(&mut key);
+
+        let score = cipher::anneal(
+            &mut key,
+            start_temp,
+            QUAGMIRE_TEMP_STEPS,
+            QUAGMIRE_ITERATIONS,
+            |key| score(key),
+            |key| {
+                key.swap(
+                    fastrand::usize(0..QUAGMIRE_ALPHABET_LEN),
+                    fastrand::usize(0..QUAGMIRE_ALPHABET_LEN),
+                );
+            },
+        );
+
+        // the same best score from separate runs is likely to be the solution
+        if (score - best_score).abs() < 0.1 {
+            break;
+        }
+
+        if score > best_score {
+            best_score = score;
+            best_key = key;
+        }
+    }
+
+    // the annealing can leave a few letters of the alphabet out of place, so swap each
+    // pair of letters while the score improves
+    let mut improved = true;
+    while improved {
+        improved = false;
+
+        for i in 0..QUAGMIRE_ALPHABET_LEN {
+            for j in i + 1..QUAGMIRE_ALPHABET_LEN {
+                best_key.swap(i, j);
+                let score = score(&best_key);
+
+                if score > best_score + 1e-6 {
+                    best_score = score;
+                    improved = true;
+                } else {
+                    best_key.swap(i, j);
+                }
+            }
+        }
+    }
+
+    let (shifts, _) = best_shifts(&counts, &best_key, &log_probabilities);
+
+    (best_key, shifts)
+}
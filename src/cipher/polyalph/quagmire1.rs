@@ -0,0 +1,185 @@
+use crate::{
+    cipher::{Asymmetric, Keyed, Solve},
+    key::{Alphabet, IdentityKey, IoKey, Key, Keyword, QuagmireSquare, VigSquare},
+    lang::Language,
+    util,
+};
+
+/// The Quagmire ciphers use the 26 letter alphabet
+const ALPHABET_LEN: usize = 26;
+
+/// The Quagmire I cipher. The plaintext alphabet is keyed and the ciphertext alphabet
+/// is straight. For each letter of the indicator keyword, the ciphertext alphabet is
+/// slid beneath the plaintext alphabet so that the indicator letter is beneath
+/// plaintext `A` (See [`QuagmireSquare`]).
+///
+/// Since the ciphertext alphabet is straight, the columns of each period are shifted
+/// copies of a single substitution, so the solver finds the period and lines up the
+/// columns by their letter frequencies. What remains is a monoalphabetic substitution,
+/// which gives the plaintext alphabet, after which the shift of each column is checked
+/// using quadgrams (See [`super::solve_aligned`]).
+///
+pub struct QuagmireI {
+    pub indicator: Keyword,
+    pub pt_alphabet: Alphabet,
+}
+
+impl QuagmireI {
+    /// Creates the tableau using the current alphabets
+    ///
+    /// # Arguments
+    ///
+    /// * `language` A [`Language`] instance
+    ///
+    fn square(&self, language: &mut Language) -> QuagmireSquare {
+        let mut square = QuagmireSquare::identity(language);
+        let straight = (0..ALPHABET_LEN as i16).collect::<Vec<_>>();
+        square.set_alphabets(self.pt_alphabet.as_slice(), &straight);
+        square
+    }
+}
+
+impl Asymmetric for QuagmireI {
+    fn encrypt(&self, language: &mut Language, msg: &str) -> String {
+        let square = self.square(language);
+
+        let mut count = 0;
+        msg.chars()
+            .map(|c| {
+                if language.is_letter(&c) {
+                    let new_cp = square.encrypt(
+                        language.get_cp(&c),
+                        self.indicator.at(count % self.indicator.len()),
+                    );
+                    count += 1;
+                    language.update_cp(&c, new_cp)
+                } else {
+                    c
+                }
+            })
+            .collect()
+    }
+    fn decrypt(&self, language: &mut Language, msg: &str) -> String {
+        let square = self.square(language);
+
+        let mut count = 0;
+        msg.chars()
+            .map(|c| {
+                if language.is_letter(&c) {
+                    let new_cp = square.decrypt(
+                        self.indicator.at(count % self.indicator.len()),
+                        language.get_cp(&c),
+                    );
+                    count += 1;
+                    language.update_cp(&c, new_cp)
+                } else {
+                    c
+                }
+            })
+            .collect()
+    }
+}
+
+impl Keyed for QuagmireI {
+    fn new(language: &mut Language) -> QuagmireI {
+        let mut result = QuagmireI {
+            indicator: Keyword::identity(language),
+            pt_alphabet: Alphabet::identity(language),
+        };
+
+        result.indicator.info_mut().set("Indicator", "ind");
+        result
+            .pt_alphabet
+            .info_mut()
+            .set("Plaintext alphabet", "pt_alph");
+
+        result
+    }
+    fn keys(&self) -> Vec<&dyn IoKey> {
+        vec![&self.indicator, &self.pt_alphabet]
+    }
+    fn keys_mut(&mut self) -> Vec<&mut dyn IoKey> {
+        vec![&mut self.indicator, &mut self.pt_alphabet]
+    }
+}
+
+impl Solve for QuagmireI {
+    fn solve(&mut self, language: &mut Language, msg: &str) {
+        language.set_alph_len(ALPHABET_LEN);
+
+        let ciphertext = language.string_to_vec(msg);
+        let period = super::find_period(&ciphertext, language);
+
+        let straight = (0..ALPHABET_LEN as i16).collect::<Vec<_>>();
+        let (shifts, pt_alphabet) = super::solve_aligned(&ciphertext, period, &straight, language);
+
+        // the position of plaintext `A` is beneath each indicator letter
+        let offset = util::invert(&pt_alphabet)[0];
+        let indicator = shifts
+            .iter()
+            .map(|&shift| util::modulo(shift + offset, ALPHABET_LEN as i16))
+            .collect::<Vec<_>>();
+
+        self.pt_alphabet
+            .set(language, pt_alphabet.as_slice())
+            .unwrap();
+        self.indicator.set(language, indicator.as_slice()).unwrap();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn cipher(language: &mut Language) -> QuagmireI {
+        let mut cipher = QuagmireI::new(language);
+        cipher.pt_alphabet.set(language, "springfever").unwrap();
+        cipher.indicator.set(language, "flower").unwrap();
+        cipher
+    }
+
+    #[test]
+    fn encrypt() {
+        let mut language = Language::from_file("examples/data/english.bin").unwrap();
+        let cipher = cipher(&mut language);
+
+        let ciphertext = cipher.encrypt(&mut language, "THEQUICKBROWNFOXJUMPSOVERTHELAZYDOG");
+
+        assert_eq!(ciphertext, "QPMGQLHRPPNEAIXKJDNDFFDPYWSULRVARFA");
+    }
+
+    #[test]
+    fn decrypt() {
+        let mut language = Language::from_file("examples/data/english.bin").unwrap();
+        let cipher = cipher(&mut language);
+
+        let plaintext = cipher.decrypt(&mut language, "QPMGQLHRPPNEAIXKJDNDFFDPYWSULRVARFA");
+
+        assert_eq!(plaintext, "THEQUICKBROWNFOXJUMPSOVERTHELAZYDOG");
+    }
+
+    #[test]
+    fn solve() {
+        let mut language = Language::from_file("examples/data/english.bin").unwrap();
+        let cipher = cipher(&mut language);
+
+        let ciphertext = cipher.encrypt(&mut language, PLAINTEXT);
+        let plaintext = cipher.decrypt(&mut language, &ciphertext);
+
+        fastrand::seed(1);
+        let mut solved = QuagmireI::new(&mut language);
+        solved.solve(&mut language, &ciphertext);
+
+        assert_eq!(solved.decrypt(&mut language, &ciphertext), plaintext);
+    }
+
+    const PLAINTEXT: &str = "Marley was dead: to begin with. There is no doubt whatever \
+        about that. The register of his burial was signed by the clergyman, the clerk, the \
+        undertaker, and the chief mourner. Scrooge signed it: and Scrooge's name was good \
+        upon 'Change, for anything he chose to put his hand to. Old Marley was as dead as a \
+        door-nail. Mind! I don't mean to say that I know, of my own knowledge, what there is \
+        particularly dead about a door-nail. I might have been inclined, myself, to regard a \
+        coffin-nail as the deadest piece of ironmongery in the trade. But the wisdom of our \
+        ancestors is in the simile; and my unhallowed hands shall not disturb it, or the \
+        Country's done for.";
+}
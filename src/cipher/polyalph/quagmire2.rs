@@ -0,0 +1,181 @@
+use crate::{
+    cipher::{Asymmetric, Keyed, Solve},
+    key::{Alphabet, IdentityKey, IoKey, Key, Keyword, QuagmireSquare, VigSquare},
+    lang::Language,
+    util,
+};
+
+/// The Quagmire ciphers use the 26 letter alphabet
+const ALPHABET_LEN: usize = 26;
+
+/// The Quagmire II cipher. The plaintext alphabet is straight and the ciphertext
+/// alphabet is keyed. For each letter of the indicator keyword, the ciphertext alphabet
+/// is slid beneath the plaintext alphabet so that the indicator letter is beneath
+/// plaintext `A` (See [`QuagmireSquare`]).
+///
+/// The solver finds the period from the periodic index of coincedence, then anneals
+/// over the ciphertext alphabet, choosing the shift of each column to best match the
+/// letter frequencies (See [`super::quagmire_solve`]).
+///
+pub struct QuagmireII {
+    pub indicator: Keyword,
+    pub ct_alphabet: Alphabet,
+}
+
+impl QuagmireII {
+    /// Creates the tableau using the current alphabets
+    ///
+    /// # Arguments
+    ///
+    /// * `language` A [`Language`] instance
+    ///
+    fn square(&self, language: &mut Language) -> QuagmireSquare {
+        let mut square = QuagmireSquare::identity(language);
+        let straight = (0..ALPHABET_LEN as i16).collect::<Vec<_>>();
+        square.set_alphabets(&straight, self.ct_alphabet.as_slice());
+        square
+    }
+}
+
+impl Asymmetric for QuagmireII {
+    fn encrypt(&self, language: &mut Language, msg: &str) -> String {
+        let square = self.square(language);
+
+        let mut count = 0;
+        msg.chars()
+            .map(|c| {
+                if language.is_letter(&c) {
+                    let new_cp = square.encrypt(
+                        language.get_cp(&c),
+                        self.indicator.at(count % self.indicator.len()),
+                    );
+                    count += 1;
+                    language.update_cp(&c, new_cp)
+                } else {
+                    c
+                }
+            })
+            .collect()
+    }
+    fn decrypt(&self, language: &mut Language, msg: &str) -> String {
+        let square = self.square(language);
+
+        let mut count = 0;
+        msg.chars()
+            .map(|c| {
+                if language.is_letter(&c) {
+                    let new_cp = square.decrypt(
+                        self.indicator.at(count % self.indicator.len()),
+                        language.get_cp(&c),
+                    );
+                    count += 1;
+                    language.update_cp(&c, new_cp)
+                } else {
+                    c
+                }
+            })
+            .collect()
+    }
+}
+
+impl Keyed for QuagmireII {
+    fn new(language: &mut Language) -> QuagmireII {
+        let mut result = QuagmireII {
+            indicator: Keyword::identity(language),
+            ct_alphabet: Alphabet::identity(language),
+        };
+
+        result.indicator.info_mut().set("Indicator", "ind");
+        result
+            .ct_alphabet
+            .info_mut()
+            .set("Ciphertext alphabet", "ct_alph");
+
+        result
+    }
+    fn keys(&self) -> Vec<&dyn IoKey> {
+        vec![&self.indicator, &self.ct_alphabet]
+    }
+    fn keys_mut(&mut self) -> Vec<&mut dyn IoKey> {
+        vec![&mut self.indicator, &mut self.ct_alphabet]
+    }
+}
+
+impl Solve for QuagmireII {
+    fn solve(&mut self, language: &mut Language, msg: &str) {
+        language.set_alph_len(ALPHABET_LEN);
+
+        let ciphertext = language.string_to_vec(msg);
+        let period = super::find_period(&ciphertext, language);
+
+        let (ct_inverse, shifts) = super::quagmire_solve(&ciphertext, period, language);
+        let ct_alphabet = util::invert(&ct_inverse);
+
+        // the indicator letter is beneath plaintext `A`
+        let indicator = shifts
+            .iter()
+            .map(|&shift| ct_alphabet[shift as usize])
+            .collect::<Vec<_>>();
+
+        self.ct_alphabet
+            .set(language, ct_alphabet.as_slice())
+            .unwrap();
+        self.indicator.set(language, indicator.as_slice()).unwrap();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn cipher(language: &mut Language) -> QuagmireII {
+        let mut cipher = QuagmireII::new(language);
+        cipher.ct_alphabet.set(language, "springfever").unwrap();
+        cipher.indicator.set(language, "flower").unwrap();
+        cipher
+    }
+
+    #[test]
+    fn encrypt() {
+        let mut language = Language::from_file("examples/data/english.bin").unwrap();
+        let cipher = cipher(&mut language);
+
+        let ciphertext = cipher.encrypt(&mut language, "THEQUICKBROWNFOXJUMPSOVERTHELAZYDOG");
+
+        assert_eq!(ciphertext, "ZXWDPBVSQHUYQUFQLWOGBBRFXAZSORGJUBH");
+    }
+
+    #[test]
+    fn decrypt() {
+        let mut language = Language::from_file("examples/data/english.bin").unwrap();
+        let cipher = cipher(&mut language);
+
+        let plaintext = cipher.decrypt(&mut language, "ZXWDPBVSQHUYQUFQLWOGBBRFXAZSORGJUBH");
+
+        assert_eq!(plaintext, "THEQUICKBROWNFOXJUMPSOVERTHELAZYDOG");
+    }
+
+    #[test]
+    fn solve() {
+        let mut language = Language::from_file("examples/data/english.bin").unwrap();
+        let cipher = cipher(&mut language);
+
+        let ciphertext = cipher.encrypt(&mut language, PLAINTEXT);
+        let plaintext = cipher.decrypt(&mut language, &ciphertext);
+
+        fastrand::seed(1);
+        let mut solved = QuagmireII::new(&mut language);
+        solved.solve(&mut language, &ciphertext);
+
+        assert_eq!(solved.decrypt(&mut language, &ciphertext), plaintext);
+    }
+
+    const PLAINTEXT: &str = "You will rejoice to hear that no disaster has accompanied the \
+        commencement of an enterprise which you have regarded with such evil forebodings. I \
+        arrived here yesterday, and my first task is to assure my dear sister of my welfare \
+        and increasing confidence in the success of my undertaking. I am already far north \
+        of London, and as I walk in the streets of Petersburgh, I feel a cold northern \
+        breeze play upon my cheeks, which braces my nerves and fills me with delight. Do you \
+        understand this feeling? This breeze, which has travelled from the regions towards \
+        which I am advancing, gives me a foretaste of those icy climes.";
+}
@@ -0,0 +1,310 @@
+use crate::{
+    cipher::{self, Asymmetric, Keyed, Solve},
+    key::{Alphabet, IdentityKey, IoKey, Key, Keyword, QuagmireSquare, VigSquare},
+    lang::Language,
+    util,
+};
+
+/// The Quagmire ciphers use the 26 letter alphabet
+const ALPHABET_LEN: usize = 26;
+
+/// The longest ciphertext keyword tried by the solver
+const KEYWORD_LEN_MAX: usize = 14;
+const START_TEMP: f64 = 0.0004;
+const TEMP_STEPS: usize = 100;
+const ITERATIONS: usize = 1000;
+const MAX_RESTARTS: usize = 12;
+
+/// The Quagmire IV cipher. The plaintext and ciphertext alphabets are both keyed, and
+/// are unrelated. For each letter of the indicator keyword, the ciphertext alphabet is
+/// slid beneath the plaintext alphabet so that the indicator letter is beneath
+/// plaintext `A` (See [`QuagmireSquare`]).
+///
+/// The solver finds the period from the periodic index of coincedence, and assumes the
+/// ciphertext alphabet is made from a keyword (See [`util::fill_alphabet_from_start`]).
+/// Once the ciphertext alphabet is undone, the columns are shifted copies of a single
+/// substitution, so it anneals over the keyword, scoring the index of coincedence of the
+/// letters and pairs of letters once the columns are lined up. What remains is a
+/// monoalphabetic substitution, which gives the plaintext alphabet (See
+/// [`super::solve_aligned`]).
+///
+pub struct QuagmireIV {
+    pub indicator: Keyword,
+    pub pt_alphabet: Alphabet,
+    pub ct_alphabet: Alphabet,
+}
+
+impl QuagmireIV {
+    /// Creates the tableau using the current alphabets
+    ///
+    /// # Arguments
+    ///
+    /// * `language` A [`Language`] instance
+    ///
+    fn square(&self, language: &mut Language) -> QuagmireSquare {
+        let mut square = QuagmireSquare::identity(language);
+        square.set_alphabets(self.pt_alphabet.as_slice(), self.ct_alphabet.as_slice());
+        square
+    }
+}
+
+impl Asymmetric for QuagmireIV {
+    fn encrypt(&self, language: &mut Language, msg: &str) -> String {
+        let square = self.square(language);
+
+        let mut count = 0;
+        msg.chars()
+            .map(|c| {
+                if language.is_letter(&c) {
+                    let new_cp = square.encrypt(
+                        language.get_cp(&c),
+                        self.indicator.at(count % self.indicator.len()),
+                    );
+                    count += 1;
+                    language.update_cp(&c, new_cp)
+                } else {
+                    c
+                }
+            })
+            .collect()
+    }
+    fn decrypt(&self, language: &mut Language, msg: &str) -> String {
+        let square = self.square(language);
+
+        let mut count = 0;
+        msg.chars()
+            .map(|c| {
+                if language.is_letter(&c) {
+                    let new_cp = square.decrypt(
+                        self.indicator.at(count % self.indicator.len()),
+                        language.get_cp(&c),
+                    );
+                    count += 1;
+                    language.update_cp(&c, new_cp)
+                } else {
+                    c
+                }
+            })
+            .collect()
+    }
+}
+
+impl Keyed for QuagmireIV {
+    fn new(language: &mut Language) -> QuagmireIV {
+        let mut result = QuagmireIV {
+            indicator: Keyword::identity(language),
+            pt_alphabet: Alphabet::identity(language),
+            ct_alphabet: Alphabet::identity(language),
+        };
+
+        result.indicator.info_mut().set("Indicator", "ind");
+        result
+            .pt_alphabet
+            .info_mut()
+            .set("Plaintext alphabet", "pt_alph");
+        result
+            .ct_alphabet
+            .info_mut()
+            .set("Ciphertext alphabet", "ct_alph");
+
+        result
+    }
+    fn keys(&self) -> Vec<&dyn IoKey> {
+        vec![&self.indicator, &self.pt_alphabet, &self.ct_alphabet]
+    }
+    fn keys_mut(&mut self) -> Vec<&mut dyn IoKey> {
+        vec![
+            &mut self.indicator,
+            &mut self.pt_alphabet,
+            &mut self.ct_alphabet,
+        ]
+    }
+}
+
+impl Solve for QuagmireIV {
+    fn solve(&mut self, language: &mut Language, msg: &str) {
+        language.set_alph_len(ALPHABET_LEN);
+
+        let ciphertext = language.string_to_vec(msg);
+        let period = super::find_period(&ciphertext, language);
+
+        let ct_alphabet =
+            util::fill_alphabet_from_start(&keyword_solve(&ciphertext, period), ALPHABET_LEN);
+        let ct_inverse = util::invert(&ct_alphabet);
+        let (shifts, pt_alphabet) =
+            super::solve_aligned(&ciphertext, period, &ct_inverse, language);
+
+        // the position of plaintext `A` is beneath each indicator letter
+        let offset = util::invert(&pt_alphabet)[0];
+        let indicator = shifts
+            .iter()
+            .map(|&shift| ct_alphabet[util::modulo(shift + offset, ALPHABET_LEN as i16) as usize])
+            .collect::<Vec<_>>();
+
+        self.pt_alphabet
+            .set(language, pt_alphabet.as_slice())
+            .unwrap();
+        self.ct_alphabet
+            .set(language, ct_alphabet.as_slice())
+            .unwrap();
+        self.indicator.set(language, indicator.as_slice()).unwrap();
+    }
+}
+
+/// Finds the index of coincedence of the letters plus that of the pairs of letters of a
+/// single substitution, which is high when the ciphertext alphabet is right and its
+/// columns line up. Pairs of letters are needed since the letters alone match just as
+/// well for many wrong alphabets on a short message.
+///
+/// # Arguments
+///
+/// * `text` A slice of the letters of the substitution
+///
+fn substitution_ioc(text: &[i16]) -> f64 {
+    let mut counts = [0.0; ALPHABET_LEN];
+    let mut pair_counts = [0.0; ALPHABET_LEN * ALPHABET_LEN];
+    for &cp in text {
+        counts[cp as usize] += 1.0;
+    }
+    for pair in text.windows(2) {
+        pair_counts[pair[0] as usize * ALPHABET_LEN + pair[1] as usize] += 1.0;
+    }
+
+    let ioc = |counts: &[f64]| {
+        let len = counts.iter().sum::<f64>();
+        counts
+            .iter()
+            .map(|count| count * (count - 1.0))
+            .sum::<f64>()
+            / (len * (len - 1.0))
+    };
+    ioc(&counts) + ioc(&pair_counts)
+}
+
+/// Anneals over the keyword of the ciphertext alphabet, lining up the columns for each
+/// keyword and scoring the result (See [`substitution_ioc`]). Returns the best keyword.
+///
+/// # Arguments
+///
+/// * `ciphertext` A slice of ciphertext code points
+/// * `period` The number of columns
+///
+fn keyword_solve(ciphertext: &[i16], period: usize) -> Vec<i16> {
+    let score = |keyword: &Vec<i16>| {
+        let ct_alphabet = util::fill_alphabet_from_start(keyword, ALPHABET_LEN);
+        let (_, text) = super::align_text(ciphertext, period, &util::invert(&ct_alphabet));
+        substitution_ioc(&text)
+    };
+
+    let mut best_keyword = Vec::new();
+    let mut best_score = f64::MIN;
+
+    for _ in 0..MAX_RESTARTS {
+        let mut keyword = (0..fastrand::usize(3..KEYWORD_LEN_MAX))
+            .map(|_| fastrand::i16(0..ALPHABET_LEN as i16))
+            .collect::<Vec<_>>();
+
+        let score = cipher::anneal(
+            &mut keyword,
+            START_TEMP,
+            TEMP_STEPS,
+            ITERATIONS,
+            score,
+            |keyword| match fastrand::usize(0..20) {
+                // insert a letter
+                0..=2 if keyword.len() < KEYWORD_LEN_MAX => {
+                    keyword.insert(
+                        fastrand::usize(0..=keyword.len()),
+                        fastrand::i16(0..ALPHABET_LEN as i16),
+                    );
+                }
+                // remove a letter
+                3..=5 if keyword.len() > 1 => {
+                    keyword.remove(fastrand::usize(0..keyword.len()));
+                }
+                // swap two letters
+                6..=7 => {
+                    let len = keyword.len();
+                    keyword.swap(fastrand::usize(0..len), fastrand::usize(0..len));
+                }
+                // change a letter
+                _ => {
+                    let idx = fastrand::usize(0..keyword.len());
+                    keyword[idx] = fastrand::i16(0..ALPHABET_LEN as i16);
+                }
+            },
+        );
+
+        // the same best score from separate runs is likely to be the solution
+        if (score - best_score).abs() < 1e-6 {
+            break;
+        }
+
+        if score > best_score {
+            best_score = score;
+            best_keyword = keyword;
+        }
+    }
+
+    best_keyword
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn cipher(language: &mut Language) -> QuagmireIV {
+        let mut cipher = QuagmireIV::new(language);
+        cipher.pt_alphabet.set(language, "senior").unwrap();
+        cipher.ct_alphabet.set(language, "percussion").unwrap();
+        cipher.indicator.set(language, "extra").unwrap();
+        cipher
+    }
+
+    #[test]
+    fn encrypt() {
+        let mut language = Language::from_file("examples/data/english.bin").unwrap();
+        let cipher = cipher(&mut language);
+
+        let ciphertext = cipher.encrypt(&mut language, "THEQUICKBROWNFOXJUMPSOVERTHELAZYDOG");
+
+        assert_eq!(ciphertext, "HCJHXYZRCNZGKIOMUNFTVVAXNHCJDATJXPH");
+    }
+
+    #[test]
+    fn decrypt() {
+        let mut language = Language::from_file("examples/data/english.bin").unwrap();
+        let cipher = cipher(&mut language);
+
+        let plaintext = cipher.decrypt(&mut language, "HCJHXYZRCNZGKIOMUNFTVVAXNHCJDATJXPH");
+
+        assert_eq!(plaintext, "THEQUICKBROWNFOXJUMPSOVERTHELAZYDOG");
+    }
+
+    #[test]
+    fn solve() {
+        let mut language = Language::from_file("examples/data/english.bin").unwrap();
+        let cipher = cipher(&mut language);
+
+        let ciphertext = cipher.encrypt(&mut language, PLAINTEXT);
+        let plaintext = cipher.decrypt(&mut language, &ciphertext);
+
+        fastrand::seed(1);
+        let mut solved = QuagmireIV::new(&mut language);
+        solved.solve(&mut language, &ciphertext);
+
+        assert_eq!(solved.decrypt(&mut language, &ciphertext), plaintext);
+    }
+
+    const PLAINTEXT: &str = "Squire Trelawney, Dr. Livesey, and the rest of these gentlemen \
+        having asked me to write down the whole particulars about Treasure Island, from the \
+        beginning to the end, keeping nothing back but the bearings of the island, and that \
+        only because there is still treasure not yet lifted, I take up my pen in the year of \
+        grace 17-, and go back to the time when my father kept the Admiral Benbow inn and the \
+        brown old seaman with the sabre cut first took up his lodging under our roof. I \
+        remember him as if it were yesterday, as he came plodding to the inn door, his \
+        sea-chest following behind him in a hand-barrow - a tall, strong, heavy, nut-brown \
+        man, his tarry pigtail falling over the shoulder of his soiled blue coat, his hands \
+        ragged and scarred, with black, broken nails, and the sabre cut across one cheek, a \
+        dirty, livid white.";
+}
@@ -1 +1,93 @@
+use crate::{
+    cipher::{Asymmetric, Keyed, Solve},
+    key::{IdentityKey, IoKey, Key, Keyword, PolybiusSquare},
+    lang::Language,
+};
 
+/// The ADFGVX square is 6x6, holding 26 letters and 10 digits
+const ALPHABET_LEN: usize = 26;
+const LABELS: [char; 6] = ['A', 'D', 'F', 'G', 'V', 'X'];
+
+pub struct Adfgvx {
+    pub square: PolybiusSquare,
+    pub keyword: Keyword,
+}
+
+impl Asymmetric for Adfgvx {
+    fn encrypt(&self, language: &mut Language, msg: &str) -> String {
+        super::fractionated_encrypt(language, &self.square, &self.keyword, &LABELS, msg)
+    }
+    fn decrypt(&self, language: &mut Language, msg: &str) -> String {
+        super::fractionated_decrypt(language, &self.square, &self.keyword, &LABELS, msg)
+    }
+}
+
+impl Keyed for Adfgvx {
+    fn new(language: &mut Language) -> Adfgvx {
+        language.set_alph_len(ALPHABET_LEN);
+
+        let mut result = Adfgvx {
+            square: PolybiusSquare::identity(language),
+            keyword: Keyword::identity(language),
+        };
+
+        result.square.info_mut().set("Square", "sq");
+        result.keyword.info_mut().set("Keyword", "kw");
+
+        result
+    }
+    fn keys(&self) -> Vec<&dyn IoKey> {
+        vec![&self.square, &self.keyword]
+    }
+    fn keys_mut(&mut self) -> Vec<&mut dyn IoKey> {
+        vec![&mut self.square, &mut self.keyword]
+    }
+}
+
+impl Solve for Adfgvx {
+    fn solve(&mut self, language: &mut Language, msg: &str) {
+        language.set_alph_len(ALPHABET_LEN);
+
+        let ciphertext = super::label_coords(&LABELS, msg);
+        let (key_order, square) = super::fractionated_solve(&ciphertext, LABELS.len(), language);
+
+        let key = key_order.iter().map(|&x| x as i16).collect::<Vec<_>>();
+        self.keyword.set(language, key.as_slice()).unwrap();
+        self.square.set(language, square.as_slice()).unwrap();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn encrypt() {
+        let mut language = Language::from_file("examples/data/english.bin").unwrap();
+        let mut cipher = Adfgvx::new(&mut language);
+        cipher
+            .square
+            .set(&mut language, "na1c3h8tb2ome5wrpd4f6g7i9j0klqsuvxyz")
+            .unwrap();
+        cipher.keyword.set(&mut language, "privacy").unwrap();
+
+        let ciphertext = cipher.encrypt(&mut language, "attack at 1200am");
+
+        assert_eq!(ciphertext, "DGDDDAGDDGAFADDFDADVDVFAADVX");
+    }
+
+    #[test]
+    fn decrypt() {
+        let mut language = Language::from_file("examples/data/english.bin").unwrap();
+        let mut cipher = Adfgvx::new(&mut language);
+        cipher
+            .square
+            .set(&mut language, "na1c3h8tb2ome5wrpd4f6g7i9j0klqsuvxyz")
+            .unwrap();
+        cipher.keyword.set(&mut language, "privacy").unwrap();
+
+        let plaintext = cipher.decrypt(&mut language, "DGDDDAGDDGAFADDFDADVDVFAADVX");
+
+        assert_eq!(plaintext, "ATTACKAT1200AM");
+    }
+}
@@ -1 +1,102 @@
+use crate::{
+    cipher::{Asymmetric, Keyed, Solve},
+    key::{IdentityKey, IoKey, Key, Keyword, PolybiusSquare},
+    lang::Language,
+};
 
+/// The ADFGX square is 5x5, so uses the 25 letter alphabet
+const ALPHABET_LEN: usize = 25;
+const LABELS: [char; 5] = ['A', 'D', 'F', 'G', 'X'];
+
+pub struct Adfgx {
+    pub square: PolybiusSquare,
+    pub keyword: Keyword,
+}
+
+impl Asymmetric for Adfgx {
+    fn encrypt(&self, language: &mut Language, msg: &str) -> String {
+        super::fractionated_encrypt(language, &self.square, &self.keyword, &LABELS, msg)
+    }
+    fn decrypt(&self, language: &mut Language, msg: &str) -> String {
+        super::fractionated_decrypt(language, &self.square, &self.keyword, &LABELS, msg)
+    }
+}
+
+impl Keyed for Adfgx {
+    fn new(language: &mut Language) -> Adfgx {
+        language.set_alph_len(ALPHABET_LEN);
+
+        let mut result = Adfgx {
+            square: PolybiusSquare::identity(language),
+            keyword: Keyword::identity(language),
+        };
+
+        result.square.info_mut().set("Square", "sq");
+        result.keyword.info_mut().set("Keyword", "kw");
+
+        result
+    }
+    fn keys(&self) -> Vec<&dyn IoKey> {
+        vec![&self.square, &self.keyword]
+    }
+    fn keys_mut(&mut self) -> Vec<&mut dyn IoKey> {
+        vec![&mut self.square, &mut self.keyword]
+    }
+}
+
+impl Solve for Adfgx {
+    fn solve(&mut self, language: &mut Language, msg: &str) {
+        language.set_alph_len(ALPHABET_LEN);
+
+        let ciphertext = super::label_coords(&LABELS, msg);
+        let (key_order, square) = super::fractionated_solve(&ciphertext, LABELS.len(), language);
+
+        let key = key_order.iter().map(|&x| x as i16).collect::<Vec<_>>();
+        self.keyword.set(language, key.as_slice()).unwrap();
+        self.square.set(language, square.as_slice()).unwrap();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn encrypt() {
+        let mut language = Language::from_file("examples/data/english.bin").unwrap();
+        let mut cipher = Adfgx::new(&mut language);
+        cipher
+            .square
+            .set(&mut language, "btalpdhozkqfvsngicuxmrewy")
+            .unwrap();
+        cipher.keyword.set(&mut language, "cargo").unwrap();
+
+        let ciphertext = cipher.encrypt(&mut language, "attack at once");
+
+        assert_eq!(ciphertext, "FAXDFADDDGDGFFFAFAXAFAFX");
+    }
+
+    #[test]
+    fn decrypt() {
+        let mut language = Language::from_file("examples/data/english.bin").unwrap();
+        let mut cipher = Adfgx::new(&mut language);
+        cipher
+            .square
+            .set(&mut language, "btalpdhozkqfvsngicuxmrewy")
+            .unwrap();
+        cipher.keyword.set(&mut language, "cargo").unwrap();
+
+        let plaintext = cipher.decrypt(&mut language, "FAXDFADDDGDGFFFAFAXAFAFX");
+
+        assert_eq!(plaintext, "ATTACKATONCE");
+    }
+
+    #[test]
+    fn solve_short() {
+        let mut language = Language::from_file("examples/data/english.bin").unwrap();
+        let mut cipher = Adfgx::new(&mut language);
+
+        // too short to test any key length, so the key is left unsolved
+        cipher.solve(&mut language, "FA");
+    }
+}
@@ -1 +1,418 @@
+use crate::{
+    cipher::{self, monoalph, Asymmetric, Keyed, Solve},
+    error::{Error, Result},
+    key::{IdentityKey, IoKey, Key, Number, PolybiusSquare},
+    lang::Language,
+    util,
+};
 
+/// The default Bifid square is 5x5, so uses the 25 letter alphabet
+const ALPHABET_LEN: usize = 25;
+/// A 6x6 square holds the 26 letter alphabet and the digits
+const ALPHABET_LEN_6X6: usize = 26;
+const MAX_PERIOD: i16 = 20;
+
+/// The Bifid cipher, as defined by the ACA. The row and column coordinates of each
+/// letter in a block of `period` letters are written beneath each other, then read off
+/// in pairs to find the ciphertext letters. A period of 0 treats the whole message as a
+/// single block.
+///
+pub struct Bifid {
+    pub square: PolybiusSquare,
+    pub period: Number,
+}
+
+impl Bifid {
+    /// Replaces the square with an identity square of the given size. A 5x5 square
+    /// holds the 25 letter alphabet, and a 6x6 square holds the 26 letter alphabet
+    /// followed by the digits `0-9`.
+    ///
+    /// # Arguments
+    ///
+    /// * `language` A [`Language`] instance
+    /// * `dim_size` The number of rows (and columns) in the square, either 5 or 6
+    ///
+    pub fn set_dim_size(&mut self, language: &mut Language, dim_size: usize) -> Result<()> {
+        let alphabet_len = match dim_size {
+            5 => ALPHABET_LEN,
+            6 => ALPHABET_LEN_6X6,
+            _ => {
+                return Err(Error::InvalidKeyFmt {
+                    expected: "A square of size 5 or 6".to_string(),
+                    actual: dim_size.to_string(),
+                })
+            }
+        };
+
+        language.set_alph_len(alphabet_len);
+
+        let info = self.square.info().clone();
+        self.square = PolybiusSquare::identity(language);
+        *self.square.info_mut() = info;
+
+        Ok(())
+    }
+
+    /// Finds the length of each block of the message, where the final block may be shorter
+    ///
+    /// # Arguments
+    ///
+    /// * `len` The length of the message
+    /// * `period` The period, or 0 for a single block
+    ///
+    fn block_lens(len: usize, period: usize) -> impl Iterator<Item = usize> {
+        let period = if period == 0 { len.max(1) } else { period };
+        (0..len)
+            .step_by(period)
+            .map(move |start| period.min(len - start))
+    }
+
+    /// Encrypts `src` into `dest` by writing the coordinates of each block beneath
+    /// each other, then reading them off in pairs
+    ///
+    /// # Arguments
+    ///
+    /// * `square` The square, row by row
+    /// * `inverse` The inverse of `square`, mapping letter -> position
+    /// * `dim_size` The number of rows (and columns) in the square
+    /// * `period` The period, or 0 for a single block
+    /// * `src` The plaintext
+    /// * `dest` Where to store the ciphertext, with the same length as `src`
+    ///
+    fn encrypt_cps(
+        square: &[i16],
+        inverse: &[i16],
+        dim_size: usize,
+        period: usize,
+        src: &[i16],
+        dest: &mut [i16],
+    ) {
+        let mut coords = Vec::with_capacity(period.max(src.len()) * 2);
+        let mut start = 0;
+
+        for len in Bifid::block_lens(src.len(), period) {
+            let block = &src[start..start + len];
+
+            coords.clear();
+            coords.extend(
+                block
+                    .iter()
+                    .map(|&cp| inverse[cp as usize] as usize / dim_size),
+            );
+            coords.extend(
+                block
+                    .iter()
+                    .map(|&cp| inverse[cp as usize] as usize % dim_size),
+            );
+
+            for (i, pair) in coords.chunks_exact(2).enumerate() {
+                dest[start + i] = square[pair[0] * dim_size + pair[1]];
+            }
+
+            start += len;
+        }
+    }
+
+    /// Decrypts `src` into `dest`, by reading the coordinates of each block in pairs,
+    /// then splitting them into the rows and columns of the plaintext
+    ///
+    /// # Arguments
+    ///
+    /// * `square` The square, row by row
+    /// * `inverse` The inverse of `square`, mapping letter -> position
+    /// * `dim_size` The number of rows (and columns) in the square
+    /// * `period` The period, or 0 for a single block
+    /// * `src` The ciphertext
+    /// * `dest` Where to store the plaintext, with the same length as `src`
+    ///
+    fn decrypt_cps(
+        square: &[i16],
+        inverse: &[i16],
+        dim_size: usize,
+        period: usize,
+        src: &[i16],
+        dest: &mut [i16],
+    ) {
+        let mut coords = Vec::with_capacity(period.max(src.len()) * 2);
+        let mut start = 0;
+
+        for len in Bifid::block_lens(src.len(), period) {
+            coords.clear();
+            src[start..start + len].iter().for_each(|&cp| {
+                let pos = inverse[cp as usize] as usize;
+                coords.push(pos / dim_size);
+                coords.push(pos % dim_size);
+            });
+
+            for i in 0..len {
+                dest[start + i] = square[coords[i] * dim_size + coords[len + i]];
+            }
+
+            start += len;
+        }
+    }
+
+    /// Converts a message to code points within the square, ignoring any other chars
+    ///
+    /// # Arguments
+    ///
+    /// * `language` A [`Language`] instance
+    /// * `msg` The message to convert
+    ///
+    fn msg_to_cps(&self, language: &mut Language, msg: &str) -> Vec<i16> {
+        language.set_alph_len(self.square.num_letters());
+
+        msg.chars()
+            .filter_map(|ch| self.square.char_to_cp(language, ch))
+            .collect()
+    }
+
+    /// Makes a random change to a square, used by the solver. Mostly swaps two
+    /// letters, but occasionally swaps two rows or two columns.
+    ///
+    /// # Arguments
+    ///
+    /// * `square` The square to modify
+    /// * `dim_size` The number of rows (and columns) in the square
+    ///
+    fn modify_square(square: &mut [i16], dim_size: usize) {
+        let a = fastrand::usize(0..dim_size);
+        let b = fastrand::usize(0..dim_size);
+
+        match fastrand::usize(0..20) {
+            0 => (0..dim_size).for_each(|col| square.swap(a * dim_size + col, b * dim_size + col)),
+            1 => (0..dim_size).for_each(|row| square.swap(row * dim_size + a, row * dim_size + b)),
+            _ => square.swap(
+                fastrand::usize(0..square.len()),
+                fastrand::usize(0..square.len()),
+            ),
+        }
+    }
+
+    /// Finds the most likely period of the ciphertext. Within a block of length `len`,
+    /// the letters `len / 2` apart hold the coordinates of adjacent plaintext letters,
+    /// so the index of coincedence of these pairs of letters is highest at the true period.
+    ///
+    /// # Arguments
+    ///
+    /// * `ciphertext` The ciphertext code points
+    /// * `num_symbols` The number of symbols in the square
+    ///
+    fn find_period(ciphertext: &[i16], num_symbols: usize) -> usize {
+        let len = ciphertext.len();
+
+        (2..=MAX_PERIOD as usize)
+            .filter(|&period| period < len)
+            .chain(std::iter::once(0))
+            .map(|period| {
+                let mut counts = vec![0_usize; num_symbols * num_symbols];
+                let mut start = 0;
+
+                for block_len in Bifid::block_lens(len, period) {
+                    let dist = block_len / 2;
+                    for i in start..start + block_len - dist {
+                        counts[ciphertext[i] as usize * num_symbols
+                            + ciphertext[i + dist] as usize] += 1;
+                    }
+                    start += block_len;
+                }
+
+                let total: usize = counts.iter().sum();
+                let ioc = counts.iter().map(|&f| f * (f.max(1) - 1)).sum::<usize>() as f64
+                    / (total * (total.max(2) - 1)) as f64;
+
+                (period, ioc)
+            })
+            .fold((0, f64::MIN), |best, x| if x.1 > best.1 { x } else { best })
+            .0
+    }
+}
+
+impl Asymmetric for Bifid {
+    fn encrypt(&self, language: &mut Language, msg: &str) -> String {
+        let plaintext = self.msg_to_cps(language, msg);
+        let mut ciphertext = vec![0; plaintext.len()];
+
+        Bifid::encrypt_cps(
+            self.square.as_slice(),
+            &util::invert(self.square.as_slice()),
+            self.square.dim_size(),
+            self.period.get() as usize,
+            &plaintext,
+            &mut ciphertext,
+        );
+
+        ciphertext
+            .into_iter()
+            .map(|cp| self.square.cp_to_char(language, cp))
+            .collect()
+    }
+    fn decrypt(&self, language: &mut Language, msg: &str) -> String {
+        let ciphertext = self.msg_to_cps(language, msg);
+        let mut plaintext = vec![0; ciphertext.len()];
+
+        Bifid::decrypt_cps(
+            self.square.as_slice(),
+            &util::invert(self.square.as_slice()),
+            self.square.dim_size(),
+            self.period.get() as usize,
+            &ciphertext,
+            &mut plaintext,
+        );
+
+        plaintext
+            .into_iter()
+            .map(|cp| self.square.cp_to_char(language, cp))
+            .collect()
+    }
+}
+
+impl Keyed for Bifid {
+    fn new(language: &mut Language) -> Bifid {
+        language.set_alph_len(ALPHABET_LEN);
+
+        let mut result = Bifid {
+            square: PolybiusSquare::identity(language),
+            period: *Number::new(language, 0).unwrap(),
+        };
+
+        result.period.set_legal_values((0..=MAX_PERIOD).collect());
+        result.square.info_mut().set("Square", "sq");
+        result.period.info_mut().set("Period", "period");
+
+        result
+    }
+    fn keys(&self) -> Vec<&dyn IoKey> {
+        vec![&self.square, &self.period]
+    }
+    fn keys_mut(&mut self) -> Vec<&mut dyn IoKey> {
+        vec![&mut self.square, &mut self.period]
+    }
+}
+
+const TEMP_STEPS: usize = 100;
+const ITERATIONS: usize = 10000;
+const MAX_RESTARTS: usize = 10;
+
+impl Solve for Bifid {
+    fn solve(&mut self, language: &mut Language, msg: &str) {
+        let ciphertext = self.msg_to_cps(language, msg);
+        let mut plaintext = vec![0; ciphertext.len()];
+
+        let dim_size = self.square.dim_size();
+        let num_symbols = dim_size * dim_size;
+        let period = Bifid::find_period(&ciphertext, num_symbols);
+
+        let identity = (0..num_symbols as i16).collect::<Vec<_>>();
+        let penalty = monoalph::unknown_symbol_penalty(language);
+        // the difference in score between keys grows with the message length
+        let start_temp = (ciphertext.len() as f64 / 15.0).max(5.0);
+
+        let mut inverse = identity.clone();
+        let mut best_square = identity.clone();
+        let mut best_score = f64::MIN;
+
+        for _ in 0..MAX_RESTARTS {
+            let mut square = identity.clone();
+            util::shuffle(&mut square);
+
+            let score = cipher::anneal(
+                &mut square,
+                start_temp,
+                TEMP_STEPS,
+                ITERATIONS,
+                |square| {
+                    square
+                        .iter()
+                        .enumerate()
+                        .for_each(|(pos, &cp)| inverse[cp as usize] = pos as i16);
+                    Bifid::decrypt_cps(
+                        square,
+                        &inverse,
+                        dim_size,
+                        period,
+                        &ciphertext,
+                        &mut plaintext,
+                    );
+                    monoalph::substitution_score(&plaintext, &identity, penalty, language)
+                },
+                |square| Bifid::modify_square(square, dim_size),
+            );
+
+            // the same best score from separate runs is likely to be the solution
+            if (score - best_score).abs() < 0.1 {
+                break;
+            }
+
+            if score > best_score {
+                best_score = score;
+                best_square = square;
+            }
+        }
+
+        self.period.set(language, period as i16).unwrap();
+        self.square.set(language, best_square.as_slice()).unwrap();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn encrypt() {
+        let mut language = Language::from_file("examples/data/english.bin").unwrap();
+        let mut cipher = Bifid::new(&mut language);
+        cipher
+            .square
+            .set(&mut language, "phqgmeaylnofdxkrcvszwbuti")
+            .unwrap();
+        cipher.period.set(&mut language, 5).unwrap();
+
+        let ciphertext = cipher.encrypt(&mut language, "defend the east wall of the castle");
+
+        assert_eq!(ciphertext, "FFYHMKHYCPLIASHADTRLHCCHLBLR");
+    }
+
+    #[test]
+    fn decrypt() {
+        let mut language = Language::from_file("examples/data/english.bin").unwrap();
+        let mut cipher = Bifid::new(&mut language);
+        cipher
+            .square
+            .set(&mut language, "bgwkzqpndsioaxefclumthyvr")
+            .unwrap();
+
+        let plaintext = cipher.decrypt(&mut language, "UAEOLWRINS");
+
+        assert_eq!(plaintext, "FLEEATONCE");
+    }
+
+    #[test]
+    fn solve() {
+        let mut language = Language::from_file("examples/data/english.bin").unwrap();
+        let mut cipher = Bifid::new(&mut language);
+        cipher
+            .square
+            .set(&mut language, "phqgmeaylnofdxkrcvszwbuti")
+            .unwrap();
+        cipher.period.set(&mut language, 5).unwrap();
+
+        let ciphertext = cipher.encrypt(&mut language, PLAINTEXT);
+        let plaintext = cipher.decrypt(&mut language, &ciphertext);
+
+        fastrand::seed(1);
+        let mut solved = Bifid::new(&mut language);
+        solved.solve(&mut language, &ciphertext);
+
+        assert_eq!(solved.decrypt(&mut language, &ciphertext), plaintext);
+    }
+
+    const PLAINTEXT: &str = "It is a truth universally acknowledged, that a single man in \
+        possession of a good fortune, must be in want of a wife. However little known the \
+        feelings or views of such a man may be on his first entering a neighbourhood, this \
+        truth is so well fixed in the minds of the surrounding families, that he is \
+        considered the rightful property of some one or other of their daughters. My dear \
+        Mr. Bennet, said his lady to him one day, have you heard that Netherfield Park is \
+        let at last?";
+}
@@ -1 +1,293 @@
+use crate::{
+    cipher::{self, Asymmetric, Keyed, Solve},
+    key::{IdentityKey, IoKey, Key, PolybiusSquare},
+    lang::{Language, ScoreSize},
+    util,
+};
 
+/// The Four-Square squares are 5x5, so use the 25 letter alphabet
+const ALPHABET_LEN: usize = 25;
+const DIM_SIZE: usize = 5;
+
+/// The Four-Square cipher. The top left and bottom right squares hold the alphabet
+/// in order, and the top right and bottom left squares are keyed. The first letter of
+/// each digraph is found in the top left square, and the second in the bottom right.
+/// The ciphertext letters are the other corners of the rectangle they form, taking the
+/// letter from the top right square first.
+///
+pub struct FourSquare {
+    /// The top right square
+    pub square1: PolybiusSquare,
+    /// The bottom left square
+    pub square2: PolybiusSquare,
+    /// Pads messages of odd length
+    pub padding: char,
+}
+
+impl FourSquare {
+    /// Encrypts each digraph of `src` into `dest`
+    ///
+    /// # Arguments
+    ///
+    /// * `squares` The top right square followed by the bottom left, each row by row
+    /// * `src` The plaintext digraphs
+    /// * `dest` Where to store the ciphertext, with the same length as `src`
+    ///
+    fn encrypt_digraphs(squares: &[i16], src: &[i16], dest: &mut [i16]) {
+        let (square1, square2) = squares.split_at(ALPHABET_LEN);
+
+        for i in (0..src.len() - src.len() % 2).step_by(2) {
+            let (row1, col1) = (src[i] as usize / DIM_SIZE, src[i] as usize % DIM_SIZE);
+            let (row2, col2) = (
+                src[i + 1] as usize / DIM_SIZE,
+                src[i + 1] as usize % DIM_SIZE,
+            );
+
+            dest[i] = square1[row1 * DIM_SIZE + col2];
+            dest[i + 1] = square2[row2 * DIM_SIZE + col1];
+        }
+    }
+
+    /// Decrypts each digraph of `src` into `dest`
+    ///
+    /// # Arguments
+    ///
+    /// * `inverse` The inverse of each square, mapping letter -> position
+    /// * `src` The ciphertext digraphs
+    /// * `dest` Where to store the plaintext, with the same length as `src`
+    ///
+    fn decrypt_digraphs(inverse: &[i16], src: &[i16], dest: &mut [i16]) {
+        let (inverse1, inverse2) = inverse.split_at(ALPHABET_LEN);
+
+        for i in (0..src.len() - src.len() % 2).step_by(2) {
+            let pos1 = inverse1[src[i] as usize] as usize;
+            let pos2 = inverse2[src[i + 1] as usize] as usize;
+
+            let (row1, col2) = (pos1 / DIM_SIZE, pos1 % DIM_SIZE);
+            let (row2, col1) = (pos2 / DIM_SIZE, pos2 % DIM_SIZE);
+
+            dest[i] = (row1 * DIM_SIZE + col1) as i16;
+            dest[i + 1] = (row2 * DIM_SIZE + col2) as i16;
+        }
+    }
+
+    /// Converts a message to code points, padding it to an even length
+    ///
+    /// # Arguments
+    ///
+    /// * `language` A [`Language`] instance
+    /// * `msg` The message to convert
+    ///
+    fn msg_to_cps(&self, language: &mut Language, msg: &str) -> Vec<i16> {
+        language.set_alph_len(ALPHABET_LEN);
+
+        let mut result = language.string_to_vec(msg);
+        if result.len() % 2 == 1 {
+            result.push(language.get_cp(&self.padding));
+        }
+
+        result
+    }
+
+    /// Finds the inverse of each square, storing it in `inverse`
+    ///
+    /// # Arguments
+    ///
+    /// * `squares` The top right square followed by the bottom left, each row by row
+    /// * `inverse` Where to store the inverse, with the same length as `squares`
+    ///
+    fn invert_into(squares: &[i16], inverse: &mut [i16]) {
+        for (square, inverse) in squares
+            .chunks_exact(ALPHABET_LEN)
+            .zip(inverse.chunks_exact_mut(ALPHABET_LEN))
+        {
+            square
+                .iter()
+                .enumerate()
+                .for_each(|(pos, &cp)| inverse[cp as usize] = pos as i16);
+        }
+    }
+
+    /// Makes a random change to one of the squares, used by the solver. Mostly swaps
+    /// two letters, but occasionally swaps two rows or two columns.
+    ///
+    /// # Arguments
+    ///
+    /// * `squares` The top right square followed by the bottom left, each row by row
+    ///
+    fn modify_squares(squares: &mut [i16]) {
+        let square = &mut squares[fastrand::usize(0..2) * ALPHABET_LEN..][..ALPHABET_LEN];
+        let a = fastrand::usize(0..DIM_SIZE);
+        let b = fastrand::usize(0..DIM_SIZE);
+
+        match fastrand::usize(0..20) {
+            0 => (0..DIM_SIZE).for_each(|col| square.swap(a * DIM_SIZE + col, b * DIM_SIZE + col)),
+            1 => (0..DIM_SIZE).for_each(|row| square.swap(row * DIM_SIZE + a, row * DIM_SIZE + b)),
+            _ => square.swap(
+                fastrand::usize(0..ALPHABET_LEN),
+                fastrand::usize(0..ALPHABET_LEN),
+            ),
+        }
+    }
+}
+
+impl Asymmetric for FourSquare {
+    fn encrypt(&self, language: &mut Language, msg: &str) -> String {
+        let plaintext = self.msg_to_cps(language, msg);
+        let mut ciphertext = vec![0; plaintext.len()];
+
+        let squares = [self.square1.as_slice(), self.square2.as_slice()].concat();
+        FourSquare::encrypt_digraphs(&squares, &plaintext, &mut ciphertext);
+
+        language.vec_to_string(&ciphertext)
+    }
+    fn decrypt(&self, language: &mut Language, msg: &str) -> String {
+        let ciphertext = self.msg_to_cps(language, msg);
+        let mut plaintext = vec![0; ciphertext.len()];
+
+        let squares = [self.square1.as_slice(), self.square2.as_slice()].concat();
+        let mut inverse = vec![0; squares.len()];
+        FourSquare::invert_into(&squares, &mut inverse);
+        FourSquare::decrypt_digraphs(&inverse, &ciphertext, &mut plaintext);
+
+        language.vec_to_string(&plaintext)
+    }
+}
+
+impl Keyed for FourSquare {
+    fn new(language: &mut Language) -> FourSquare {
+        language.set_alph_len(ALPHABET_LEN);
+
+        let mut result = FourSquare {
+            square1: PolybiusSquare::identity(language),
+            square2: PolybiusSquare::identity(language),
+            padding: 'X',
+        };
+
+        result.square1.info_mut().set("Square 1", "sq1");
+        result.square2.info_mut().set("Square 2", "sq2");
+
+        result
+    }
+    fn keys(&self) -> Vec<&dyn IoKey> {
+        vec![&self.square1, &self.square2]
+    }
+    fn keys_mut(&mut self) -> Vec<&mut dyn IoKey> {
+        vec![&mut self.square1, &mut self.square2]
+    }
+}
+
+const TEMP_STEPS: usize = 100;
+const ITERATIONS: usize = 20000;
+const MAX_RESTARTS: usize = 20;
+
+impl Solve for FourSquare {
+    fn solve(&mut self, language: &mut Language, msg: &str) {
+        language.set_alph_len(ALPHABET_LEN);
+
+        let ciphertext = language.string_to_vec(msg);
+        let ciphertext = &ciphertext[..ciphertext.len() - ciphertext.len() % 2];
+        let mut plaintext = vec![0; ciphertext.len()];
+        let mut inverse = vec![0; 2 * ALPHABET_LEN];
+
+        // the difference in score between keys grows with the message length
+        let start_temp = (ciphertext.len() as f64 / 15.0).max(5.0);
+
+        let mut best_squares = Vec::new();
+        let mut best_score = f64::MIN;
+
+        for _ in 0..MAX_RESTARTS {
+            let mut squares = (0..2 * ALPHABET_LEN)
+                .map(|i| (i % ALPHABET_LEN) as i16)
+                .collect::<Vec<_>>();
+            squares
+                .chunks_exact_mut(ALPHABET_LEN)
+                .for_each(util::shuffle);
+
+            let score = cipher::anneal(
+                &mut squares,
+                start_temp,
+                TEMP_STEPS,
+                ITERATIONS,
+                |squares| {
+                    FourSquare::invert_into(squares, &mut inverse);
+                    FourSquare::decrypt_digraphs(&inverse, ciphertext, &mut plaintext);
+
+                    // trigrams smooth out the score of keys which are close to the
+                    // solution, so that the search is less likely to get stuck
+                    language.score(&plaintext, ScoreSize::Quadgrams)
+                        + language.score(&plaintext, ScoreSize::Trigrams)
+                },
+                |squares| FourSquare::modify_squares(squares),
+            );
+
+            // the same best score from separate runs is likely to be the solution
+            if (score - best_score).abs() < 0.1 {
+                break;
+            }
+
+            if score > best_score {
+                best_score = score;
+                best_squares = squares;
+            }
+        }
+
+        let (square1, square2) = best_squares.split_at(ALPHABET_LEN);
+        self.square1.set(language, square1).unwrap();
+        self.square2.set(language, square2).unwrap();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn encrypt() {
+        let mut language = Language::from_file("examples/data/english.bin").unwrap();
+        let mut cipher = FourSquare::new(&mut language);
+        cipher.square1.set(&mut language, "example").unwrap();
+        cipher.square2.set(&mut language, "keyword").unwrap();
+
+        let ciphertext = cipher.encrypt(&mut language, "Help me Obi Wan Kenobi");
+
+        assert_eq!(ciphertext, "FYNFNEHWBXAFFOKHMD");
+    }
+
+    #[test]
+    fn decrypt() {
+        let mut language = Language::from_file("examples/data/english.bin").unwrap();
+        let mut cipher = FourSquare::new(&mut language);
+        cipher.square1.set(&mut language, "example").unwrap();
+        cipher.square2.set(&mut language, "keyword").unwrap();
+
+        let plaintext = cipher.decrypt(&mut language, "FYNFNEHWBXAFFOKHMD");
+
+        assert_eq!(plaintext, "HELPMEOBIWANKENOBI");
+    }
+
+    #[test]
+    fn solve() {
+        let mut language = Language::from_file("examples/data/english.bin").unwrap();
+        let mut cipher = FourSquare::new(&mut language);
+        cipher.square1.set(&mut language, "example").unwrap();
+        cipher.square2.set(&mut language, "keyword").unwrap();
+
+        let ciphertext = cipher.encrypt(&mut language, PLAINTEXT);
+        let plaintext = cipher.decrypt(&mut language, &ciphertext);
+
+        fastrand::seed(1);
+        let mut solved = FourSquare::new(&mut language);
+        solved.solve(&mut language, &ciphertext);
+
+        assert_eq!(solved.decrypt(&mut language, &ciphertext), plaintext);
+    }
+
+    const PLAINTEXT: &str = "Happy families are all alike; every unhappy family is unhappy \
+        in its own way. Everything was in confusion in the Oblonskys' house. The wife had \
+        discovered that the husband was carrying on an intrigue with a French girl, who had \
+        been a governess in their family, and she had announced to her husband that she \
+        could not go on living in the same house with him. This position of affairs had now \
+        lasted three days, and not only the husband and wife themselves, but all the members \
+        of their family and household, were painfully conscious of it.";
+}
@@ -1 +1,359 @@
+use crate::{
+    cipher::{self, monoalph, monoalph::morse, Asymmetric, Keyed, Solve},
+    key::{Alphabet, IdentityKey, IoKey, Key},
+    lang::{Language, ScoreSize},
+    util,
+};
 
+/// Each of the 26 letters stands for a trigram of `.-x`, where `xxx` is never used
+const ALPHABET_LEN: usize = 26;
+/// The symbols of each trigram, in the order used to number the trigrams
+const SYMBOLS: [char; 3] = ['.', '-', 'x'];
+/// The index of the separator `x` in [`SYMBOLS`]
+const SEPARATOR: usize = 2;
+/// Morse codes have at most 5 symbols, so fit into 6 bits with a leading 1
+const CODE_TABLE_LEN: usize = 64;
+/// Marks a code in the code table which is not a letter or a digit
+const INVALID: i16 = -1;
+/// Marks a code in the code table which is a digit, so is not scored
+const DIGIT: i16 = -2;
+
+/// The Fractionated Morse cipher. The plaintext is converted to Morse code, with an
+/// `x` between letters and `xx` between words, then padded with `x` to a multiple of
+/// three symbols. Each trigram of symbols is then substituted for a letter. Trigrams
+/// are numbered in the order `...`, `..-`, `..x`, `.-.` and so on, so the keyed alphabet
+/// gives the letter for each trigram.
+///
+pub struct FractionatedMorse {
+    pub alphabet: Alphabet,
+    /// A word or phrase known to be in the plaintext, used by the solver
+    pub crib: Option<String>,
+}
+
+impl FractionatedMorse {
+    /// Converts a message to Morse symbols (indexes into [`SYMBOLS`]), ignoring any
+    /// chars without a Morse code, and pads the result to a multiple of three symbols
+    ///
+    /// # Arguments
+    ///
+    /// * `msg` The message to convert
+    ///
+    fn msg_to_symbols(msg: &str) -> Vec<usize> {
+        let mut result = Vec::new();
+
+        for word in msg.to_lowercase().split_whitespace() {
+            let codes = word
+                .chars()
+                .filter_map(|ch| morse::LETTERS.iter().skip(1).position(|&x| x == ch))
+                .map(|pos| morse::MORSE[pos + 1])
+                .collect::<Vec<_>>();
+
+            for (i, code) in codes.into_iter().enumerate() {
+                if i == 0 && !result.is_empty() {
+                    result.extend([SEPARATOR, SEPARATOR]);
+                } else if i > 0 {
+                    result.push(SEPARATOR);
+                }
+
+                result.extend(code.chars().map(|ch| (ch == '-') as usize));
+            }
+        }
+
+        while result.len() % 3 != 0 {
+            result.push(SEPARATOR);
+        }
+
+        result
+    }
+
+    /// Converts Morse symbols back to a message, with words separated by spaces.
+    /// Any invalid codes are ignored.
+    ///
+    /// # Arguments
+    ///
+    /// * `symbols` The Morse symbols, as indexes into [`SYMBOLS`]
+    ///
+    fn symbols_to_msg(symbols: &[usize]) -> String {
+        let code = symbols.iter().map(|&x| SYMBOLS[x]).collect::<String>();
+
+        code.trim_matches(SYMBOLS[SEPARATOR])
+            .split("xx")
+            .map(|word| {
+                word.split(SYMBOLS[SEPARATOR])
+                    .filter_map(|letter| morse::MORSE.iter().position(|&x| x == letter))
+                    .map(|pos| morse::LETTERS[pos].to_ascii_uppercase())
+                    .collect::<String>()
+            })
+            .filter(|word| !word.is_empty())
+            .collect::<Vec<_>>()
+            .join(" ")
+    }
+
+    /// Finds the Morse symbols of a trigram
+    ///
+    /// # Arguments
+    ///
+    /// * `trigram` The index of the trigram
+    ///
+    fn trigram_symbols(trigram: usize) -> [usize; 3] {
+        [trigram / 9, trigram / 3 % 3, trigram % 3]
+    }
+
+    /// Creates a table mapping each Morse code to the code point of its letter, used by
+    /// the solver. Each code is stored as a binary number with a leading 1, where a dot
+    /// is 0 and a dash is 1.
+    ///
+    /// # Arguments
+    ///
+    /// * `language` A [`Language`] instance
+    ///
+    fn code_table(language: &Language) -> Vec<i16> {
+        let mut result = vec![INVALID; CODE_TABLE_LEN];
+
+        for (&ch, code) in morse::LETTERS.iter().zip(morse::MORSE).skip(1) {
+            let idx = code.chars().fold(1, |acc, x| acc * 2 + (x == '-') as usize);
+            result[idx] = if ch.is_ascii_digit() {
+                DIGIT
+            } else {
+                language.get_cp(&ch)
+            };
+        }
+
+        result
+    }
+
+    /// Decrypts `ciphertext`, storing the code point of each letter in `dest`, and
+    /// returns the number of invalid Morse codes found. Digits are skipped.
+    ///
+    /// # Arguments
+    ///
+    /// * `table` The table from [`FractionatedMorse::code_table`]
+    /// * `inverse` Maps each ciphertext letter to its trigram
+    /// * `ciphertext` The ciphertext code points
+    /// * `dest` Where to store the plaintext code points
+    ///
+    fn decrypt_cps(
+        table: &[i16],
+        inverse: &[i16],
+        ciphertext: &[i16],
+        dest: &mut Vec<i16>,
+    ) -> usize {
+        let mut invalid = 0;
+        let mut code = 1;
+
+        let mut push_letter = |code: usize| match table.get(code).copied().unwrap_or(INVALID) {
+            INVALID => invalid += 1,
+            DIGIT => {}
+            cp => dest.push(cp),
+        };
+
+        for &cp in ciphertext {
+            for symbol in FractionatedMorse::trigram_symbols(inverse[cp as usize] as usize) {
+                if symbol == SEPARATOR {
+                    if code > 1 {
+                        push_letter(code);
+                        code = 1;
+                    }
+                } else {
+                    code = (code * 2 + symbol).min(CODE_TABLE_LEN);
+                }
+            }
+        }
+
+        if code > 1 {
+            push_letter(code);
+        }
+
+        invalid
+    }
+
+    /// Finds the greatest number of letters of `crib` that match the plaintext, at any
+    /// position
+    ///
+    /// # Arguments
+    ///
+    /// * `plaintext` The plaintext code points
+    /// * `crib` The crib code points
+    ///
+    fn crib_matches(plaintext: &[i16], crib: &[i16]) -> usize {
+        plaintext
+            .windows(crib.len().max(1))
+            .map(|window| window.iter().zip(crib).filter(|(a, b)| a == b).count())
+            .max()
+            .unwrap_or(0)
+    }
+}
+
+impl Asymmetric for FractionatedMorse {
+    fn encrypt(&self, language: &mut Language, msg: &str) -> String {
+        language.set_alph_len(ALPHABET_LEN);
+
+        let ciphertext = FractionatedMorse::msg_to_symbols(msg)
+            .chunks_exact(3)
+            .map(|trigram| {
+                self.alphabet
+                    .encrypt((trigram[0] * 9 + trigram[1] * 3 + trigram[2]) as i16)
+            })
+            .collect::<Vec<_>>();
+
+        language.vec_to_string(&ciphertext)
+    }
+    fn decrypt(&self, language: &mut Language, msg: &str) -> String {
+        language.set_alph_len(ALPHABET_LEN);
+
+        let symbols = language
+            .string_to_vec(msg)
+            .into_iter()
+            .flat_map(|cp| FractionatedMorse::trigram_symbols(self.alphabet.decrypt(cp) as usize))
+            .collect::<Vec<_>>();
+
+        FractionatedMorse::symbols_to_msg(&symbols)
+    }
+}
+
+impl Keyed for FractionatedMorse {
+    fn new(language: &mut Language) -> FractionatedMorse {
+        language.set_alph_len(ALPHABET_LEN);
+
+        let mut result = FractionatedMorse {
+            alphabet: Alphabet::identity(language),
+            crib: None,
+        };
+
+        result.alphabet.info_mut().set("Alphabet", "alph");
+
+        result
+    }
+    fn keys(&self) -> Vec<&dyn IoKey> {
+        vec![&self.alphabet]
+    }
+    fn keys_mut(&mut self) -> Vec<&mut dyn IoKey> {
+        vec![&mut self.alphabet]
+    }
+}
+
+const TEMP_STEPS: usize = 100;
+const ITERATIONS: usize = 10000;
+const MAX_RESTARTS: usize = 5;
+
+impl Solve for FractionatedMorse {
+    fn solve(&mut self, language: &mut Language, msg: &str) {
+        language.set_alph_len(ALPHABET_LEN);
+
+        let ciphertext = language.string_to_vec(msg);
+        let crib = self
+            .crib
+            .as_ref()
+            .map(|crib| language.string_to_vec(crib))
+            .unwrap_or_default();
+        let mut plaintext = Vec::with_capacity(ciphertext.len() * 3);
+
+        let table = FractionatedMorse::code_table(language);
+        let penalty = monoalph::unknown_symbol_penalty(language);
+        // each letter of the crib found is worth about as much as an unlikely quadgram
+        let crib_weight = -penalty / ScoreSize::Quadgrams.length() as f64;
+        // the difference in score between keys grows with the message length
+        let start_temp = (ciphertext.len() as f64 / 15.0).max(5.0);
+
+        let mut best_inverse = Vec::new();
+        let mut best_score = f64::MIN;
+
+        for _ in 0..MAX_RESTARTS {
+            // maps each ciphertext letter to its trigram
+            let mut inverse = (0..ALPHABET_LEN as i16).collect::<Vec<_>>();
+            util::shuffle(&mut inverse);
+
+            let score = cipher::anneal(
+                &mut inverse,
+                start_temp,
+                TEMP_STEPS,
+                ITERATIONS,
+                |inverse| {
+                    plaintext.clear();
+                    let invalid = FractionatedMorse::decrypt_cps(
+                        &table,
+                        inverse,
+                        &ciphertext,
+                        &mut plaintext,
+                    );
+                    let score = language.score(&plaintext, ScoreSize::Quadgrams)
+                        + invalid as f64 * penalty
+                        + FractionatedMorse::crib_matches(&plaintext, &crib) as f64 * crib_weight;
+
+                    // the number of letters depends on the key, so compare the score per letter
+                    score / (plaintext.len() + invalid).max(1) as f64 * ciphertext.len() as f64
+                },
+                |inverse| {
+                    inverse.swap(
+                        fastrand::usize(0..ALPHABET_LEN),
+                        fastrand::usize(0..ALPHABET_LEN),
+                    )
+                },
+            );
+
+            // the same best score from separate runs is likely to be the solution
+            if (score - best_score).abs() < 0.1 {
+                break;
+            }
+
+            if score > best_score {
+                best_score = score;
+                best_inverse = inverse;
+            }
+        }
+
+        self.alphabet
+            .set(language, util::invert(&best_inverse).as_slice())
+            .unwrap();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn encrypt() {
+        let mut language = Language::from_file("examples/data/english.bin").unwrap();
+        let mut cipher = FractionatedMorse::new(&mut language);
+        cipher.alphabet.set(&mut language, "roundtable").unwrap();
+
+        let ciphertext = cipher.encrypt(&mut language, "Come at once");
+
+        assert_eq!(ciphertext, "CBIILTMHVVFL");
+    }
+
+    #[test]
+    fn decrypt() {
+        let mut language = Language::from_file("examples/data/english.bin").unwrap();
+        let mut cipher = FractionatedMorse::new(&mut language);
+        cipher.alphabet.set(&mut language, "roundtable").unwrap();
+
+        let plaintext = cipher.decrypt(&mut language, "CBIILTMHVVFL");
+
+        assert_eq!(plaintext, "COME AT ONCE");
+    }
+
+    #[test]
+    fn solve() {
+        let mut language = Language::from_file("examples/data/english.bin").unwrap();
+        let mut cipher = FractionatedMorse::new(&mut language);
+        cipher.alphabet.set(&mut language, "roundtable").unwrap();
+
+        let ciphertext = cipher.encrypt(&mut language, PLAINTEXT);
+        let plaintext = cipher.decrypt(&mut language, &ciphertext);
+
+        fastrand::seed(1);
+        let mut solved = FractionatedMorse::new(&mut language);
+        solved.solve(&mut language, &ciphertext);
+
+        assert_eq!(solved.decrypt(&mut language, &ciphertext), plaintext);
+    }
+
+    const PLAINTEXT: &str = "To Sherlock Holmes she is always the woman. I have seldom \
+        heard him mention her under any other name. In his eyes she eclipses and \
+        predominates the whole of her sex. It was not that he felt any emotion akin to love \
+        for Irene Adler. All emotions, and that one particularly, were abhorrent to his \
+        cold, precise but admirably balanced mind. He was, I take it, the most perfect \
+        reasoning and observing machine that the world has seen.";
+}
@@ -1,41 +1,224 @@
 use crate::{
-    cipher::{Asymmetric, Keyed},
-    key::{IdentityKey, IoKey, Matrix, MatrixDimSize},
-    lang::Language,
+    cipher::{Asymmetric, Keyed, Solve},
+    error::{Error, Result},
+    key::{IdentityKey, IoKey, Key, Matrix},
+    lang::{Language, ScoreSize},
     util,
 };
 
+/// The number of best scoring rows tried in each row of the decryption matrix
+const ROW_CANDIDATES: usize = 8;
+/// The number of blocks searched for an invertible plaintext matrix
+const MAX_KNOWN_BLOCKS: usize = 30;
+
+/// The Hill cipher. The message is split into blocks the size of the matrix, and each
+/// block is multiplied by the matrix. The matrix may be from 2x2 up to 8x8, and the
+/// final block is completed with the `padding` letter.
+///
+/// The solver uses the attack of Bauer and Millward. Each row of the decryption matrix
+/// gives the letters at one position of each block, so the rows are found separately
+/// by scoring every possible row with the chi-squared statistic. The best rows are then
+/// combined into an invertible matrix, scored using quadgrams. Both 2x2 and 3x3
+/// matrices are tried.
+///
 pub struct Hill {
     pub matrix: Matrix,
+    /// The letter added to complete the final block, which must be in the alphabet
+    pub padding: char,
 }
 
 impl Hill {
     fn run_array(language: &Language, mat: &Matrix, src: &[i16], dest: &mut [i16]) {
-        let size = mat.size as usize;
-
-        for i in (0..src.len() - (size - 1)).step_by(size) {
-            for j in 0..size {
-                dest[i + j] = util::modulo(
-                    match mat.size {
-                        MatrixDimSize::Two => mat.at(j, 0) * src[i] + mat.at(j, 1) * src[i + 1],
-                        MatrixDimSize::Three => {
-                            mat.at(j, 0) * src[i]
-                                + mat.at(j, 1) * src[i + 1]
-                                + mat.at(j, 2) * src[i + 2]
-                        }
-                    },
+        let size = mat.size();
+
+        for (src, dest) in src.chunks_exact(size).zip(dest.chunks_exact_mut(size)) {
+            for (j, cp) in dest.iter_mut().enumerate() {
+                *cp = util::modulo(
+                    (0..size).map(|k| mat.at(j, k) * src[k]).sum(),
                     language.cp_count(),
                 );
             }
         }
     }
-    fn pad_txt(txt: &mut Vec<i16>, multiple: usize) {
+    fn pad_txt(txt: &mut Vec<i16>, multiple: usize, padding: i16) {
         if !txt.is_empty() {
             let extra_len =
                 util::modulo(multiple as i16 - txt.len() as i16, multiple as i16) as usize;
-            txt.resize(txt.len() + extra_len, 0);
+            txt.resize(txt.len() + extra_len, padding);
+        }
+    }
+    /// Multiplies two square matrices, given row by row
+    ///
+    /// # Arguments
+    ///
+    /// * `a` The left matrix
+    /// * `b` The right matrix
+    /// * `size` The number of rows (and columns)
+    /// * `modulus` The modulus of each element
+    ///
+    fn mat_mul(a: &[i16], b: &[i16], size: usize, modulus: i16) -> Vec<i16> {
+        (0..size * size)
+            .map(|idx| {
+                let (row, col) = (idx / size, idx % size);
+                (0..size).fold(0, |acc, k| {
+                    util::modulo(acc + a[row * size + k] * b[k * size + col], modulus)
+                })
+            })
+            .collect()
+    }
+
+    /// Finds the rows of a decryption matrix giving letter frequencies closest to the
+    /// language, ordered from best to worst. Rows sharing a factor with the alphabet
+    /// length are skipped, since they cannot be part of an invertible matrix.
+    ///
+    /// # Arguments
+    ///
+    /// * `language` A [`Language`] instance
+    /// * `ciphertext` The ciphertext, a whole number of blocks long
+    /// * `size` The number of rows (and columns) of the matrix
+    ///
+    fn row_candidates(language: &Language, ciphertext: &[i16], size: usize) -> Vec<Vec<i16>> {
+        let cp_count = language.cp_count();
+        let mut letters = vec![0; ciphertext.len() / size];
+        let mut scored = Vec::new();
+
+        for idx in 0..(cp_count as usize).pow(size as u32) {
+            let row = (0..size)
+                .map(|k| (idx / (cp_count as usize).pow(k as u32) % cp_count as usize) as i16)
+                .collect::<Vec<_>>();
+
+            if row.iter().fold(cp_count, |acc, &x| util::gcd(x, acc)) != 1 {
+                continue;
+            }
+
+            for (letter, block) in letters.iter_mut().zip(ciphertext.chunks_exact(size)) {
+                *letter = util::modulo(row.iter().zip(block).map(|(a, b)| a * b).sum(), cp_count);
+            }
+
+            scored.push((language.chi_squared(&letters), row));
+        }
+
+        scored.sort_by(|a, b| a.0.total_cmp(&b.0));
+        scored
+            .into_iter()
+            .take(ROW_CANDIDATES)
+            .map(|(_, row)| row)
+            .collect()
+    }
+
+    /// Finds the best decryption matrix of the given size, returning its quadgram
+    /// score and the matrix, or `None` if no invertible matrix was found
+    ///
+    /// # Arguments
+    ///
+    /// * `language` A [`Language`] instance
+    /// * `ciphertext` The ciphertext, a whole number of blocks long
+    /// * `size` The number of rows (and columns) of the matrix
+    ///
+    fn solve_size(
+        language: &mut Language,
+        ciphertext: &[i16],
+        size: usize,
+    ) -> Option<(f64, Box<Matrix>)> {
+        let candidates = Hill::row_candidates(language, ciphertext, size);
+        let mut plaintext = vec![0; ciphertext.len()];
+        let mut best = None;
+        let mut best_score = f64::MIN;
+
+        // try each ordered choice of distinct rows
+        for idx in 0..candidates.len().pow(size as u32) {
+            let choice = (0..size)
+                .map(|k| idx / candidates.len().pow(k as u32) % candidates.len())
+                .collect::<Vec<_>>();
+
+            if (1..size).any(|k| choice[..k].contains(&choice[k])) {
+                continue;
+            }
+
+            let value = choice
+                .iter()
+                .flat_map(|&row| candidates[row].iter().copied())
+                .collect::<Vec<_>>();
+
+            if let Ok(matrix) = Matrix::new(language, value.as_slice()) {
+                Hill::run_array(language, &matrix, ciphertext, &mut plaintext);
+                let score = language.score(&plaintext, ScoreSize::Quadgrams);
+
+                if score > best_score {
+                    best_score = score;
+                    best = Some(matrix);
+                }
+            }
+        }
+
+        best.map(|matrix| (best_score, matrix))
+    }
+
+    /// Finds the matrix from a known plaintext and its ciphertext. Blocks of the
+    /// plaintext are chosen to form an invertible matrix `P`, and the matching blocks
+    /// of the ciphertext form `C`, so the matrix is `C * P^-1`. The size of the current
+    /// matrix is used.
+    ///
+    /// # Arguments
+    ///
+    /// * `language` A [`Language`] instance
+    /// * `plaintext` The known plaintext
+    /// * `ciphertext` The ciphertext of `plaintext`
+    ///
+    pub fn recover_key(
+        &mut self,
+        language: &mut Language,
+        plaintext: &str,
+        ciphertext: &str,
+    ) -> Result<()> {
+        let size = self.matrix.size();
+        let cp_count = language.cp_count();
+        let plaintext = language.string_to_vec(plaintext);
+        let ciphertext = language.string_to_vec(ciphertext);
+
+        let len = plaintext.len().min(ciphertext.len());
+        if len < size * size {
+            return Err(Error::InsufficientInputLen {
+                expected: size * size,
+                actual: len,
+            });
+        }
+
+        let num_blocks = (len / size).min(MAX_KNOWN_BLOCKS);
+
+        // each column of the matrices is a block
+        let to_matrix = |txt: &[i16], blocks: &[usize]| {
+            (0..size * size)
+                .map(|idx| txt[blocks[idx % size] * size + idx / size])
+                .collect::<Vec<_>>()
+        };
+
+        // try each choice of blocks in increasing order
+        let mut blocks = (0..size).collect::<Vec<_>>();
+        loop {
+            if let Ok(p) = Matrix::new(language, to_matrix(&plaintext, &blocks).as_slice()) {
+                let c = to_matrix(&ciphertext, &blocks);
+                let key = Hill::mat_mul(&c, p.invert(language).as_slice(), size, cp_count);
+                return self.matrix.set(language, key.as_slice());
+            }
+
+            match (0..size).rev().find(|&k| blocks[k] < num_blocks - size + k) {
+                Some(k) => {
+                    blocks[k] += 1;
+                    for j in k + 1..size {
+                        blocks[j] = blocks[j - 1] + 1;
+                    }
+                }
+                None => break,
+            }
         }
+
+        Err(Error::InvalidKeyFmt {
+            expected: "Plaintext blocks forming an invertible matrix".to_string(),
+            actual: language.vec_to_string(&plaintext),
+        })
     }
+
     fn arr_to_str(language: &Language, msg: &str, dest: &[i16]) -> String {
         let mut i = 0;
         let mut result = msg
@@ -66,7 +249,7 @@ impl Asymmetric for Hill {
         let mut dest = vec![0; txt.len()];
 
         if !txt.is_empty() {
-            Hill::pad_txt(&mut txt, self.matrix.size as usize);
+            Hill::pad_txt(&mut txt, self.matrix.size(), language.get_cp(&self.padding));
             dest.resize(txt.len(), 0);
 
             Hill::run_array(language, &self.matrix, &txt, &mut dest);
@@ -81,7 +264,7 @@ impl Asymmetric for Hill {
         let inv = self.matrix.invert(language);
 
         if !txt.is_empty() {
-            Hill::pad_txt(&mut txt, inv.size as usize);
+            Hill::pad_txt(&mut txt, inv.size(), language.get_cp(&self.padding));
             dest.resize(txt.len(), 0);
 
             Hill::run_array(language, &inv, &txt, &mut dest);
@@ -95,6 +278,7 @@ impl Keyed for Hill {
     fn new(language: &mut Language) -> Self {
         let mut result = Hill {
             matrix: Matrix::identity(language),
+            padding: 'A',
         };
 
         result.matrix.info_mut().set("Matrix", "mat");
@@ -109,6 +293,24 @@ impl Keyed for Hill {
     }
 }
 
+impl Solve for Hill {
+    fn solve(&mut self, language: &mut Language, msg: &str) {
+        let mut ciphertext = language.string_to_vec(msg);
+        // use the same letters for each size, so the scores can be compared
+        ciphertext.truncate(ciphertext.len() - ciphertext.len() % 6);
+
+        let best = [2, 3]
+            .iter()
+            .filter_map(|&size| Hill::solve_size(language, &ciphertext, size))
+            .max_by(|a, b| a.0.total_cmp(&b.0));
+
+        if let Some((_, matrix)) = best {
+            let key = matrix.invert(language);
+            self.matrix.set(language, key.as_slice()).unwrap();
+        }
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -137,4 +339,48 @@ mod tests {
 
         assert_eq!(&ciphertext, "SECRETMESSAGEAA");
     }
+
+    #[test]
+    fn x4_padding() {
+        let mut language = Language::from_file("examples/data/english.bin").unwrap();
+        let mut cipher = Hill::new(&mut language);
+        cipher
+            .matrix
+            .set(&mut language, "encryptionmatrix")
+            .unwrap();
+        cipher.padding = 'X';
+
+        let ciphertext = cipher.encrypt(&mut language, "RETREATNOW");
+        assert_eq!(&ciphertext, "FDYYPPYHZNEB");
+
+        let plaintext = cipher.decrypt(&mut language, &ciphertext);
+        assert_eq!(&plaintext, "RETREATNOWXX");
+    }
+
+    #[test]
+    fn recover_key() {
+        let mut language = Language::from_file("examples/data/english.bin").unwrap();
+        let mut cipher = Hill::new(&mut language);
+        cipher.matrix.set(&mut language, "alphabeta").unwrap();
+
+        let plaintext = "DEFENDTHEEASTWALLOFTHECASTLE";
+        let ciphertext = cipher.encrypt(&mut language, plaintext);
+
+        let identity: &[i16] = &[1, 0, 0, 0, 1, 0, 0, 0, 1];
+        cipher.matrix.set(&mut language, identity).unwrap();
+        cipher
+            .recover_key(&mut language, plaintext, &ciphertext)
+            .unwrap();
+
+        assert_eq!(cipher.matrix.as_slice(), &[0, 11, 15, 7, 0, 1, 4, 19, 0]);
+
+        // every choice of blocks from the plaintext has an even determinant
+        assert!(cipher
+            .recover_key(
+                &mut language,
+                "AAAAAASECRETMESSAGE",
+                "AAAAAAWYSRIOCYUMCUACQ"
+            )
+            .is_err());
+    }
 }
@@ -1,3 +1,10 @@
+use crate::{
+    cipher::{monoalph, transpos},
+    key::{Keyword, PolybiusSquare},
+    lang::Language,
+    util,
+};
+
 pub mod adfgvx;
 pub mod adfgx;
 pub mod bifid;
@@ -7,3 +14,290 @@ pub mod hill;
 pub mod trifid;
 pub mod two_square;
 pub mod vic;
+
+const KEY_LEN_MAX_TEST: usize = 12;
+const ORDER_RESTARTS: usize = 10;
+const MAX_REFINEMENTS: usize = 5;
+const CANDIDATE_THRESHOLD: f64 = 0.9;
+
+/// Encrypts a message with an ADFGX style cipher. Each letter is replaced by
+/// the labels of its row and column in `square`, then the labels are put through
+/// a columnar transposition keyed by `keyword`.
+///
+/// # Arguments
+///
+/// * `language` The current language instance
+/// * `square` The Polybius square
+/// * `keyword` The transposition keyword
+/// * `labels` The label of each row/column of the square
+/// * `msg` The message to encrypt
+///
+pub fn fractionated_encrypt(
+    language: &mut Language,
+    square: &PolybiusSquare,
+    keyword: &Keyword,
+    labels: &[char],
+    msg: &str,
+) -> String {
+    language.set_alph_len(square.num_letters());
+
+    let coords = msg
+        .chars()
+        .filter_map(|ch| square.char_to_cp(language, ch))
+        .flat_map(|cp| {
+            let (row, col) = square.pos(cp);
+            vec![row as i16, col as i16]
+        })
+        .collect::<Vec<_>>();
+
+    let mut ciphertext = vec![0; coords.len()];
+    transpos::column_indexes(coords.len(), &keyword.find_order())
+        .into_iter()
+        .enumerate()
+        .for_each(|(idx, ct_idx)| ciphertext[ct_idx] = coords[idx]);
+
+    ciphertext
+        .into_iter()
+        .map(|coord| labels[coord as usize])
+        .collect()
+}
+
+/// Decrypts a message encrypted with an ADFGX style cipher. Chars in the message
+/// which are not labels are ignored.
+///
+/// # Arguments
+///
+/// * `language` The current language instance
+/// * `square` The Polybius square
+/// * `keyword` The transposition keyword
+/// * `labels` The label of each row/column of the square
+/// * `msg` The message to decrypt
+///
+pub fn fractionated_decrypt(
+    language: &mut Language,
+    square: &PolybiusSquare,
+    keyword: &Keyword,
+    labels: &[char],
+    msg: &str,
+) -> String {
+    language.set_alph_len(square.num_letters());
+
+    let ciphertext = label_coords(labels, msg);
+
+    let coords = transpos::column_indexes(ciphertext.len(), &keyword.find_order())
+        .into_iter()
+        .map(|ct_idx| ciphertext[ct_idx])
+        .collect::<Vec<_>>();
+
+    coords
+        .chunks_exact(2)
+        .map(|pair| square.cp_to_char(language, square.at(pair[0] as usize, pair[1] as usize)))
+        .collect()
+}
+
+/// Converts the labels in a message to row/col coordinates, ignoring any other chars
+///
+/// # Arguments
+///
+/// * `labels` The label of each row/column of the square
+/// * `msg` The message to convert
+///
+pub fn label_coords(labels: &[char], msg: &str) -> Vec<i16> {
+    msg.to_uppercase()
+        .chars()
+        .filter_map(|ch| labels.iter().position(|&label| label == ch))
+        .map(|pos| pos as i16)
+        .collect()
+}
+
+/// Undoes the transposition of an ADFGX style ciphertext, then combines each pair
+/// of coordinates into a single symbol (`row * dim_size + col`).
+///
+/// # Arguments
+///
+/// * `ciphertext` The ciphertext coordinates, with an even length
+/// * `key_order` The column order of the transposition
+/// * `dim_size` The dimension size of the square
+///
+fn fractionated_symbols(ciphertext: &[i16], key_order: &[usize], dim_size: usize) -> Vec<i16> {
+    let indexes = transpos::column_indexes(ciphertext.len(), key_order);
+
+    indexes
+        .chunks_exact(2)
+        .map(|pair| ciphertext[pair[0]] * dim_size as i16 + ciphertext[pair[1]])
+        .collect()
+}
+
+/// Calculates the index of coincedence of a slice of symbols, which may include
+/// more symbols than the language alphabet. If `bigrams` is set, the index of
+/// coincedence of overlapping pairs of symbols is calculated instead.
+///
+/// # Arguments
+///
+/// * `symbols` The symbols to analyse
+/// * `num_symbols` The number of distinct symbols
+/// * `bigrams` Whether to count pairs of symbols
+///
+fn symbol_ioc(symbols: &[i16], num_symbols: usize, bigrams: bool) -> f64 {
+    let mut counts;
+    if bigrams {
+        counts = vec![0; num_symbols * num_symbols];
+        symbols
+            .windows(2)
+            .for_each(|pair| counts[pair[0] as usize * num_symbols + pair[1] as usize] += 1);
+    } else {
+        counts = vec![0; num_symbols];
+        symbols.iter().for_each(|&x| counts[x as usize] += 1);
+    }
+
+    let len: usize = counts.iter().sum();
+    let total: usize = counts.iter().map(|&f| f * (f.max(1) - 1)).sum();
+
+    total as f64 / (len * (len.max(2) - 1)) as f64
+}
+
+/// Hill climbs the column order of a transposition until the score no longer improves,
+/// by swapping single columns, and pairs of adjacent columns. Returns the best score.
+///
+/// # Arguments
+///
+/// * `key_order` The column order to improve
+/// * `score` A function of type: (key_order) -> score, where a greater score is better
+///
+fn climb_order<F>(key_order: &mut [usize], mut score: F) -> f64
+where
+    F: FnMut(&[usize]) -> f64,
+{
+    let key_len = key_order.len();
+    let mut best_score = score(key_order);
+
+    let mut improved = true;
+    while improved {
+        improved = false;
+
+        for i in 0..key_len - 1 {
+            for j in i + 1..key_len {
+                key_order.swap(i, j);
+                let new_score = score(key_order);
+                if new_score > best_score {
+                    best_score = new_score;
+                    improved = true;
+                } else {
+                    key_order.swap(i, j);
+                }
+
+                if i + 1 < j && j + 1 < key_len {
+                    key_order.swap(i, j);
+                    key_order.swap(i + 1, j + 1);
+                    let new_score = score(key_order);
+                    if new_score > best_score {
+                        best_score = new_score;
+                        improved = true;
+                    } else {
+                        key_order.swap(i, j);
+                        key_order.swap(i + 1, j + 1);
+                    }
+                }
+            }
+        }
+    }
+
+    best_score
+}
+
+/// Solves an ADFGX style cipher. For each key length, the transposition is first found
+/// by maximising the index of coincedence of the symbols formed from pairs of coordinates
+/// (which is high when the coordinates are correctly paired), then of pairs of symbols (which
+/// is high when the pairs are in the right order). The substitution is solved for the most
+/// likely key lengths, and the column order refined using the quadgram score of the plaintext.
+/// Returns the best column order and square.
+///
+/// # Arguments
+///
+/// * `ciphertext` The ciphertext coordinates
+/// * `dim_size` The dimension size of the square
+/// * `language` The current language instance, using the alphabet of the square
+///
+pub fn fractionated_solve(
+    ciphertext: &[i16],
+    dim_size: usize,
+    language: &Language,
+) -> (Vec<usize>, Vec<i16>) {
+    let num_symbols = dim_size * dim_size;
+    let ciphertext = &ciphertext[..ciphertext.len() - ciphertext.len() % 2];
+
+    let statistic = |order: &[usize]| {
+        let symbols = fractionated_symbols(ciphertext, order, dim_size);
+        symbol_ioc(&symbols, num_symbols, false) + symbol_ioc(&symbols, num_symbols, true)
+    };
+
+    // find the most likely column order for each key length
+    let mut candidates = Vec::new();
+    for key_len in 2..=KEY_LEN_MAX_TEST.min(ciphertext.len() / 2) {
+        let mut best_order = Vec::new();
+        let mut best_ioc = f64::MIN;
+
+        for _ in 0..ORDER_RESTARTS {
+            let mut order = (0..key_len).collect::<Vec<_>>();
+            util::shuffle(&mut order);
+
+            let ioc = climb_order(&mut order, statistic);
+
+            if ioc > best_ioc {
+                best_ioc = ioc;
+                best_order = order;
+            }
+        }
+
+        candidates.push((best_ioc, best_order));
+    }
+    candidates.sort_by(|a, b| b.0.partial_cmp(&a.0).unwrap());
+
+    let mut best_score = f64::MIN;
+    let mut best_order = vec![0];
+    let mut best_square = (0..num_symbols as i16).collect::<Vec<_>>();
+
+    // the ciphertext is too short to test any key length
+    if candidates.is_empty() {
+        return (best_order, best_square);
+    }
+
+    let penalty = monoalph::unknown_symbol_penalty(language);
+    let plaintext_score = |symbols: &[i16], inv_key: &[i16]| {
+        monoalph::substitution_score(symbols, inv_key, penalty, language)
+    };
+
+    // only keep key lengths with an index of coincedence close to the best
+    let max_ioc = candidates[0].0;
+    for (_, mut key_order) in candidates
+        .into_iter()
+        .take_while(|(ioc, _)| *ioc >= max_ioc * CANDIDATE_THRESHOLD)
+    {
+        // solve the substitution, then improve the order of the columns
+        let mut symbols = fractionated_symbols(ciphertext, &key_order, dim_size);
+        let mut inv_key = monoalph::substitution_solve(&symbols, num_symbols, language);
+        let mut score = plaintext_score(&symbols, &inv_key);
+
+        for _ in 0..MAX_REFINEMENTS {
+            let new_score = climb_order(&mut key_order, |order| {
+                plaintext_score(&fractionated_symbols(ciphertext, order, dim_size), &inv_key)
+            });
+
+            if new_score - score < 0.1 {
+                break;
+            }
+
+            symbols = fractionated_symbols(ciphertext, &key_order, dim_size);
+            inv_key = monoalph::substitution_solve(&symbols, num_symbols, language);
+            score = plaintext_score(&symbols, &inv_key);
+        }
+
+        if score > best_score {
+            best_score = score;
+            best_order = key_order;
+            best_square = inv_key;
+        }
+    }
+
+    (best_order, best_square)
+}
@@ -1 +1,393 @@
+use crate::{
+    cipher::{self, Asymmetric, Keyed, Solve},
+    key::{Alphabet, IdentityKey, IoKey, Key, Number},
+    lang::{LangAlphabet, Language, ScoreSize},
+    util,
+};
 
+/// The Trifid cube is 3x3x3, so uses a 27 letter alphabet
+const ALPHABET_LEN: usize = 27;
+const DIM_SIZE: usize = 3;
+/// Added to the standard alphabet when the language has no 27 letter alphabet
+const EXTRA_SYMBOL: char = '+';
+const MAX_PERIOD: i16 = 20;
+
+/// The Trifid cipher. The cube is stored as an [`Alphabet`] of 27 symbols, filled layer by
+/// layer, then row by row. The layer, row and column of each letter in a block of `period`
+/// letters are written beneath each other, then read off in threes to find the ciphertext
+/// letters. A period of 0 treats the whole message as a single block.
+///
+/// If the language has no 27 letter alphabet, one is added to it by appending `+` to
+/// the standard alphabet. The added alphabet stays in the language.
+///
+pub struct Trifid {
+    pub cube: Alphabet,
+    pub period: Number,
+}
+
+impl Trifid {
+    /// Selects the 27 letter alphabet. If the language does not have one, it is created
+    /// by adding `+` to the end of the standard alphabet. The new alphabet is kept by the
+    /// language, so other ciphers using 27 letters will use it too, until it is removed
+    /// with [`Language::del_alphabet`].
+    ///
+    /// # Arguments
+    ///
+    /// * `language` A [`Language`] instance
+    ///
+    fn use_alphabet(language: &mut Language) {
+        if !language.set_alph_len(ALPHABET_LEN) {
+            language.set_alph_len(ALPHABET_LEN - 1);
+
+            let upper = (0..language.cp_count())
+                .map(|cp| language.cp_to_upper(cp))
+                .chain(std::iter::once(EXTRA_SYMBOL))
+                .collect();
+            let lower = (0..language.cp_count())
+                .map(|cp| language.cp_to_lower(cp))
+                .chain(std::iter::once(EXTRA_SYMBOL))
+                .collect();
+
+            language.add_alphabet(
+                LangAlphabet::new(
+                    upper,
+                    lower,
+                    vec![],
+                    vec![],
+                    (0..ALPHABET_LEN as i16).collect(),
+                )
+                .expect("the standard alphabet should not contain the extra symbol"),
+            );
+            language.set_alph_len(ALPHABET_LEN);
+        }
+    }
+
+    /// Finds the length of each block of the message, where the final block may be shorter
+    ///
+    /// # Arguments
+    ///
+    /// * `len` The length of the message
+    /// * `period` The period, or 0 for a single block
+    ///
+    fn block_lens(len: usize, period: usize) -> impl Iterator<Item = usize> {
+        let period = if period == 0 { len.max(1) } else { period };
+        (0..len)
+            .step_by(period)
+            .map(move |start| period.min(len - start))
+    }
+
+    /// Encrypts `src` into `dest` by writing the coordinates of each block beneath
+    /// each other, then reading them off in threes
+    ///
+    /// # Arguments
+    ///
+    /// * `cube` The cube, layer by layer
+    /// * `inverse` The inverse of `cube`, mapping letter -> position
+    /// * `period` The period, or 0 for a single block
+    /// * `src` The plaintext
+    /// * `dest` Where to store the ciphertext, with the same length as `src`
+    ///
+    fn encrypt_cps(cube: &[i16], inverse: &[i16], period: usize, src: &[i16], dest: &mut [i16]) {
+        let mut coords = Vec::with_capacity(period.max(src.len()) * DIM_SIZE);
+        let mut start = 0;
+
+        for len in Trifid::block_lens(src.len(), period) {
+            let block = &src[start..start + len];
+
+            coords.clear();
+            for divisor in [DIM_SIZE * DIM_SIZE, DIM_SIZE, 1] {
+                coords.extend(
+                    block
+                        .iter()
+                        .map(|&cp| inverse[cp as usize] as usize / divisor % DIM_SIZE),
+                );
+            }
+
+            for (i, coord) in coords.chunks_exact(DIM_SIZE).enumerate() {
+                dest[start + i] = cube[(coord[0] * DIM_SIZE + coord[1]) * DIM_SIZE + coord[2]];
+            }
+
+            start += len;
+        }
+    }
+
+    /// Decrypts `src` into `dest`, by reading the coordinates of each block in threes,
+    /// then splitting them into the layers, rows and columns of the plaintext
+    ///
+    /// # Arguments
+    ///
+    /// * `cube` The cube, layer by layer
+    /// * `inverse` The inverse of `cube`, mapping letter -> position
+    /// * `period` The period, or 0 for a single block
+    /// * `src` The ciphertext
+    /// * `dest` Where to store the plaintext, with the same length as `src`
+    ///
+    fn decrypt_cps(cube: &[i16], inverse: &[i16], period: usize, src: &[i16], dest: &mut [i16]) {
+        let mut coords = Vec::with_capacity(period.max(src.len()) * DIM_SIZE);
+        let mut start = 0;
+
+        for len in Trifid::block_lens(src.len(), period) {
+            coords.clear();
+            src[start..start + len].iter().for_each(|&cp| {
+                let pos = inverse[cp as usize] as usize;
+                coords.push(pos / (DIM_SIZE * DIM_SIZE));
+                coords.push(pos / DIM_SIZE % DIM_SIZE);
+                coords.push(pos % DIM_SIZE);
+            });
+
+            for i in 0..len {
+                dest[start + i] =
+                    cube[(coords[i] * DIM_SIZE + coords[len + i]) * DIM_SIZE + coords[2 * len + i]];
+            }
+
+            start += len;
+        }
+    }
+
+    /// Makes a random change to a cube, used by the solver. Mostly swaps two
+    /// letters, but occasionally swaps two layers, two rows or two columns.
+    ///
+    /// # Arguments
+    ///
+    /// * `cube` The cube to modify
+    ///
+    fn modify_cube(cube: &mut [i16]) {
+        let a = fastrand::usize(0..DIM_SIZE);
+        let b = fastrand::usize(0..DIM_SIZE);
+
+        // the distance between cells which differ only in the layer, row or column
+        let stride = match fastrand::usize(0..20) {
+            0 => DIM_SIZE * DIM_SIZE,
+            1 => DIM_SIZE,
+            2 => 1,
+            _ => {
+                cube.swap(
+                    fastrand::usize(0..ALPHABET_LEN),
+                    fastrand::usize(0..ALPHABET_LEN),
+                );
+                return;
+            }
+        };
+
+        (0..ALPHABET_LEN)
+            .filter(|pos| pos / stride % DIM_SIZE == a)
+            .for_each(|pos| cube.swap(pos, pos - a * stride + b * stride));
+    }
+}
+
+impl Asymmetric for Trifid {
+    fn encrypt(&self, language: &mut Language, msg: &str) -> String {
+        Trifid::use_alphabet(language);
+
+        let plaintext = language.string_to_vec(msg);
+        let mut ciphertext = vec![0; plaintext.len()];
+
+        Trifid::encrypt_cps(
+            self.cube.as_slice(),
+            &util::invert(self.cube.as_slice()),
+            self.period.get() as usize,
+            &plaintext,
+            &mut ciphertext,
+        );
+
+        language.vec_to_string(&ciphertext)
+    }
+    fn decrypt(&self, language: &mut Language, msg: &str) -> String {
+        Trifid::use_alphabet(language);
+
+        let ciphertext = language.string_to_vec(msg);
+        let mut plaintext = vec![0; ciphertext.len()];
+
+        Trifid::decrypt_cps(
+            self.cube.as_slice(),
+            &util::invert(self.cube.as_slice()),
+            self.period.get() as usize,
+            &ciphertext,
+            &mut plaintext,
+        );
+
+        language.vec_to_string(&plaintext)
+    }
+}
+
+impl Keyed for Trifid {
+    fn new(language: &mut Language) -> Trifid {
+        Trifid::use_alphabet(language);
+
+        let mut result = Trifid {
+            cube: Alphabet::identity(language),
+            period: *Number::new(language, 5).unwrap(),
+        };
+
+        result.period.set_legal_values((0..=MAX_PERIOD).collect());
+        result.cube.info_mut().set("Cube", "cube");
+        result.period.info_mut().set("Period", "period");
+
+        result
+    }
+    fn keys(&self) -> Vec<&dyn IoKey> {
+        vec![&self.cube, &self.period]
+    }
+    fn keys_mut(&mut self) -> Vec<&mut dyn IoKey> {
+        vec![&mut self.cube, &mut self.period]
+    }
+}
+
+const TEMP_STEPS: usize = 100;
+const ITERATIONS: usize = 10000;
+const RANK_ITERATIONS: usize = 2000;
+const RANK_RESTARTS: usize = 2;
+const MAX_RESTARTS: usize = 5;
+const PERIOD_CANDIDATES: usize = 3;
+
+impl Solve for Trifid {
+    fn solve(&mut self, language: &mut Language, msg: &str) {
+        Trifid::use_alphabet(language);
+
+        let ciphertext = language.string_to_vec(msg);
+        let mut plaintext = vec![0; ciphertext.len()];
+        let mut inverse = (0..ALPHABET_LEN as i16).collect::<Vec<_>>();
+
+        // the difference in score between keys grows with the message length
+        let start_temp = (ciphertext.len() as f64 / 15.0).max(5.0);
+
+        // each plaintext letter depends on three ciphertext letters, so quadgrams give
+        // little guidance until most of the cube is correct, unlike bigrams
+        let mut anneal_cube = |period: usize, iterations: usize| {
+            let mut cube = (0..ALPHABET_LEN as i16).collect::<Vec<_>>();
+            util::shuffle(&mut cube);
+
+            let score = cipher::anneal(
+                &mut cube,
+                start_temp,
+                TEMP_STEPS,
+                iterations,
+                |cube| {
+                    cube.iter()
+                        .enumerate()
+                        .for_each(|(pos, &cp)| inverse[cp as usize] = pos as i16);
+                    Trifid::decrypt_cps(cube, &inverse, period, &ciphertext, &mut plaintext);
+                    language.score_iter(plaintext.iter().copied(), ScoreSize::Bigrams)
+                },
+                |cube| Trifid::modify_cube(cube),
+            );
+
+            (score, cube)
+        };
+
+        // rank the periods with a short search for each
+        let mut periods = (DIM_SIZE..=MAX_PERIOD as usize)
+            .filter(|&period| period < ciphertext.len())
+            .chain(std::iter::once(0))
+            .map(|period| {
+                let score = (0..RANK_RESTARTS)
+                    .map(|_| anneal_cube(period, RANK_ITERATIONS).0)
+                    .fold(f64::MIN, f64::max);
+                (score, period)
+            })
+            .collect::<Vec<_>>();
+        periods.sort_by(|a, b| b.0.partial_cmp(&a.0).unwrap());
+
+        let mut best_cube = Vec::new();
+        let mut best_period = 0;
+        let mut best_score = f64::MIN;
+
+        for (_, period) in periods.into_iter().take(PERIOD_CANDIDATES) {
+            let mut period_cube = Vec::new();
+            let mut period_score = f64::MIN;
+
+            for _ in 0..MAX_RESTARTS {
+                let (score, cube) = anneal_cube(period, ITERATIONS);
+
+                // the same best score from separate runs is likely to be the solution
+                if (score - period_score).abs() < 0.1 {
+                    break;
+                }
+
+                if score > period_score {
+                    period_score = score;
+                    period_cube = cube;
+                }
+            }
+
+            // compare the periods using quadgrams
+            let mut plaintext = vec![0; ciphertext.len()];
+            Trifid::decrypt_cps(
+                &period_cube,
+                &util::invert(&period_cube),
+                period,
+                &ciphertext,
+                &mut plaintext,
+            );
+            let score = language.score_iter(plaintext.into_iter(), ScoreSize::Quadgrams);
+
+            if score > best_score {
+                best_score = score;
+                best_cube = period_cube;
+                best_period = period;
+            }
+        }
+
+        self.period.set(language, best_period as i16).unwrap();
+        self.cube.set(language, best_cube.as_slice()).unwrap();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn encrypt() {
+        let mut language = Language::from_file("examples/data/english.bin").unwrap();
+        let mut cipher = Trifid::new(&mut language);
+        cipher
+            .cube
+            .set(&mut language, "felix marie delastelle")
+            .unwrap();
+
+        let ciphertext = cipher.encrypt(&mut language, "Aide-toi, le ciel t'aidera");
+
+        assert_eq!(ciphertext, "FMJFVOISSUFTFPUFEQQC");
+    }
+
+    #[test]
+    fn decrypt() {
+        let mut language = Language::from_file("examples/data/english.bin").unwrap();
+        let mut cipher = Trifid::new(&mut language);
+        cipher
+            .cube
+            .set(&mut language, "felix marie delastelle")
+            .unwrap();
+
+        let plaintext = cipher.decrypt(&mut language, "FMJFVOISSUFTFPUFEQQC");
+
+        assert_eq!(plaintext, "AIDETOILECIELTAIDERA");
+    }
+
+    #[test]
+    fn solve() {
+        let mut language = Language::from_file("examples/data/english.bin").unwrap();
+        let mut cipher = Trifid::new(&mut language);
+        cipher
+            .cube
+            .set(&mut language, "felix marie delastelle")
+            .unwrap();
+
+        let ciphertext = cipher.encrypt(&mut language, PLAINTEXT);
+        let plaintext = cipher.decrypt(&mut language, &ciphertext);
+
+        fastrand::seed(1);
+        let mut solved = Trifid::new(&mut language);
+        solved.solve(&mut language, &ciphertext);
+
+        assert_eq!(solved.decrypt(&mut language, &ciphertext), plaintext);
+    }
+
+    const PLAINTEXT: &str = "There was no possibility of taking a walk that day. We had \
+        been wandering, indeed, in the leafless shrubbery an hour in the morning; but since \
+        dinner the cold winter wind had brought with it clouds so sombre, and a rain so \
+        penetrating, that further outdoor exercise was now out of the question. I was glad \
+        of it: I never liked long walks, especially on chilly afternoons: dreadful to me \
+        was the coming home in the raw twilight, with nipped fingers and toes.";
+}
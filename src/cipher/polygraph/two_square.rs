@@ -1 +1,295 @@
+use crate::{
+    cipher::{self, Asymmetric, Keyed, Solve},
+    key::{IdentityKey, IoKey, Key, PolybiusSquare},
+    lang::{Language, ScoreSize},
+    util,
+};
 
+/// The Two-Square squares are 5x5, so use the 25 letter alphabet
+const ALPHABET_LEN: usize = 25;
+const DIM_SIZE: usize = 5;
+
+/// How the two squares of a Two-Square cipher are arranged
+///
+#[derive(Clone, Copy)]
+pub enum TwoSquareOrientation {
+    /// The first square is to the left of the second. Digraphs with both letters in
+    /// the same row are unchanged.
+    Horizontal,
+    /// The first square is above the second. Digraphs with both letters in the same
+    /// column are unchanged.
+    Vertical,
+}
+
+/// The Two-Square cipher. The first letter of each digraph is found in the first
+/// square, and the second letter in the second square. The ciphertext letters are
+/// the other corners of the rectangle they form, taking the letter from the first
+/// square first. Since this is its own inverse, encryption and decryption are identical.
+///
+pub struct TwoSquare {
+    pub square1: PolybiusSquare,
+    pub square2: PolybiusSquare,
+    /// Pads messages of odd length
+    pub padding: char,
+    pub orientation: TwoSquareOrientation,
+}
+
+impl TwoSquare {
+    /// Substitutes each digraph of `src` into `dest`
+    ///
+    /// # Arguments
+    ///
+    /// * `squares` The first square followed by the second, each row by row
+    /// * `inverse` The inverse of each square, mapping letter -> position
+    /// * `orientation` How the squares are arranged
+    /// * `src` The digraphs to substitute
+    /// * `dest` Where to store the result, with the same length as `src`
+    ///
+    fn run_digraphs(
+        squares: &[i16],
+        inverse: &[i16],
+        orientation: TwoSquareOrientation,
+        src: &[i16],
+        dest: &mut [i16],
+    ) {
+        let (square1, square2) = squares.split_at(ALPHABET_LEN);
+        let (inverse1, inverse2) = inverse.split_at(ALPHABET_LEN);
+
+        for i in (0..src.len() - src.len() % 2).step_by(2) {
+            let pos1 = inverse1[src[i] as usize] as usize;
+            let pos2 = inverse2[src[i + 1] as usize] as usize;
+
+            let (row1, col1) = (pos1 / DIM_SIZE, pos1 % DIM_SIZE);
+            let (row2, col2) = (pos2 / DIM_SIZE, pos2 % DIM_SIZE);
+
+            let (pos1, pos2) = match orientation {
+                TwoSquareOrientation::Horizontal if row1 == row2 => (pos1, pos2),
+                TwoSquareOrientation::Vertical if col1 == col2 => (pos1, pos2),
+                TwoSquareOrientation::Horizontal => {
+                    (row2 * DIM_SIZE + col1, row1 * DIM_SIZE + col2)
+                }
+                TwoSquareOrientation::Vertical => (row1 * DIM_SIZE + col2, row2 * DIM_SIZE + col1),
+            };
+
+            dest[i] = square1[pos1];
+            dest[i + 1] = square2[pos2];
+        }
+    }
+
+    /// Substitutes each digraph of a message, padding it to an even length
+    ///
+    /// # Arguments
+    ///
+    /// * `language` A [`Language`] instance
+    /// * `msg` The message to substitute
+    ///
+    fn run(&self, language: &mut Language, msg: &str) -> String {
+        language.set_alph_len(ALPHABET_LEN);
+
+        let mut src = language.string_to_vec(msg);
+        if src.len() % 2 == 1 {
+            src.push(language.get_cp(&self.padding));
+        }
+        let mut dest = vec![0; src.len()];
+
+        let squares = [self.square1.as_slice(), self.square2.as_slice()].concat();
+        TwoSquare::run_digraphs(
+            &squares,
+            &TwoSquare::invert(&squares),
+            self.orientation,
+            &src,
+            &mut dest,
+        );
+
+        language.vec_to_string(&dest)
+    }
+
+    /// Finds the inverse of each square, mapping letter -> position
+    ///
+    /// # Arguments
+    ///
+    /// * `squares` The first square followed by the second, each row by row
+    ///
+    fn invert(squares: &[i16]) -> Vec<i16> {
+        let mut inverse = vec![0; squares.len()];
+        TwoSquare::invert_into(squares, &mut inverse);
+        inverse
+    }
+
+    /// Finds the inverse of each square, storing it in `inverse`
+    ///
+    /// # Arguments
+    ///
+    /// * `squares` The first square followed by the second, each row by row
+    /// * `inverse` Where to store the inverse, with the same length as `squares`
+    ///
+    fn invert_into(squares: &[i16], inverse: &mut [i16]) {
+        for (square, inverse) in squares
+            .chunks_exact(ALPHABET_LEN)
+            .zip(inverse.chunks_exact_mut(ALPHABET_LEN))
+        {
+            square
+                .iter()
+                .enumerate()
+                .for_each(|(pos, &cp)| inverse[cp as usize] = pos as i16);
+        }
+    }
+
+    /// Makes a random change to the squares, used by the solver. Mostly swaps two
+    /// letters of one square, but occasionally swaps two columns of both squares
+    /// (vertical) or two rows of both squares (horizontal), which keeps the letters
+    /// found in the same line of each square together.
+    ///
+    /// # Arguments
+    ///
+    /// * `squares` The first square followed by the second, each row by row
+    /// * `orientation` How the squares are arranged
+    ///
+    fn modify_squares(squares: &mut [i16], orientation: TwoSquareOrientation) {
+        if fastrand::usize(0..20) == 0 {
+            let a = fastrand::usize(0..DIM_SIZE);
+            let b = fastrand::usize(0..DIM_SIZE);
+
+            for square in squares.chunks_exact_mut(ALPHABET_LEN) {
+                match orientation {
+                    TwoSquareOrientation::Horizontal => (0..DIM_SIZE)
+                        .for_each(|col| square.swap(a * DIM_SIZE + col, b * DIM_SIZE + col)),
+                    TwoSquareOrientation::Vertical => (0..DIM_SIZE)
+                        .for_each(|row| square.swap(row * DIM_SIZE + a, row * DIM_SIZE + b)),
+                }
+            }
+        } else {
+            let offset = fastrand::usize(0..2) * ALPHABET_LEN;
+            squares.swap(
+                offset + fastrand::usize(0..ALPHABET_LEN),
+                offset + fastrand::usize(0..ALPHABET_LEN),
+            );
+        }
+    }
+}
+
+impl Asymmetric for TwoSquare {
+    fn encrypt(&self, language: &mut Language, msg: &str) -> String {
+        self.run(language, msg)
+    }
+    fn decrypt(&self, language: &mut Language, msg: &str) -> String {
+        self.run(language, msg)
+    }
+}
+
+impl Keyed for TwoSquare {
+    fn new(language: &mut Language) -> TwoSquare {
+        language.set_alph_len(ALPHABET_LEN);
+
+        let mut result = TwoSquare {
+            square1: PolybiusSquare::identity(language),
+            square2: PolybiusSquare::identity(language),
+            padding: 'X',
+            orientation: TwoSquareOrientation::Vertical,
+        };
+
+        result.square1.info_mut().set("Square 1", "sq1");
+        result.square2.info_mut().set("Square 2", "sq2");
+
+        result
+    }
+    fn keys(&self) -> Vec<&dyn IoKey> {
+        vec![&self.square1, &self.square2]
+    }
+    fn keys_mut(&mut self) -> Vec<&mut dyn IoKey> {
+        vec![&mut self.square1, &mut self.square2]
+    }
+}
+
+const TEMP_STEPS: usize = 100;
+const ITERATIONS: usize = 20000;
+const MAX_RESTARTS: usize = 5;
+
+impl Solve for TwoSquare {
+    fn solve(&mut self, language: &mut Language, msg: &str) {
+        language.set_alph_len(ALPHABET_LEN);
+
+        let ciphertext = language.string_to_vec(msg);
+        let ciphertext = &ciphertext[..ciphertext.len() - ciphertext.len() % 2];
+        let mut plaintext = vec![0; ciphertext.len()];
+        let mut inverse = vec![0; 2 * ALPHABET_LEN];
+
+        // the difference in score between keys grows with the message length
+        let start_temp = (ciphertext.len() as f64 / 15.0).max(5.0);
+
+        let mut best_squares = Vec::new();
+        let mut best_score = f64::MIN;
+
+        for _ in 0..MAX_RESTARTS {
+            let mut squares = (0..2 * ALPHABET_LEN)
+                .map(|i| (i % ALPHABET_LEN) as i16)
+                .collect::<Vec<_>>();
+            squares
+                .chunks_exact_mut(ALPHABET_LEN)
+                .for_each(util::shuffle);
+
+            let score = cipher::anneal(
+                &mut squares,
+                start_temp,
+                TEMP_STEPS,
+                ITERATIONS,
+                |squares| {
+                    TwoSquare::invert_into(squares, &mut inverse);
+                    TwoSquare::run_digraphs(
+                        squares,
+                        &inverse,
+                        self.orientation,
+                        ciphertext,
+                        &mut plaintext,
+                    );
+                    language.score(&plaintext, ScoreSize::Quadgrams)
+                },
+                |squares| TwoSquare::modify_squares(squares, self.orientation),
+            );
+
+            // the same best score from separate runs is likely to be the solution
+            if (score - best_score).abs() < 0.1 {
+                break;
+            }
+
+            if score > best_score {
+                best_score = score;
+                best_squares = squares;
+            }
+        }
+
+        let (square1, square2) = best_squares.split_at(ALPHABET_LEN);
+        self.square1.set(language, square1).unwrap();
+        self.square2.set(language, square2).unwrap();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn encrypt() {
+        let mut language = Language::from_file("examples/data/english.bin").unwrap();
+        let mut cipher = TwoSquare::new(&mut language);
+        cipher.square1.set(&mut language, "example").unwrap();
+        cipher.square2.set(&mut language, "keyword").unwrap();
+
+        let ciphertext = cipher.encrypt(&mut language, "Help me Obi Wan Kenobi");
+
+        assert_eq!(ciphertext, "HECMXWSRKYXPHWNODG");
+    }
+
+    #[test]
+    fn decrypt() {
+        let mut language = Language::from_file("examples/data/english.bin").unwrap();
+        let mut cipher = TwoSquare::new(&mut language);
+        cipher.square1.set(&mut language, "example").unwrap();
+        cipher.square2.set(&mut language, "keyword").unwrap();
+        cipher.orientation = TwoSquareOrientation::Horizontal;
+
+        let plaintext = cipher.decrypt(&mut language, "XGOAMELQAIREMGPLHB");
+
+        assert_eq!(plaintext, "HELPMEOBIWANKENOBI");
+    }
+}
@@ -1 +1,504 @@
+use crate::{
+    cipher::{Asymmetric, Keyed},
+    key::{Digits, IdentityKey, IoKey, Key, Keyword, Number, StraddleCheckerboard},
+    lang::Language,
+    util,
+};
 
+/// The checkerboard holds the 26 letter alphabet
+const ALPHABET_LEN: usize = 26;
+/// The number of letters of the phrase used to derive the keys
+const PHRASE_LEN: usize = 20;
+/// The length of each half of the phrase, and of each line of derived digits
+const LINE_LEN: usize = 10;
+/// The number of lines of digits generated by chain addition
+const NUM_LINES: usize = 5;
+const DATE_LEN: usize = 6;
+const GROUP_LEN: usize = 5;
+/// Each digit of a number is written this many times, to tell it apart from the
+/// figure shift which ends the number
+const FIGURE_REPEATS: usize = 3;
+/// Keeps the two transpositions within the 50 digits they are read from
+const MAX_PERSONAL_NUMBER: i16 = 16;
+
+/// The keys derived for a single message
+///
+struct MessageKeys {
+    /// The digits labelling the columns of the checkerboard
+    col_digits: Vec<i16>,
+    /// The column order of the first (columnar) transposition
+    transpos1: Vec<usize>,
+    /// The column order of the second (disrupted) transposition
+    transpos2: Vec<usize>,
+}
+
+/// The VIC cipher, as used by Reino Häyhänen. For each message, a checkerboard
+/// labelling and two transposition keys are derived from the phrase, the date, the
+/// personal number and a keygroup chosen for the message. The plaintext is encoded
+/// with the straddling checkerboard, then put through a columnar transposition and
+/// a disrupted transposition. The keygroup is then inserted into the ciphertext, at
+/// the group given by the last digit of the date, counting from the end.
+///
+/// Numbers are written between two figure shifts, with each digit repeated three
+/// times. The ciphertext is padded to a whole number of groups with figure shifts.
+/// When decrypting, the keygroup is read from the ciphertext rather than from the key.
+///
+pub struct Vic {
+    /// The first 20 letters are used, so the phrase is repeated if it is shorter
+    pub phrase: Keyword,
+    /// The day, month and year, such as `3/9/1945` for 3 September 1945
+    pub date: Digits,
+    pub personal_number: Number,
+    /// Chosen at random for each message
+    pub keygroup: Digits,
+    /// The arrangement of the letters. The digits labelling the columns are derived
+    /// from the other keys.
+    pub checkerboard: StraddleCheckerboard,
+}
+
+impl Vic {
+    /// Subtracts each digit of `b` from `a` without borrowing
+    ///
+    /// # Arguments
+    ///
+    /// * `a` The digits to subtract from
+    /// * `b` The digits to subtract
+    ///
+    fn sub_digits(a: &[i16], b: &[i16]) -> Vec<i16> {
+        a.iter().zip(b).map(|(x, y)| (x - y + 10) % 10).collect()
+    }
+
+    /// Extends `digits` by chain addition (a lagged Fibonacci generator), where each
+    /// new digit is the sum of the digit `digits.len()` places before it and the digit
+    /// after that, without carrying. Returns only the `count` new digits.
+    ///
+    /// # Arguments
+    ///
+    /// * `digits` The digits to start from
+    /// * `count` The number of digits to generate
+    ///
+    fn chain_add(digits: &[i16], count: usize) -> Vec<i16> {
+        let mut result = digits.to_vec();
+
+        for i in 0..count {
+            result.push((result[i] + result[i + 1]) % 10);
+        }
+
+        result.split_off(digits.len())
+    }
+
+    /// Finds the order of a key, where ties are ordered from left to right
+    ///
+    /// # Arguments
+    ///
+    /// * `key` The key to order
+    /// * `zero_last` Whether `0` comes after `9`, as when ordering digits
+    ///
+    fn key_order(key: &[i16], zero_last: bool) -> Vec<usize> {
+        util::find_order(
+            &key.iter()
+                .map(|&x| if zero_last && x == 0 { 10 } else { x })
+                .collect::<Vec<_>>(),
+        )
+    }
+
+    /// Sequentializes a key of up to 10 items, numbering the items `1-9` then `0`
+    /// in order
+    ///
+    /// # Arguments
+    ///
+    /// * `key` The key to sequentialize
+    /// * `zero_last` Whether `0` comes after `9`, as when ordering digits
+    ///
+    fn sequentialize(key: &[i16], zero_last: bool) -> Vec<i16> {
+        Vic::key_order(key, zero_last)
+            .into_iter()
+            .map(|pos| ((pos + 1) % 10) as i16)
+            .collect()
+    }
+
+    /// Derives the keys for a message from the keygroup and the other keys
+    ///
+    /// # Arguments
+    ///
+    /// * `keygroup` The keygroup of the message
+    ///
+    fn message_keys(&self, keygroup: &[i16]) -> MessageKeys {
+        let phrase = (0..PHRASE_LEN)
+            .map(|i| self.phrase.at(i % self.phrase.len()))
+            .collect::<Vec<_>>();
+
+        // line C: the keygroup minus the start of the date, extended to 10 digits
+        let mut line_c = Vic::sub_digits(keygroup, &self.date.as_slice()[..GROUP_LEN]);
+        line_c.extend(Vic::chain_add(&line_c, LINE_LEN - GROUP_LEN));
+
+        // line E: each half of the phrase, sequentialized
+        let line_e1 = Vic::sequentialize(&phrase[..LINE_LEN], false);
+        let line_e2 = Vic::sequentialize(&phrase[LINE_LEN..], false);
+
+        // line G: line C added to the first half of line E
+        let line_g = line_c
+            .iter()
+            .zip(&line_e1)
+            .map(|(x, y)| (x + y) % 10)
+            .collect::<Vec<_>>();
+
+        // line H: line G, where each digit `1-9, 0` is replaced by the digit in the
+        // same position in the second half of line E
+        let line_h = line_g
+            .iter()
+            .map(|&x| line_e2[(x as usize + 9) % 10])
+            .collect::<Vec<_>>();
+
+        // lines K-P: 50 digits by chain addition from line H
+        let block = Vic::chain_add(&line_h, LINE_LEN * NUM_LINES);
+        let line_p = &block[LINE_LEN * (NUM_LINES - 1)..];
+
+        // the transposition lengths come from the last two unequal digits of line P
+        let last = line_p[LINE_LEN - 1];
+        let second_last = line_p.iter().rev().find(|&&x| x != last).unwrap_or(&last);
+        let personal_number = self.personal_number.get() as usize;
+        let len1 = personal_number + *second_last as usize;
+        let len2 = personal_number + last as usize;
+
+        // the transposition keys are read from the columns of lines K-P, in the order
+        // of line H
+        let col_order = Vic::key_order(&line_h, true);
+        let mut keys = (0..LINE_LEN).flat_map(|pos| {
+            let col = col_order.iter().position(|&x| x == pos).unwrap();
+            block.iter().skip(col).step_by(LINE_LEN).copied()
+        });
+        let key1 = keys.by_ref().take(len1).collect::<Vec<_>>();
+        let key2 = keys.take(len2).collect::<Vec<_>>();
+
+        MessageKeys {
+            col_digits: Vic::sequentialize(line_p, true),
+            transpos1: Vic::key_order(&key1, true),
+            transpos2: Vic::key_order(&key2, true),
+        }
+    }
+
+    /// Finds the order in which the cells of a transposition block are read, column by
+    /// column in the order of the key
+    ///
+    /// # Arguments
+    ///
+    /// * `len` The number of cells in the block
+    /// * `key_order` The order of the columns
+    ///
+    fn columnar_cells(len: usize, key_order: &[usize]) -> Vec<usize> {
+        let width = key_order.len();
+
+        (0..width)
+            .flat_map(|pos| {
+                let col = key_order.iter().position(|&x| x == pos).unwrap();
+                (col..len).step_by(width)
+            })
+            .collect()
+    }
+
+    /// Finds the order in which the cells of a disrupted transposition block are
+    /// written. The block contains triangular areas, the first starting at the top of
+    /// the first column in the key order, and extending to the end of the row. Each row
+    /// after, the triangle starts one column further to the right, until it is empty.
+    /// The next triangle then starts in the row below, at the next column in the key
+    /// order. The cells outside of the triangles are written first, row by row, followed
+    /// by the cells inside the triangles.
+    ///
+    /// # Arguments
+    ///
+    /// * `len` The number of cells in the block
+    /// * `key_order` The order of the columns
+    ///
+    fn disrupted_cells(len: usize, key_order: &[usize]) -> Vec<usize> {
+        let width = key_order.len();
+        let num_rows = len.div_ceil(width);
+
+        let mut in_triangle = vec![false; num_rows * width];
+        let mut row = 0;
+        for pos in 0..width {
+            let mut start = key_order.iter().position(|&x| x == pos).unwrap();
+            while row < num_rows && start <= width {
+                (start..width).for_each(|col| in_triangle[row * width + col] = true);
+                row += 1;
+                start += 1;
+            }
+        }
+
+        let outside = (0..len).filter(|&cell| !in_triangle[cell]);
+        let inside = (0..len).filter(|&cell| in_triangle[cell]);
+        outside.chain(inside).collect()
+    }
+
+    /// Writes `src` into the cells of a block in the order of `write_cells`, then reads
+    /// them in the order of `read_cells`
+    ///
+    /// # Arguments
+    ///
+    /// * `src` The digits to transpose
+    /// * `write_cells` The order in which the cells are written
+    /// * `read_cells` The order in which the cells are read
+    ///
+    fn transpose(src: &[i16], write_cells: &[usize], read_cells: &[usize]) -> Vec<i16> {
+        let mut block = vec![0; src.len()];
+        write_cells
+            .iter()
+            .zip(src)
+            .for_each(|(&cell, &digit)| block[cell] = digit);
+        read_cells.iter().map(|&cell| block[cell]).collect()
+    }
+
+    /// Finds the index of the group holding the keygroup, in a message of `num_groups`
+    /// groups including the keygroup
+    ///
+    /// # Arguments
+    ///
+    /// * `num_groups` The number of groups in the message
+    ///
+    fn keygroup_pos(&self, num_groups: usize) -> usize {
+        let from_end = self.date.as_slice()[DATE_LEN - 1].max(1) as usize;
+        num_groups.saturating_sub(from_end)
+    }
+
+    /// Creates a copy of the checkerboard, with the digits for the message
+    ///
+    /// # Arguments
+    ///
+    /// * `keys` The keys for the message
+    ///
+    fn message_checkerboard(&self, keys: &MessageKeys) -> StraddleCheckerboard {
+        let mut result = self.checkerboard.clone();
+        result.set_col_digits(&keys.col_digits).unwrap();
+        result
+    }
+}
+
+impl Asymmetric for Vic {
+    fn encrypt(&self, language: &mut Language, msg: &str) -> String {
+        language.set_alph_len(ALPHABET_LEN);
+
+        let keygroup = self.keygroup.as_slice();
+        let keys = self.message_keys(keygroup);
+        let checkerboard = self.message_checkerboard(&keys);
+
+        let mut digits = checkerboard.encode_msg(language, msg, FIGURE_REPEATS);
+
+        // pad with figure shifts, which decode to nothing, or a single row digit,
+        // which is ignored as it ends part way through a symbol
+        let mut padding = Vec::new();
+        checkerboard.encode(checkerboard.figure_shift(), &mut padding);
+        while !digits.len().is_multiple_of(GROUP_LEN) {
+            if GROUP_LEN - digits.len() % GROUP_LEN >= padding.len() {
+                digits.extend(&padding);
+            } else {
+                digits.push(padding[0]);
+            }
+        }
+
+        let len = digits.len();
+        let digits = Vic::transpose(
+            &digits,
+            &(0..len).collect::<Vec<_>>(),
+            &Vic::columnar_cells(len, &keys.transpos1),
+        );
+        let mut digits = Vic::transpose(
+            &digits,
+            &Vic::disrupted_cells(len, &keys.transpos2),
+            &Vic::columnar_cells(len, &keys.transpos2),
+        );
+
+        let pos = self.keygroup_pos(len / GROUP_LEN + 1) * GROUP_LEN;
+        digits.splice(pos..pos, keygroup.iter().copied());
+
+        let digits = digits.iter().map(|x| x.to_string()).collect::<String>();
+        util::blocks(&digits, GROUP_LEN, " ")
+    }
+    fn decrypt(&self, language: &mut Language, msg: &str) -> String {
+        language.set_alph_len(ALPHABET_LEN);
+
+        let mut digits = msg
+            .chars()
+            .filter_map(|ch| ch.to_digit(10))
+            .map(|x| x as i16)
+            .collect::<Vec<_>>();
+        digits.truncate(digits.len() - digits.len() % GROUP_LEN);
+
+        if digits.is_empty() {
+            return String::new();
+        }
+
+        let pos = self.keygroup_pos(digits.len() / GROUP_LEN) * GROUP_LEN;
+        let keygroup = digits.drain(pos..pos + GROUP_LEN).collect::<Vec<_>>();
+        let keys = self.message_keys(&keygroup);
+        let checkerboard = self.message_checkerboard(&keys);
+
+        let len = digits.len();
+        let digits = Vic::transpose(
+            &digits,
+            &Vic::columnar_cells(len, &keys.transpos2),
+            &Vic::disrupted_cells(len, &keys.transpos2),
+        );
+        let digits = Vic::transpose(
+            &digits,
+            &Vic::columnar_cells(len, &keys.transpos1),
+            &(0..len).collect::<Vec<_>>(),
+        );
+
+        checkerboard.decode_msg(language, &digits, FIGURE_REPEATS)
+    }
+}
+
+impl Keyed for Vic {
+    fn new(language: &mut Language) -> Vic {
+        language.set_alph_len(ALPHABET_LEN);
+
+        let mut result = Vic {
+            phrase: Keyword::identity(language),
+            date: Digits::identity(language),
+            personal_number: *Number::new(language, 1).unwrap(),
+            keygroup: Digits::identity(language),
+            checkerboard: StraddleCheckerboard::identity(language),
+        };
+
+        result.date.set_len(DATE_LEN);
+        result.keygroup.set_len(GROUP_LEN);
+        result
+            .personal_number
+            .set_legal_values((1..=MAX_PERSONAL_NUMBER).collect());
+
+        result.phrase.info_mut().set("Phrase", "phrase");
+        result.date.info_mut().set("Date", "date");
+        result
+            .personal_number
+            .info_mut()
+            .set("Personal number", "pnum");
+        result.keygroup.info_mut().set("Keygroup", "kg");
+        result.checkerboard.info_mut().set("Checkerboard", "cb");
+
+        result
+    }
+    fn keys(&self) -> Vec<&dyn IoKey> {
+        vec![
+            &self.phrase,
+            &self.date,
+            &self.personal_number,
+            &self.keygroup,
+            &self.checkerboard,
+        ]
+    }
+    fn keys_mut(&mut self) -> Vec<&mut dyn IoKey> {
+        vec![
+            &mut self.phrase,
+            &mut self.date,
+            &mut self.personal_number,
+            &mut self.keygroup,
+            &mut self.checkerboard,
+        ]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn cipher(language: &mut Language) -> Vic {
+        let mut cipher = Vic::new(language);
+        cipher
+            .phrase
+            .set(language, "I have not yet begun to fight")
+            .unwrap();
+        cipher.date.set(language, "4/7/1776").unwrap();
+        cipher.personal_number.set(language, 9).unwrap();
+        cipher.keygroup.set(language, "72401").unwrap();
+        cipher.checkerboard.set(language, "AT ONE SIR").unwrap();
+        cipher
+    }
+
+    #[test]
+    fn message_keys() {
+        let mut language = Language::from_file("examples/data/english.bin").unwrap();
+        let cipher = cipher(&mut language);
+
+        let keys = cipher.message_keys(cipher.keygroup.as_slice());
+
+        assert_eq!(keys.col_digits, vec![7, 3, 4, 0, 1, 5, 8, 9, 2, 6]);
+        assert_eq!(
+            keys.transpos1,
+            Vic::key_order(&[7, 7, 1, 3, 0, 7, 2, 4, 3, 3], true)
+        );
+        assert_eq!(
+            keys.transpos2,
+            Vic::key_order(&[5, 2, 9, 0, 3, 4, 8, 5, 4, 4, 3, 2, 1, 7], true)
+        );
+    }
+
+    #[test]
+    fn message_keys_hayhanen() {
+        let mut language = Language::from_file("examples/data/english.bin").unwrap();
+        let mut cipher = Vic::new(&mut language);
+
+        // Häyhänen's phrase was the first 20 letters of "ТОЛЬКО СЛЫШНО НА УЛИЦЕ ГДЕ-ТО".
+        // Only the alphabetical order of the letters matters, so each Russian letter
+        // is replaced by the English letter in the same position in that order.
+        cipher
+            .phrase
+            .set(&mut language, "JHFOEHIFNMGHGAKFDLCB")
+            .unwrap();
+        cipher.date.set(&mut language, "3/9/1945").unwrap();
+        cipher.personal_number.set(&mut language, 13).unwrap();
+        cipher.keygroup.set(&mut language, "77651").unwrap();
+
+        let keys = cipher.message_keys(cipher.keygroup.as_slice());
+
+        assert_eq!(keys.col_digits, vec![8, 1, 5, 6, 4, 2, 9, 0, 7, 3]);
+        assert_eq!(
+            keys.transpos1,
+            Vic::key_order(
+                &[9, 1, 1, 5, 0, 4, 3, 4, 5, 0, 9, 4, 3, 3, 5, 7, 1, 4, 8, 3, 8],
+                true
+            )
+        );
+        assert_eq!(
+            keys.transpos2,
+            Vic::key_order(&[4, 1, 3, 3, 5, 9, 0, 2, 8, 4, 6, 7, 2, 0, 2, 1], true)
+        );
+
+        let ciphertext = cipher.encrypt(&mut language, "Meet at the usual place at 5.");
+        assert_eq!(
+            cipher.decrypt(&mut language, &ciphertext),
+            "MEETATTHEUSUALPLACEAT5."
+        );
+    }
+
+    #[test]
+    fn encrypt() {
+        let mut language = Language::from_file("examples/data/english.bin").unwrap();
+        let cipher = cipher(&mut language);
+
+        let ciphertext = cipher.encrypt(
+            &mut language,
+            "We are discovered. Take what you can. Burn everything else. Move to safehouse Foxtrot 3.",
+        );
+
+        assert_eq!(
+            ciphertext,
+            "87687 04052 84248 48442 90388 03218 33718 35680 25924 44032 68658 06481 58456 85004 56038 54554 54517 72401 84503 74063 85479 39381 45819"
+        );
+    }
+
+    #[test]
+    fn decrypt() {
+        let mut language = Language::from_file("examples/data/english.bin").unwrap();
+        let cipher = cipher(&mut language);
+
+        let plaintext = cipher.decrypt(
+            &mut language,
+            "87687 04052 84248 48442 90388 03218 33718 35680 25924 44032 68658 06481 58456 85004 56038 54554 54517 72401 84503 74063 85479 39381 45819",
+        );
+
+        assert_eq!(
+            plaintext,
+            "WEAREDISCOVERED.TAKEWHATYOUCAN.BURNEVERYTHINGELSE.MOVETOSAFEHOUSEFOXTROT3."
+        );
+    }
+}
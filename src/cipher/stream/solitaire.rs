@@ -1 +1,132 @@
+use crate::{
+    cipher::{Asymmetric, Keyed},
+    key::{Cards, IdentityKey, IoKey},
+    lang::Language,
+    util,
+};
 
+/// Solitaire uses the 26 letter alphabet
+const ALPHABET_LEN: usize = 26;
+/// The ciphertext is written in groups of 5 letters
+const GROUP_LEN: usize = 5;
+/// The letter used to pad the plaintext to a multiple of [`GROUP_LEN`]
+const PADDING: char = 'X';
+
+/// The Solitaire (Pontifex) stream cipher, designed by Bruce Schneier. The deck of
+/// cards generates a key stream of values from 1 to 52, which are added to the
+/// plaintext letters (modulo 26) to encrypt, or subtracted from the ciphertext letters
+/// to decrypt. The plaintext is padded with `X` to a multiple of 5 letters, and the
+/// ciphertext is written in groups of 5.
+///
+pub struct Solitaire {
+    pub deck: Cards,
+}
+
+impl Solitaire {
+    /// Adds or subtracts the key stream from the letters of `msg`, ignoring any other
+    /// chars
+    ///
+    /// # Arguments
+    ///
+    /// * `language` A [`Language`] instance
+    /// * `msg` The message to encrypt or decrypt
+    /// * `decrypt` Whether to subtract the key stream
+    ///
+    fn run(&self, language: &mut Language, msg: &str, decrypt: bool) -> Vec<i16> {
+        language.set_alph_len(ALPHABET_LEN);
+
+        let mut cps = language.string_to_vec(msg);
+        if !decrypt {
+            while !cps.len().is_multiple_of(GROUP_LEN) {
+                cps.push(language.get_cp(&PADDING));
+            }
+        }
+
+        let key_stream = self.deck.clone().key_stream(cps.len());
+        cps.iter()
+            .zip(key_stream)
+            .map(|(&cp, shift)| {
+                let shift = if decrypt { -shift } else { shift };
+                util::modulo(cp + shift, ALPHABET_LEN as i16)
+            })
+            .collect()
+    }
+}
+
+impl Asymmetric for Solitaire {
+    fn encrypt(&self, language: &mut Language, msg: &str) -> String {
+        let ciphertext = self.run(language, msg, false);
+        util::blocks(&language.vec_to_string(&ciphertext), GROUP_LEN, " ")
+    }
+    fn decrypt(&self, language: &mut Language, msg: &str) -> String {
+        let plaintext = self.run(language, msg, true);
+        language.vec_to_string(&plaintext)
+    }
+}
+
+impl Keyed for Solitaire {
+    fn new(language: &mut Language) -> Solitaire {
+        language.set_alph_len(ALPHABET_LEN);
+
+        let mut result = Solitaire {
+            deck: Cards::identity(language),
+        };
+
+        result.deck.info_mut().set("Deck", "deck");
+
+        result
+    }
+    fn keys(&self) -> Vec<&dyn IoKey> {
+        vec![&self.deck]
+    }
+    fn keys_mut(&mut self) -> Vec<&mut dyn IoKey> {
+        vec![&mut self.deck]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::key::Key;
+
+    #[test]
+    fn encrypt() {
+        let mut language = Language::from_file("examples/data/english.bin").unwrap();
+        let mut cipher = Solitaire::new(&mut language);
+
+        let vectors = [
+            ("", "AAAAAAAAAAAAAAA", "EXKYI ZSGEH UNTIQ"),
+            ("f", "AAAAAAAAAAAAAAA", "XYIUQ BMHKK JBEGY"),
+            ("fo", "AAAAAAAAAAAAAAA", "TUJYM BERLG XNDIW"),
+            ("foo", "AAAAAAAAAAAAAAA", "ITHZU JIWGR FARMW"),
+            ("a", "AAAAAAAAAAAAAAA", "XODAL GSCUL IQNSC"),
+            ("aa", "AAAAAAAAAAAAAAA", "OHGWM XXCAI MCIQP"),
+            ("aaa", "AAAAAAAAAAAAAAA", "DCSQY HBQZN GDRUT"),
+            ("b", "AAAAAAAAAAAAAAA", "XQEEM OITLZ VDSQS"),
+            ("bc", "AAAAAAAAAAAAAAA", "QNGRK QIHCL GWSCE"),
+            ("bcd", "AAAAAAAAAAAAAAA", "FMUBY BMAXH NQXCJ"),
+            (
+                "cryptonomicon",
+                "AAAAAAAAAAAAAAAAAAAAAAAAA",
+                "SUGSR SXSWQ RMXOH IPBFP XARYQ",
+            ),
+            ("cryptonomicon", "SOLITAIRE", "KIRAK SFJAN"),
+        ];
+
+        for (passphrase, plaintext, ciphertext) in vectors {
+            cipher.deck.set(&mut language, passphrase).unwrap();
+            assert_eq!(cipher.encrypt(&mut language, plaintext), ciphertext);
+        }
+    }
+
+    #[test]
+    fn decrypt() {
+        let mut language = Language::from_file("examples/data/english.bin").unwrap();
+        let mut cipher = Solitaire::new(&mut language);
+        cipher.deck.set(&mut language, "cryptonomicon").unwrap();
+
+        let plaintext = cipher.decrypt(&mut language, "KIRAK SFJAN");
+
+        assert_eq!(plaintext, "SOLITAIREX");
+    }
+}
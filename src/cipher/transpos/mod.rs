@@ -6,6 +6,36 @@ pub mod myszkowski;
 pub mod railfence;
 pub mod scytale;
 
+/// Finds the indexes for a columnar transposition where the final row may be
+/// incomplete. The plaintext is written into rows of `key_order.len()` letters, and
+/// read out by columns in the order given by `key_order`. Returns a vec where the item
+/// at each plaintext index is the index of that letter in the ciphertext.
+///
+/// # Arguments
+///
+/// * `len` The length of the text
+/// * `key_order` The position of each column in the ciphertext (See `Keyword::find_order`)
+///
+pub fn column_indexes(len: usize, key_order: &[usize]) -> Vec<usize> {
+    let key_len = key_order.len();
+    let num_rows = len / key_len;
+    let num_long_cols = len % key_len;
+
+    // find where each column starts in the ciphertext
+    let mut col_starts = vec![0; key_len];
+    let mut start = 0;
+    for pos in 0..key_len {
+        if let Some(col) = key_order.iter().position(|&x| x == pos) {
+            col_starts[col] = start;
+            start += num_rows + (col < num_long_cols) as usize;
+        }
+    }
+
+    (0..len)
+        .map(|idx| col_starts[idx % key_len] + idx / key_len)
+        .collect()
+}
+
 /// Solves a block/column transposition depending on arguments
 ///
 /// # Arguments
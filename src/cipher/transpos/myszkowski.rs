@@ -1 +1,204 @@
+use crate::{
+    cipher::{self, Asymmetric, Keyed, Solve},
+    key::{IdentityKey, IoKey, Key, Keyword},
+    lang::{Language, ScoreSize},
+};
 
+/// The Myszkowski transposition. The plaintext is written into rows the length of the
+/// keyword, and the columns are read out in the alphabetical order of the keyword
+/// letters. Columns under the same letter share a rank, and are read out together row
+/// by row, from left to right. The final row may be incomplete.
+///
+pub struct Myszkowski {
+    pub keyword: Keyword,
+}
+
+impl Myszkowski {
+    /// Finds the plaintext index of each letter of the ciphertext
+    ///
+    /// # Arguments
+    ///
+    /// * `len` The length of the text
+    /// * `key` The keyword, where columns with equal values share a rank
+    ///
+    fn indexes(len: usize, key: &[i16]) -> Vec<usize> {
+        let mut ranks = key.to_vec();
+        ranks.sort_unstable();
+        ranks.dedup();
+
+        let key_len = key.len();
+        let num_rows = len.div_ceil(key_len);
+
+        let mut result = Vec::with_capacity(len);
+        for rank in ranks {
+            for row in 0..num_rows {
+                result.extend(
+                    (0..key_len)
+                        .filter(|&col| key[col] == rank)
+                        .map(|col| row * key_len + col)
+                        .filter(|&idx| idx < len),
+                );
+            }
+        }
+
+        result
+    }
+
+    /// Finds the rank of each column, where the lowest value has rank 0 and equal
+    /// values share a rank
+    ///
+    /// # Arguments
+    ///
+    /// * `key` The value of each column
+    ///
+    fn ranks(key: &[i16]) -> Vec<i16> {
+        let mut values = key.to_vec();
+        values.sort_unstable();
+        values.dedup();
+
+        key.iter()
+            .map(|x| values.binary_search(x).unwrap() as i16)
+            .collect()
+    }
+}
+
+impl Asymmetric for Myszkowski {
+    fn encrypt(&self, language: &mut Language, msg: &str) -> String {
+        let plaintext = language.string_to_vec(msg);
+        let mut iter = Myszkowski::indexes(plaintext.len(), self.keyword.as_slice()).into_iter();
+        msg.chars()
+            .filter_map(|ch| {
+                if language.is_letter(&ch) {
+                    Some(language.update_cp(&ch, plaintext[iter.next()?]))
+                } else {
+                    Some(ch)
+                }
+            })
+            .collect()
+    }
+    fn decrypt(&self, language: &mut Language, msg: &str) -> String {
+        let ciphertext = language.string_to_vec(msg);
+        let mut plaintext = vec![0; ciphertext.len()];
+        for (&cp, idx) in ciphertext.iter().zip(Myszkowski::indexes(
+            ciphertext.len(),
+            self.keyword.as_slice(),
+        )) {
+            plaintext[idx] = cp;
+        }
+
+        let mut iter = plaintext.into_iter();
+        msg.chars()
+            .filter_map(|ch| {
+                if language.is_letter(&ch) {
+                    Some(language.update_cp(&ch, iter.next()?))
+                } else {
+                    Some(ch)
+                }
+            })
+            .collect()
+    }
+}
+
+impl Keyed for Myszkowski {
+    fn new(language: &mut Language) -> Myszkowski {
+        let mut result = Myszkowski {
+            keyword: Keyword::identity(language),
+        };
+
+        result.keyword.info_mut().set("Keyword", "kw");
+
+        result
+    }
+    fn keys(&self) -> Vec<&dyn IoKey> {
+        vec![&self.keyword]
+    }
+    fn keys_mut(&mut self) -> Vec<&mut dyn IoKey> {
+        vec![&mut self.keyword]
+    }
+}
+
+const MIN_KEY_LEN: usize = 2;
+const MAX_KEY_LEN: usize = 12;
+const TEMP_STEPS: usize = 50;
+const ITERATIONS: usize = 1000;
+const MAX_RESTARTS: usize = 3;
+
+impl Solve for Myszkowski {
+    fn solve(&mut self, language: &mut Language, msg: &str) {
+        let ciphertext = language.string_to_vec(msg);
+        let len = ciphertext.len();
+        let mut plaintext = vec![0; len];
+
+        // the difference in score between keys grows with the message length
+        let start_temp = (len as f64 / 15.0).max(5.0);
+
+        let mut best_key = vec![0];
+        let mut best_score = f64::MIN;
+
+        for key_len in MIN_KEY_LEN..=MAX_KEY_LEN.min(len) {
+            for _ in 0..MAX_RESTARTS {
+                // the rank of each column, which may repeat
+                let mut key = (0..key_len)
+                    .map(|_| fastrand::i16(0..key_len as i16))
+                    .collect::<Vec<_>>();
+
+                let score = cipher::anneal(
+                    &mut key,
+                    start_temp,
+                    TEMP_STEPS,
+                    ITERATIONS,
+                    |key| {
+                        for (&cp, idx) in ciphertext.iter().zip(Myszkowski::indexes(len, key)) {
+                            plaintext[idx] = cp;
+                        }
+                        language.score(&plaintext, ScoreSize::Quadgrams)
+                    },
+                    |key| {
+                        // either change the rank of a column, or swap two columns
+                        if fastrand::bool() {
+                            key[fastrand::usize(0..key_len)] = fastrand::i16(0..key_len as i16);
+                        } else {
+                            key.swap(fastrand::usize(0..key_len), fastrand::usize(0..key_len));
+                        }
+                    },
+                );
+
+                if score > best_score {
+                    best_score = score;
+                    best_key = key;
+                }
+            }
+        }
+
+        self.keyword
+            .set(language, Myszkowski::ranks(&best_key).as_slice())
+            .unwrap();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn encrypt() {
+        let mut language = Language::from_file("examples/data/english.bin").unwrap();
+        let mut cipher = Myszkowski::new(&mut language);
+        cipher.keyword.set(&mut language, "tomato").unwrap();
+
+        let ciphertext = cipher.encrypt(&mut language, "WEAREDISCOVEREDFLEEATONCE");
+
+        assert_eq!(ciphertext, "ROFOACDTEDSEEEACWEIVRLENE");
+    }
+
+    #[test]
+    fn decrypt() {
+        let mut language = Language::from_file("examples/data/english.bin").unwrap();
+        let mut cipher = Myszkowski::new(&mut language);
+        cipher.keyword.set(&mut language, "tomato").unwrap();
+
+        let plaintext = cipher.decrypt(&mut language, "ROFOACDTEDSEEEACWEIVRLENE");
+
+        assert_eq!(plaintext, "WEAREDISCOVEREDFLEEATONCE");
+    }
+}
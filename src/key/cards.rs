@@ -5,8 +5,15 @@ use crate::{
     util,
 };
 
-/// Represents a deck of cards (See Solitaire cipher)
+/// Represents a deck of cards (See Solitaire cipher). Cards are numbered in bridge
+/// order (clubs, diamonds, hearts, spades), followed by the A and B jokers.
 ///
+/// As a string, the key is a passphrase which keys the deck using Schneier's
+/// procedure: starting from the ordered deck, each letter of the passphrase performs
+/// the steps used to generate the key stream (without an output card), followed by a
+/// count cut using the value of the letter.
+///
+#[derive(Clone)]
 pub struct Cards {
     value: Vec<i16>,
     info: KeyInfo,
@@ -70,67 +77,65 @@ impl Cards {
         self.value = tmp;
     }
 
-    /// Performs a count cut on the deck
+    /// Performs a count cut on the deck, moving cards from the top of the deck to
+    /// just above the bottom card
     ///
     /// # Arguments
     ///
-    /// * `length` The length of the count cut
+    /// * `length` The number of cards to move
     ///
+    fn count_cut(&mut self, length: usize) {
+        self.value[..53].rotate_left(length.min(53));
+    }
+
+    /// Calculates the count of a card, from 1 to 52 in bridge order, where both
+    /// jokers count as 53
     ///
-    fn count_cut(&mut self, length: i16) {
-        if self.value[53] < 52 {
-            let mut tmp = vec![0; 54];
-            let mut idx = 0;
-            for i in length..53 {
-                // leave last card
-                tmp[idx] = self.value[i as usize];
-                idx += 1;
-            }
-            for i in 0..length {
-                tmp[idx] = self.value[i as usize];
-                idx += 1;
-            }
-            self.value.copy_from_slice(&tmp[0..53]);
-        }
+    /// # Arguments
+    ///
+    /// * `card` The card to count
+    ///
+    fn count(card: i16) -> usize {
+        std::cmp::min(card as usize + 1, 53)
+    }
+
+    /// Performs the steps which are repeated for each card of the key stream, and when
+    /// keying the deck: moving the jokers, a triple cut, and a count cut using the
+    /// bottom card
+    ///
+    fn step(&mut self) {
+        self.shift_joker(A_JOKER);
+        self.shift_joker(B_JOKER);
+        self.shift_joker(B_JOKER);
+        self.triple_cut();
+        self.count_cut(Cards::count(self.value[53]));
     }
 
     /// Calculates the output card of the deck (used for the key stream)
     ///
     fn output_card(&self) -> i16 {
-        if self.value[0] < 52 {
-            let x = 1 + self.value[(self.value[0] + 1) as usize];
-            return std::cmp::min(x, 53);
-        }
-        // return last card if it is a joker
-        std::cmp::min(self.value[53] + 1, 53)
+        std::cmp::min(self.value[Cards::count(self.value[0])] + 1, 53)
     }
 
     /// Calculates the key stream of the deck, which is then used to shift
-    /// the plaintext letters
+    /// the plaintext letters. Each value is the count of a card from 1 to 52,
+    /// since a joker as the output card is skipped.
     ///
     /// # Arguments
     ///
     /// * `stream_len` The required length of the key stream
     ///
     pub fn key_stream(&mut self, stream_len: usize) -> Vec<i16> {
-        let mut stream = vec![0; stream_len];
+        let mut stream = Vec::with_capacity(stream_len);
 
-        let mut idx = 0;
-        for _ in 0..stream_len {
-            self.shift_joker(A_JOKER);
-            self.shift_joker(B_JOKER);
-            self.shift_joker(B_JOKER);
-            self.triple_cut();
-            if self.value[53] < 52 {
-                // not a joker
-                self.count_cut(self.value[53] + 1);
-            }
+        while stream.len() < stream_len {
+            self.step();
             let output_card = self.output_card();
             if output_card < 53 {
-                stream[idx] = output_card;
-                idx += 1;
+                stream.push(output_card);
             }
         }
+
         stream
     }
 }
@@ -144,12 +149,8 @@ impl Key<&[i16]> for Cards {
     }
     fn set(&mut self, _language: &mut Language, arg: &[i16]) -> Result<()> {
         for &card in arg {
-            self.shift_joker(A_JOKER);
-            self.shift_joker(B_JOKER);
-            self.shift_joker(B_JOKER);
-            self.triple_cut();
-            self.count_cut(self.value[53] + 1);
-            self.count_cut(card + 1);
+            self.step();
+            self.count_cut(card as usize + 1);
         }
         Ok(())
     }
@@ -161,6 +162,7 @@ impl Key<&str> for Cards {
         Ok(Box::new(cards))
     }
     fn set(&mut self, language: &mut Language, arg: &str) -> Result<()> {
+        self.reset(language);
         let vec = language.string_to_vec(arg);
         self.set(language, vec.as_slice())
     }
@@ -0,0 +1,135 @@
+use crate::{
+    error::{Error, Result},
+    key::{IdentityKey, IoKey, Key, KeyInfo, StatefulKey},
+    lang::Language,
+};
+
+/// Represents a string of decimal digits, such as a date or message indicator (See
+/// VIC cipher). If a length is set, the key must contain exactly that many digits.
+/// Any other chars are ignored when setting the key from a string, so a date can be
+/// given as `3/9/1945`.
+///
+pub struct Digits {
+    value: Vec<i16>,
+    len: usize,
+    info: KeyInfo,
+}
+
+impl Digits {
+    /// Returns the number of digits
+    ///
+    pub fn len(&self) -> usize {
+        self.value.len()
+    }
+
+    /// Are there no digits?
+    ///
+    pub fn is_empty(&self) -> bool {
+        self.value.is_empty()
+    }
+
+    /// Gets the digits as a slice
+    ///
+    pub fn as_slice(&self) -> &[i16] {
+        &self.value
+    }
+
+    /// Sets the required number of digits, and resets the key to zeros. A length
+    /// of 0 allows any number of digits.
+    ///
+    /// # Arguments
+    ///
+    /// * `len` The required number of digits
+    ///
+    pub fn set_len(&mut self, len: usize) {
+        self.len = len;
+        self.value = vec![0; len];
+    }
+
+    /// Checks that `arg` holds digits of the required length
+    ///
+    /// # Arguments
+    ///
+    /// * `arg` The digits to check
+    ///
+    fn check_val(&self, arg: &[i16]) -> Result<()> {
+        if arg.iter().any(|&x| !(0..=9).contains(&x)) || (self.len != 0 && arg.len() != self.len) {
+            Err(Error::InvalidKeyFmt {
+                expected: self.desc(),
+                actual: format!("{:?}", arg),
+            })
+        } else {
+            Ok(())
+        }
+    }
+}
+
+impl Key<&[i16]> for Digits {
+    fn new(language: &mut Language, arg: &[i16]) -> Result<Box<Self>> {
+        let mut result = Box::new(Digits::identity(language));
+        result.set(language, arg)?;
+        Ok(result)
+    }
+    fn set(&mut self, _language: &mut Language, arg: &[i16]) -> Result<()> {
+        self.check_val(arg)?;
+        self.value = Vec::from(arg);
+        Ok(())
+    }
+}
+impl Key<&str> for Digits {
+    fn new(language: &mut Language, arg: &str) -> Result<Box<Self>> {
+        let mut result = Box::new(Digits::identity(language));
+        result.set(language, arg)?;
+        Ok(result)
+    }
+    fn set(&mut self, language: &mut Language, arg: &str) -> Result<()> {
+        let digits = arg
+            .chars()
+            .filter_map(|ch| ch.to_digit(10))
+            .map(|x| x as i16)
+            .collect::<Vec<_>>();
+        self.set(language, digits.as_slice())
+    }
+}
+
+impl IdentityKey for Digits {
+    fn identity(_language: &mut Language) -> Self {
+        Self {
+            value: Vec::new(),
+            len: 0,
+            info: KeyInfo::default(),
+        }
+    }
+}
+
+impl StatefulKey for Digits {
+    fn reset(&mut self, _language: &mut Language) {
+        self.value = vec![0; self.len];
+    }
+    fn to_string(&self, _language: &mut Language) -> String {
+        self.value.iter().map(|x| x.to_string()).collect()
+    }
+    fn randomize(&mut self, _language: &mut Language) {
+        self.value
+            .iter_mut()
+            .for_each(|x| *x = fastrand::i16(0..10));
+    }
+}
+
+impl IoKey for Digits {
+    fn set_key_str(&mut self, language: &mut Language, arg: &str) -> Result<()> {
+        self.set(language, arg)
+    }
+    fn info(&self) -> &KeyInfo {
+        &self.info
+    }
+    fn info_mut(&mut self) -> &mut KeyInfo {
+        &mut self.info
+    }
+    fn desc(&self) -> String {
+        match self.len {
+            0 => "<digits>".to_string(),
+            len => format!("<digits: {}>", len),
+        }
+    }
+}
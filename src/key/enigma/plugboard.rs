@@ -5,11 +5,42 @@ use crate::{
     util,
 };
 
-/// Represents an Enigma Plugboard (See Enigma cipher)
+/// The number of positions of the Uhr box
+const UHR_POSITIONS: usize = 40;
+/// The number of cables connected to the Uhr box
+const UHR_CABLES: usize = 10;
+/// The wiring of the rotating disk of the Uhr box, from each contact on the side of the
+/// large plug pins (keyboard) to a contact on the side of the small plug pins (entry
+/// wheel). The a plug of cable `n` uses contact `4n` on each side, and the b plug uses
+/// contact `4n + 2`.
+#[rustfmt::skip]
+const UHR_WIRING: [usize; UHR_POSITIONS] = [
+    6, 31, 4, 29, 18, 39, 16, 25, 30, 23, 28, 1, 38, 11, 36, 37, 26, 27, 24, 21,
+    14, 3, 12, 17, 2, 7, 0, 33, 10, 35, 8, 5, 22, 19, 20, 13, 34, 15, 32, 9,
+];
+
+/// The cables and position of an Uhr box connected to the plugboard
+///
+#[derive(Clone)]
+struct Uhr {
+    /// The letters of the a and b plugs of each cable
+    cables: Vec<(i16, i16)>,
+    /// The position of the disk, from 0 to 39
+    position: usize,
+}
+
+/// Represents an Enigma Plugboard (See Enigma cipher). Plugs are normally reciprocal,
+/// swapping two letters, but the plugboard can instead be connected to an Uhr box,
+/// which sends the letters of the a plugs to the b plugs and back through a
+/// rotating disk, giving a plugboard that is not reciprocal.
 ///
 #[derive(Clone)]
 pub struct Plugboard {
+    /// The output for each letter, from the keyboard to the entry wheel
     substitution: Vec<i16>,
+    /// The output for each letter, from the entry wheel to the lamps
+    inverse: Vec<i16>,
+    uhr: Option<Uhr>,
     info: KeyInfo,
 }
 
@@ -23,10 +54,13 @@ impl Plugboard {
     /// * `letter2` The second letter of the plug
     ///
     pub fn add_plug(&mut self, letter1: i16, letter2: i16) {
+        debug_assert!(self.uhr.is_none());
         debug_assert!(self.is_valid_plug(letter1, letter2));
 
         self.substitution[letter1 as usize] = letter2;
         self.substitution[letter2 as usize] = letter1;
+        self.inverse[letter1 as usize] = letter2;
+        self.inverse[letter2 as usize] = letter1;
     }
 
     /// Removes a plug between two letters, provided that the plug exists
@@ -37,13 +71,16 @@ impl Plugboard {
     /// * `letter2` The second letter of the plug
     ///
     pub fn del_plug(&mut self, letter1: i16, letter2: i16) {
+        debug_assert!(self.uhr.is_none());
         debug_assert!(self.is_existing_plug(letter1, letter2));
 
         self.substitution[letter1 as usize] = letter1;
         self.substitution[letter2 as usize] = letter2;
+        self.inverse[letter1 as usize] = letter1;
+        self.inverse[letter2 as usize] = letter2;
     }
 
-    /// Sends a letter through the plugboard and returns the output
+    /// Sends a letter through the plugboard from the keyboard and returns the output
     /// letter. If the letter is not plugged, returns that letter.
     ///
     /// # Arguments
@@ -54,6 +91,80 @@ impl Plugboard {
         self.substitution[letter as usize]
     }
 
+    /// Sends a letter back through the plugboard from the entry wheel and returns the
+    /// output letter. This is the same as [`Plugboard::input`] unless an Uhr box is
+    /// connected.
+    ///
+    /// # Arguments
+    ///
+    /// * `letter` The letter to input to the plug
+    ///
+    pub fn output(&self, letter: i16) -> i16 {
+        self.inverse[letter as usize]
+    }
+
+    /// Connects an Uhr box in place of the plugs, removing any existing plugs.
+    ///
+    /// # Arguments
+    ///
+    /// * `cables` The letters of the a and b plugs of each of the 10 cables, which must
+    ///   all be different
+    /// * `position` The position of the Uhr box, from 0 to 39
+    ///
+    pub fn set_uhr(&mut self, cables: &[(i16, i16)], position: usize) {
+        debug_assert_eq!(cables.len(), UHR_CABLES);
+        debug_assert!(position < UHR_POSITIONS);
+
+        self.uhr = Some(Uhr {
+            cables: cables.to_vec(),
+            position,
+        });
+        self.wire_uhr();
+    }
+
+    /// Disconnects the Uhr box, leaving no plugs
+    ///
+    pub fn remove_uhr(&mut self) {
+        self.uhr = None;
+        self.wire_uhr();
+    }
+
+    /// The position of the Uhr box, if it is connected
+    ///
+    pub fn uhr_position(&self) -> Option<usize> {
+        self.uhr.as_ref().map(|uhr| uhr.position)
+    }
+
+    /// Finds the outputs of the plugboard from the cables and position of the Uhr box,
+    /// or clears the plugboard if it is not connected
+    ///
+    fn wire_uhr(&mut self) {
+        util::fill_consecutive_vec(&mut self.substitution, 0, 26);
+        util::fill_consecutive_vec(&mut self.inverse, 0, 26);
+
+        if let Some(uhr) = &self.uhr {
+            let plug_letter = |contact: usize| {
+                let (a, b) = uhr.cables[contact / 4];
+                if contact.is_multiple_of(4) {
+                    a
+                } else {
+                    b
+                }
+            };
+
+            for cable in 0..UHR_CABLES {
+                for &contact in [4 * cable, 4 * cable + 2].iter() {
+                    let rotated = UHR_WIRING[(contact + uhr.position) % UHR_POSITIONS];
+                    let output = (rotated + UHR_POSITIONS - uhr.position) % UHR_POSITIONS;
+
+                    let (input, output) = (plug_letter(contact), plug_letter(output));
+                    self.substitution[input as usize] = output;
+                    self.inverse[output as usize] = input;
+                }
+            }
+        }
+    }
+
     /// Checks whether creating a plug between letter1 and letter2 is allowed.
     ///
     /// # Arguments
@@ -65,7 +176,10 @@ impl Plugboard {
         let idx1 = letter1 as usize;
         let idx2 = letter2 as usize;
 
-        self.substitution[idx1] == letter1 && self.substitution[idx2] == letter2 && idx1 != idx2
+        self.uhr.is_none()
+            && self.substitution[idx1] == letter1
+            && self.substitution[idx2] == letter2
+            && idx1 != idx2
     }
 
     /// Checks whether two letters are plugged together
@@ -102,6 +216,12 @@ impl Key<&str> for Plugboard {
     fn set(&mut self, language: &mut Language, arg: &str) -> Result<()> {
         language.set_alph_len(26);
 
+        if let Some(prefix) = arg.get(..4) {
+            if prefix.eq_ignore_ascii_case("uhr:") {
+                return self.set_uhr_str(language, arg, &arg[4..]);
+            }
+        }
+
         let tokens = arg
             .split(':')
             .map(|t| language.string_to_vec(t))
@@ -130,6 +250,9 @@ impl Key<&str> for Plugboard {
                 tmp.add_plug(t[0], t[1]);
             }
 
+            if self.uhr.is_some() {
+                self.remove_uhr();
+            }
             for t in tokens.iter() {
                 self.add_plug(t[0], t[1]);
             }
@@ -139,10 +262,62 @@ impl Key<&str> for Plugboard {
     }
 }
 
+impl Plugboard {
+    /// Parses the position and cables of an Uhr box, in the form
+    /// `<position>:<10 colon delimited pairs of letters>`, where the first letter of
+    /// each pair is the a plug of the cable and the second is the b plug.
+    ///
+    /// # Arguments
+    ///
+    /// * `language` A [`Language`] instance
+    /// * `arg` The whole key string, for error messages
+    /// * `uhr` The key string following the `uhr:` prefix
+    ///
+    fn set_uhr_str(&mut self, language: &mut Language, arg: &str, uhr: &str) -> Result<()> {
+        let mut args = uhr.split(':');
+
+        let position = match args.next().map(str::parse::<usize>) {
+            Some(Ok(position)) if position < UHR_POSITIONS => position,
+            _ => {
+                return Err(Error::InvalidKeyFmt {
+                    expected: "An Uhr position from 0 to 39".to_string(),
+                    actual: arg.to_string(),
+                })
+            }
+        };
+
+        let tokens = args.map(|t| language.string_to_vec(t)).collect::<Vec<_>>();
+        if tokens.len() != UHR_CABLES || tokens.iter().any(|t| t.len() != 2) {
+            return Err(Error::InvalidKeyFmt {
+                expected: "10 colon ':' delimited pairs of letters".to_string(),
+                actual: arg.to_string(),
+            });
+        }
+
+        let mut used = [false; 26];
+        for &letter in tokens.iter().flatten() {
+            if used[letter as usize] {
+                return Err(Error::InvalidKeyFmt {
+                    expected: "No letter to be used by two plugs".to_string(),
+                    actual: arg.to_string(),
+                });
+            }
+            used[letter as usize] = true;
+        }
+
+        let cables = tokens.iter().map(|t| (t[0], t[1])).collect::<Vec<_>>();
+        self.set_uhr(&cables, position);
+
+        Ok(())
+    }
+}
+
 impl IdentityKey for Plugboard {
     fn identity(_language: &mut Language) -> Self {
         Self {
             substitution: (0..26).collect(),
+            inverse: (0..26).collect(),
+            uhr: None,
             info: KeyInfo::default(),
         }
     }
@@ -150,9 +325,18 @@ impl IdentityKey for Plugboard {
 
 impl StatefulKey for Plugboard {
     fn reset(&mut self, _language: &mut Language) {
-        util::fill_consecutive_vec(&mut self.substitution, 0, 26);
+        self.remove_uhr();
     }
     fn to_string(&self, language: &mut Language) -> String {
+        if let Some(uhr) = &self.uhr {
+            let cables = uhr
+                .cables
+                .iter()
+                .map(|&(a, b)| format!("{}{}", language.cp_to_upper(a), language.cp_to_upper(b)))
+                .collect::<Vec<_>>();
+            return format!("Uhr {}: {}", uhr.position, cables.join(" "));
+        }
+
         let mut tmp = self.substitution.clone();
         let mut data = String::new();
         for i in 0..26 {
@@ -192,6 +376,7 @@ impl IoKey for Plugboard {
         &mut self.info
     }
     fn desc(&self) -> String {
-        "<colon delimited pairs of letters>".to_string()
+        "<colon delimited pairs of letters> or uhr:<position>:<10 colon delimited pairs of letters>"
+            .to_string()
     }
 }
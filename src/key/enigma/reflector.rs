@@ -2,20 +2,68 @@ use crate::{
     error::{Error, Result},
     key::{IoKey, Key, KeyInfo, StatefulKey},
     lang::Language,
+    util,
 };
 
 // Wiring details from: https://en.wikipedia.org/wiki/Enigma_rotor_details#Rotor_wiring_tables
 
 /// Collection of all Enigma Reflector types from the M3 and M4 Enigma
-/// machines. (See Enigma cipher)
+/// machines, the rewirable UKW-D, and the settable reflectors of the Enigma K (also
+/// used by the Swiss-K, G-260 and G-111), Railway Enigma and Enigma G-312. (See
+/// Enigma cipher)
 ///
-#[derive(Clone, Copy)]
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
 pub enum ReflectorType {
     A = 0,
     B = 1,
     C = 2,
     BThin = 3, // M4
     CThin = 4, // M4
+    K = 5,
+    Railway = 6,
+    G312 = 7,
+    D = 8,
+}
+
+/// Every reflector type, in order
+const REFLECTOR_TYPES: [ReflectorType; 9] = [
+    ReflectorType::A,
+    ReflectorType::B,
+    ReflectorType::C,
+    ReflectorType::BThin,
+    ReflectorType::CThin,
+    ReflectorType::K,
+    ReflectorType::Railway,
+    ReflectorType::G312,
+    ReflectorType::D,
+];
+
+impl ReflectorType {
+    /// The name of the reflector type
+    ///
+    pub fn name(&self) -> &'static str {
+        match *self {
+            ReflectorType::A => "A",
+            ReflectorType::B => "B",
+            ReflectorType::C => "C",
+            ReflectorType::BThin => "B thin",
+            ReflectorType::CThin => "C thin",
+            ReflectorType::K => "K",
+            ReflectorType::Railway => "Railway",
+            ReflectorType::G312 => "G312",
+            ReflectorType::D => "D",
+        }
+    }
+
+    /// Whether the position of the reflector can be set. The reflectors of the
+    /// military machines are fixed.
+    ///
+    pub fn is_settable(&self) -> bool {
+        matches!(
+            *self,
+            ReflectorType::K | ReflectorType::Railway | ReflectorType::G312
+        )
+    }
 }
 
 impl From<usize> for ReflectorType {
@@ -25,18 +73,35 @@ impl From<usize> for ReflectorType {
             1 => ReflectorType::B,
             2 => ReflectorType::C,
             3 => ReflectorType::BThin,
-            _ => ReflectorType::CThin,
+            4 => ReflectorType::CThin,
+            5 => ReflectorType::K,
+            6 => ReflectorType::Railway,
+            7 => ReflectorType::G312,
+            _ => ReflectorType::D,
         }
     }
 }
 
 #[rustfmt::skip]
-const WIRINGS: [[i16; 26]; 5] = [
+const WIRINGS: [[i16; 26]; 8] = [
     [ 4, 9, 12, 25, 0, 11, 24, 23, 21, 1, 22, 5, 2, 17, 16, 20, 14, 13, 19, 18, 15, 8, 10, 7, 6, 3, ],
     [ 24, 17, 20, 7, 16, 18, 11, 3, 15, 23, 13, 6, 14, 10, 12, 8, 4, 1, 5, 25, 2, 22, 21, 9, 0, 19, ],
     [ 5, 21, 15, 9, 8, 0, 14, 24, 4, 3, 17, 25, 23, 22, 6, 2, 19, 10, 20, 16, 18, 1, 13, 12, 7, 11, ],
     [ 4, 13, 10, 16, 0, 20, 24, 22, 9, 8, 2, 14, 15, 1, 11, 12, 3, 23, 25, 21, 5, 19, 7, 17, 6, 18, ],
     [ 17, 3, 14, 1, 9, 13, 19, 10, 21, 4, 7, 12, 11, 5, 2, 22, 25, 0, 23, 6, 24, 8, 15, 18, 20, 16, ],
+    [ 8, 12, 4, 19, 2, 6, 5, 17, 0, 24, 18, 16, 1, 25, 23, 22, 11, 7, 10, 3, 21, 20, 15, 14, 9, 13, ],
+    [ 16, 24, 7, 14, 6, 13, 4, 2, 21, 15, 20, 25, 19, 5, 3, 9, 0, 23, 22, 12, 10, 8, 18, 17, 1, 11, ],
+    [ 17, 20, 11, 16, 12, 25, 9, 18, 24, 6, 14, 2, 4, 19, 10, 22, 3, 0, 7, 13, 1, 23, 15, 21, 8, 5, ],
+];
+/// The pair of letters that is always wired together in the UKW-D
+const UKW_D_FIXED: (i16, i16) = (1, 14);
+/// The number of pairs of letters that can be wired in the UKW-D
+const UKW_D_PAIRS: usize = 12;
+/// The wiring of the UKW-D before it is rewired: B/O, then the remaining letters
+/// paired in order
+#[rustfmt::skip]
+const UKW_D_DEFAULT: [i16; 26] = [
+    2, 14, 0, 4, 3, 6, 5, 8, 7, 10, 9, 12, 11, 15, 1, 13, 17, 16, 19, 18, 21, 20, 23, 22, 25, 24,
 ];
 
 /// Represents an Enigma Reflector (See Enigma cipher)
@@ -45,6 +110,16 @@ const WIRINGS: [[i16; 26]; 5] = [
 pub struct Reflector {
     /// The current internal wiring of the reflector
     wiring_type: ReflectorType,
+
+    /// The position of the reflector, for the settable reflectors
+    pub position: i16,
+
+    /// The wiring of the rewirable reflector (UKW-D)
+    rewiring: [i16; 26],
+
+    /// The reflector types that can be used in the machine
+    allowed: Vec<ReflectorType>,
+
     info: KeyInfo,
 }
 
@@ -57,7 +132,109 @@ impl Reflector {
     /// * `letter` The letter to input to the reflector
     ///
     pub fn input(&self, letter: i16) -> i16 {
-        WIRINGS[self.wiring_type as usize][letter as usize]
+        let pos = util::modulo(letter + self.position, 26) as usize;
+
+        let wiring = match self.wiring_type {
+            ReflectorType::D => &self.rewiring,
+            wiring_type => &WIRINGS[wiring_type as usize],
+        };
+
+        util::modulo(wiring[pos] - self.position, 26)
+    }
+
+    /// Rewires the UKW-D. Together with the fixed pair B/O, the pairs must use every
+    /// letter exactly once.
+    ///
+    /// # Arguments
+    ///
+    /// * `pairs` The 12 pairs of letters to wire together
+    ///
+    pub fn rewire(&mut self, pairs: &[(i16, i16)]) {
+        debug_assert_eq!(pairs.len(), UKW_D_PAIRS);
+
+        let (fixed1, fixed2) = UKW_D_FIXED;
+        self.rewiring[fixed1 as usize] = fixed2;
+        self.rewiring[fixed2 as usize] = fixed1;
+        for &(letter1, letter2) in pairs {
+            self.rewiring[letter1 as usize] = letter2;
+            self.rewiring[letter2 as usize] = letter1;
+        }
+    }
+
+    /// Parses the 12 pairs of letters of the UKW-D and rewires it
+    ///
+    /// # Arguments
+    ///
+    /// * `language` A [`Language`] instance
+    /// * `arg` The whole key string, for error messages
+    /// * `pairs` The colon delimited pairs of letters
+    ///
+    fn set_rewiring_str(
+        &mut self,
+        language: &mut Language,
+        arg: &str,
+        pairs: &[&str],
+    ) -> Result<()> {
+        language.set_alph_len(26);
+
+        let tokens = pairs
+            .iter()
+            .map(|t| language.string_to_vec(t))
+            .collect::<Vec<_>>();
+        if tokens.len() != UKW_D_PAIRS || tokens.iter().any(|t| t.len() != 2) {
+            return Err(Error::InvalidKeyFmt {
+                expected: "12 colon ':' delimited pairs of letters".to_string(),
+                actual: arg.to_string(),
+            });
+        }
+
+        let mut used = [false; 26];
+        used[UKW_D_FIXED.0 as usize] = true;
+        used[UKW_D_FIXED.1 as usize] = true;
+        for &letter in tokens.iter().flatten() {
+            if used[letter as usize] {
+                return Err(Error::InvalidKeyFmt {
+                    expected: "Every letter except B and O to be wired exactly once".to_string(),
+                    actual: arg.to_string(),
+                });
+            }
+            used[letter as usize] = true;
+        }
+
+        let pairs = tokens.iter().map(|t| (t[0], t[1])).collect::<Vec<_>>();
+        self.rewire(&pairs);
+
+        Ok(())
+    }
+
+    /// Advance the position of the reflector by a single step, for the moving
+    /// reflector of the Enigma G
+    ///
+    pub fn step(&mut self) {
+        self.position = util::modulo(self.position + 1, 26);
+    }
+
+    /// The type of the reflector, which decides its wiring
+    ///
+    pub fn wiring_type(&self) -> ReflectorType {
+        self.wiring_type
+    }
+
+    /// Restricts the reflector types that can be set, for a particular machine. If
+    /// the current type is not allowed, it is set to the first allowed type.
+    ///
+    /// # Arguments
+    ///
+    /// * `allowed` The allowed reflector types, which must not be empty
+    ///
+    pub fn set_allowed(&mut self, allowed: &[ReflectorType]) {
+        debug_assert!(!allowed.is_empty());
+
+        self.allowed = allowed.to_vec();
+        if !self.allowed.contains(&self.wiring_type) {
+            self.wiring_type = self.allowed[0];
+            self.position = 0;
+        }
     }
 }
 
@@ -65,11 +242,31 @@ impl Key<ReflectorType> for Reflector {
     fn new(_language: &mut Language, arg: ReflectorType) -> Result<Box<Self>> {
         Ok(Box::new(Reflector {
             wiring_type: arg,
+            position: 0,
+            rewiring: UKW_D_DEFAULT,
+            allowed: REFLECTOR_TYPES.to_vec(),
             info: KeyInfo::default(),
         }))
     }
     fn set(&mut self, _language: &mut Language, arg: ReflectorType) -> Result<()> {
+        if !self.allowed.contains(&arg) {
+            return Err(Error::InvalidKeyFmt {
+                expected: format!(
+                    "One of [{}]",
+                    self.allowed
+                        .iter()
+                        .map(|reflector| reflector.name().replace(' ', "").to_lowercase())
+                        .collect::<Vec<_>>()
+                        .join(", ")
+                ),
+                actual: arg.name().replace(' ', "").to_lowercase(),
+            });
+        }
+
         self.wiring_type = arg;
+        if !arg.is_settable() {
+            self.position = 0;
+        }
         Ok(())
     }
 }
@@ -79,52 +276,114 @@ impl Key<&str> for Reflector {
         result.set(language, arg)?;
         Ok(result)
     }
-    fn set(&mut self, _language: &mut Language, arg: &str) -> Result<()> {
-        match arg.to_lowercase().as_str() {
-            "a" => {
-                self.wiring_type = ReflectorType::A;
-                Ok(())
-            }
-            "b" => {
-                self.wiring_type = ReflectorType::B;
-                Ok(())
+    fn set(&mut self, language: &mut Language, arg: &str) -> Result<()> {
+        let args = arg.split(':').collect::<Vec<_>>();
+        if let Some(&reflector) = args.first() {
+            let name = reflector.to_lowercase();
+            match REFLECTOR_TYPES.iter().copied().find(|reflector_type| {
+                reflector_type.name().replace(' ', "").to_lowercase() == name
+            }) {
+                Some(reflector_type) => self.set(language, reflector_type)?,
+                None => {
+                    return Err(Error::InvalidKeyFmt {
+                        expected: format!(
+                            "One of [{}]",
+                            REFLECTOR_TYPES
+                                .iter()
+                                .map(|reflector_type| reflector_type
+                                    .name()
+                                    .replace(' ', "")
+                                    .to_lowercase())
+                                .collect::<Vec<_>>()
+                                .join(", ")
+                        ),
+                        actual: arg.to_string(),
+                    })
+                }
             }
-            "c" => {
-                self.wiring_type = ReflectorType::C;
-                Ok(())
-            }
-            "bthin" => {
-                self.wiring_type = ReflectorType::BThin;
-                Ok(())
+        }
+        if self.wiring_type == ReflectorType::D && args.len() > 1 {
+            return self.set_rewiring_str(language, arg, &args[1..]);
+        }
+        if let Some(&position) = args.get(1) {
+            if !self.wiring_type.is_settable() {
+                return Err(Error::InvalidKeyFmt {
+                    expected: "A reflector with a settable position".to_string(),
+                    actual: arg.to_string(),
+                });
             }
-            "cthin" => {
-                self.wiring_type = ReflectorType::CThin;
-                Ok(())
+            match position.parse::<usize>() {
+                Ok(num) => {
+                    if num >= 26 {
+                        return Err(Error::InvalidKeyFmt {
+                            expected: "An integer from 0 to 25".to_string(),
+                            actual: position.to_string(),
+                        });
+                    }
+                    self.position = num as i16;
+                }
+                Err(_) => {
+                    return Err(Error::InvalidKeyFmt {
+                        expected: "An integer".to_string(),
+                        actual: position.to_string(),
+                    })
+                }
             }
-            _ => Err(Error::InvalidKeyFmt {
-                expected: "One of [a, b, c, bthin, cthin]".to_string(),
-                actual: arg.to_string(),
-            }),
         }
+        Ok(())
     }
 }
 
 impl StatefulKey for Reflector {
     fn reset(&mut self, _language: &mut Language) {
-        self.wiring_type = ReflectorType::B;
+        self.wiring_type = if self.allowed.contains(&ReflectorType::B) {
+            ReflectorType::B
+        } else {
+            self.allowed[0]
+        };
+        self.position = 0;
+        self.rewiring = UKW_D_DEFAULT;
     }
-    fn to_string(&self, _language: &mut Language) -> String {
-        match self.wiring_type {
-            ReflectorType::A => "A",
-            ReflectorType::B => "B",
-            ReflectorType::C => "C",
-            ReflectorType::BThin => "B thin",
-            ReflectorType::CThin => "C thin",
+    fn to_string(&self, language: &mut Language) -> String {
+        if self.wiring_type == ReflectorType::D {
+            let pairs = (0..26)
+                .filter(|&letter| letter < self.rewiring[letter as usize])
+                .map(|letter| {
+                    format!(
+                        "{}{}",
+                        language.cp_to_upper(letter),
+                        language.cp_to_upper(self.rewiring[letter as usize])
+                    )
+                })
+                .collect::<Vec<_>>();
+            format!("type:D, pairs:{}", pairs.join(" "))
+        } else if self.wiring_type.is_settable() {
+            format!(
+                "type:{}, position:{}",
+                self.wiring_type.name(),
+                self.position
+            )
+        } else {
+            self.wiring_type.name().to_string()
         }
-        .to_string()
     }
     fn randomize(&mut self, _language: &mut Language) {
-        self.wiring_type = ReflectorType::from(fastrand::usize(0..5));
+        self.wiring_type = self.allowed[fastrand::usize(0..self.allowed.len())];
+        self.position = if self.wiring_type.is_settable() {
+            fastrand::i16(0..26)
+        } else {
+            0
+        };
+
+        let mut letters = (0..26)
+            .filter(|&letter| letter != UKW_D_FIXED.0 && letter != UKW_D_FIXED.1)
+            .collect::<Vec<_>>();
+        util::shuffle(&mut letters);
+        let pairs = letters
+            .chunks(2)
+            .map(|pair| (pair[0], pair[1]))
+            .collect::<Vec<_>>();
+        self.rewire(&pairs);
     }
 }
 
@@ -139,6 +398,6 @@ impl IoKey for Reflector {
         &mut self.info
     }
     fn desc(&self) -> String {
-        "<reflector_name>".to_string()
+        "<reflector_name>:<position> or d:<12 colon delimited pairs of letters>".to_string()
     }
 }
@@ -8,11 +8,13 @@ use crate::{
 // Wiring details from: https://en.wikipedia.org/wiki/Enigma_rotor_details#Rotor_wiring_tables
 
 /// Collection of all Enigma Rotor types, from the Enigma I, M3 Army Enigma,
-/// M3 & M4 Naval Enigma, and M4 R2 Enigma models. All of these Enigma models
-/// can be simulated using combinations of these rotors. (See Enigma cipher)
+/// M3 & M4 Naval Enigma, and M4 R2 Enigma models, along with the commercial
+/// Enigma K, Swiss-K, Railway Enigma and the Abwehr Enigma G (G-312, G-260 and
+/// G-111). All of these Enigma models can be simulated using combinations of these
+/// rotors. (See Enigma cipher)
 ///
-#[derive(Clone, Copy)]
-#[allow(clippy::clippy::upper_case_acronyms)]
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+#[allow(clippy::upper_case_acronyms)]
 pub enum RotorType {
     I = 0,
     II = 1,
@@ -24,6 +26,93 @@ pub enum RotorType {
     VIII = 7,
     Beta = 8,
     Gamma = 9,
+    KI = 10,
+    KII = 11,
+    KIII = 12,
+    SwissKI = 13,
+    SwissKII = 14,
+    SwissKIII = 15,
+    RailwayI = 16,
+    RailwayII = 17,
+    RailwayIII = 18,
+    G312I = 19,
+    G312II = 20,
+    G312III = 21,
+    G260I = 22,
+    G260II = 23,
+    G260III = 24,
+    G111I = 25,
+    G111II = 26,
+    G111V = 27,
+}
+
+/// Every rotor type, in order
+const ROTOR_TYPES: [RotorType; 28] = [
+    RotorType::I,
+    RotorType::II,
+    RotorType::III,
+    RotorType::IV,
+    RotorType::V,
+    RotorType::VI,
+    RotorType::VII,
+    RotorType::VIII,
+    RotorType::Beta,
+    RotorType::Gamma,
+    RotorType::KI,
+    RotorType::KII,
+    RotorType::KIII,
+    RotorType::SwissKI,
+    RotorType::SwissKII,
+    RotorType::SwissKIII,
+    RotorType::RailwayI,
+    RotorType::RailwayII,
+    RotorType::RailwayIII,
+    RotorType::G312I,
+    RotorType::G312II,
+    RotorType::G312III,
+    RotorType::G260I,
+    RotorType::G260II,
+    RotorType::G260III,
+    RotorType::G111I,
+    RotorType::G111II,
+    RotorType::G111V,
+];
+
+impl RotorType {
+    /// The name of the rotor type
+    ///
+    pub fn name(&self) -> &'static str {
+        match *self {
+            RotorType::I => "I",
+            RotorType::II => "II",
+            RotorType::III => "III",
+            RotorType::IV => "IV",
+            RotorType::V => "V",
+            RotorType::VI => "VI",
+            RotorType::VII => "VII",
+            RotorType::VIII => "VIII",
+            RotorType::Beta => "Beta",
+            RotorType::Gamma => "Gamma",
+            RotorType::KI => "K-I",
+            RotorType::KII => "K-II",
+            RotorType::KIII => "K-III",
+            RotorType::SwissKI => "SwissK-I",
+            RotorType::SwissKII => "SwissK-II",
+            RotorType::SwissKIII => "SwissK-III",
+            RotorType::RailwayI => "Railway-I",
+            RotorType::RailwayII => "Railway-II",
+            RotorType::RailwayIII => "Railway-III",
+            RotorType::G312I => "G312-I",
+            RotorType::G312II => "G312-II",
+            RotorType::G312III => "G312-III",
+            RotorType::G260I => "G260-I",
+            RotorType::G260II => "G260-II",
+            RotorType::G260III => "G260-III",
+            RotorType::G111I => "G111-I",
+            RotorType::G111II => "G111-II",
+            RotorType::G111V => "G111-V",
+        }
+    }
 }
 
 impl From<usize> for RotorType {
@@ -42,7 +131,7 @@ impl From<usize> for RotorType {
 }
 
 #[rustfmt::skip]
-const WIRINGS: [[i16; 26]; 10] = [
+const WIRINGS: [[i16; 26]; 28] = [
     [ 4, 10, 12, 5, 11, 6, 3, 16, 21, 25, 13, 19, 14, 22, 24, 7, 23, 20, 18, 15, 0, 8, 1, 17, 2, 9, ],
     [ 0, 9, 3, 10, 18, 8, 17, 20, 23, 1, 11, 7, 22, 19, 12, 2, 16, 6, 25, 13, 15, 24, 5, 21, 14, 4, ],
     [ 1, 3, 5, 7, 9, 11, 2, 15, 17, 19, 23, 21, 25, 13, 24, 4, 8, 22, 6, 0, 10, 12, 20, 18, 16, 14, ],
@@ -53,9 +142,27 @@ const WIRINGS: [[i16; 26]; 10] = [
     [ 5, 10, 16, 7, 19, 11, 23, 14, 2, 1, 9, 18, 15, 3, 25, 17, 0, 12, 4, 22, 13, 8, 20, 24, 6, 21, ],
     [ 11, 4, 24, 9, 21, 2, 13, 8, 23, 22, 15, 1, 16, 12, 3, 17, 19, 0, 10, 25, 6, 5, 20, 7, 14, 18, ],
     [ 5, 18, 14, 10, 0, 13, 20, 4, 17, 7, 12, 1, 19, 8, 24, 2, 22, 11, 16, 15, 25, 23, 21, 6, 9, 3, ],
+    [ 11, 15, 6, 18, 25, 12, 7, 0, 4, 14, 16, 10, 21, 23, 17, 5, 24, 1, 20, 19, 13, 8, 2, 9, 3, 22, ],
+    [ 18, 11, 21, 6, 1, 19, 5, 23, 9, 16, 14, 7, 4, 22, 8, 17, 25, 24, 0, 12, 10, 15, 2, 13, 3, 20, ],
+    [ 2, 9, 6, 3, 15, 18, 7, 10, 19, 20, 17, 0, 22, 25, 23, 5, 12, 24, 13, 16, 14, 1, 21, 11, 8, 4, ],
+    [ 15, 4, 25, 20, 14, 7, 23, 18, 2, 21, 5, 12, 19, 1, 6, 11, 17, 8, 13, 16, 9, 22, 0, 24, 3, 10, ],
+    [ 25, 14, 20, 4, 18, 24, 3, 10, 5, 22, 15, 2, 8, 16, 23, 7, 12, 21, 1, 11, 6, 13, 9, 17, 0, 19, ],
+    [ 4, 7, 17, 21, 23, 6, 0, 14, 1, 16, 20, 18, 8, 12, 25, 5, 11, 24, 13, 22, 10, 19, 15, 3, 9, 2, ],
+    [ 9, 6, 3, 16, 14, 23, 20, 18, 2, 0, 12, 8, 5, 17, 21, 19, 15, 13, 4, 22, 10, 1, 11, 25, 24, 7, ],
+    [ 13, 19, 25, 15, 18, 5, 1, 14, 10, 12, 22, 17, 2, 9, 3, 8, 21, 11, 0, 4, 24, 20, 23, 7, 6, 16, ],
+    [ 9, 21, 8, 20, 1, 7, 19, 2, 3, 24, 0, 10, 4, 16, 25, 15, 14, 18, 6, 23, 13, 17, 12, 22, 5, 11, ],
+    [ 3, 12, 19, 22, 18, 8, 11, 17, 20, 24, 16, 13, 10, 5, 4, 9, 2, 0, 25, 1, 15, 6, 23, 14, 7, 21, ],
+    [ 7, 16, 25, 6, 15, 9, 19, 12, 14, 1, 11, 13, 2, 8, 5, 3, 24, 0, 22, 21, 4, 20, 18, 17, 10, 23, ],
+    [ 20, 16, 13, 19, 11, 18, 25, 5, 12, 17, 4, 7, 3, 15, 23, 10, 8, 1, 21, 24, 6, 9, 2, 22, 14, 0, ],
+    [ 17, 2, 18, 15, 1, 11, 10, 16, 0, 20, 12, 7, 22, 24, 19, 8, 5, 25, 21, 6, 14, 9, 13, 4, 23, 3, ],
+    [ 22, 2, 12, 8, 1, 21, 15, 9, 23, 0, 17, 14, 18, 6, 13, 3, 11, 25, 10, 4, 24, 7, 20, 5, 16, 19, ],
+    [ 5, 21, 3, 7, 25, 4, 11, 18, 16, 12, 0, 23, 14, 10, 24, 8, 22, 15, 6, 2, 1, 20, 9, 19, 13, 17, ],
+    [ 22, 11, 17, 7, 1, 16, 20, 13, 3, 10, 9, 2, 25, 18, 4, 23, 14, 19, 12, 0, 6, 24, 5, 15, 21, 8, ],
+    [ 19, 5, 9, 16, 0, 25, 22, 12, 7, 11, 2, 20, 8, 23, 17, 3, 24, 6, 14, 4, 21, 1, 13, 18, 10, 15, ],
+    [ 16, 19, 15, 8, 23, 22, 21, 3, 5, 17, 12, 20, 18, 11, 9, 14, 7, 2, 0, 13, 4, 25, 10, 24, 1, 6, ],
 ];
 #[rustfmt::skip]
-const INV_WIRINGS: [[i16; 26]; 10] = [
+const INV_WIRINGS: [[i16; 26]; 28] = [
     [ 20, 22, 24, 6, 0, 3, 5, 15, 21, 25, 1, 4, 2, 10, 12, 19, 7, 23, 18, 11, 17, 8, 13, 16, 14, 9, ],
     [ 0, 9, 15, 2, 25, 22, 17, 11, 5, 1, 3, 10, 14, 19, 24, 20, 16, 6, 4, 13, 7, 23, 12, 8, 21, 18, ],
     [ 19, 0, 6, 1, 15, 2, 18, 3, 16, 4, 20, 5, 21, 13, 25, 7, 24, 8, 23, 9, 22, 11, 17, 10, 14, 12, ],
@@ -66,26 +173,216 @@ const INV_WIRINGS: [[i16; 26]; 10] = [
     [ 16, 9, 8, 13, 18, 0, 24, 3, 21, 10, 1, 5, 17, 20, 7, 12, 2, 15, 11, 4, 22, 25, 19, 6, 23, 14, ],
     [ 17, 11, 5, 14, 1, 21, 20, 23, 7, 3, 18, 0, 13, 6, 24, 10, 12, 15, 25, 16, 22, 4, 9, 8, 2, 19, ],
     [ 4, 11, 15, 25, 7, 0, 23, 9, 13, 24, 3, 17, 10, 5, 2, 19, 18, 8, 1, 12, 6, 22, 16, 21, 14, 20, ],
+    [ 7, 17, 22, 24, 8, 15, 2, 6, 21, 23, 11, 0, 5, 20, 9, 1, 10, 14, 3, 19, 18, 12, 25, 13, 16, 4, ],
+    [ 18, 4, 22, 24, 12, 6, 3, 11, 14, 8, 20, 1, 19, 23, 10, 21, 9, 15, 0, 5, 25, 2, 13, 7, 17, 16, ],
+    [ 11, 21, 0, 3, 25, 15, 2, 6, 24, 1, 7, 23, 16, 18, 20, 4, 19, 10, 5, 8, 9, 22, 12, 14, 17, 13, ],
+    [ 22, 13, 8, 24, 1, 10, 14, 5, 17, 20, 25, 15, 11, 18, 4, 0, 19, 16, 7, 12, 3, 9, 21, 6, 23, 2, ],
+    [ 24, 18, 11, 6, 3, 8, 20, 15, 12, 22, 7, 19, 16, 21, 1, 10, 13, 23, 4, 25, 2, 17, 9, 14, 5, 0, ],
+    [ 6, 8, 25, 23, 0, 15, 5, 1, 12, 24, 20, 16, 13, 18, 7, 22, 9, 2, 11, 21, 10, 3, 19, 4, 17, 14, ],
+    [ 9, 21, 8, 2, 18, 12, 1, 25, 11, 0, 20, 22, 10, 17, 4, 16, 3, 13, 7, 15, 6, 14, 19, 5, 24, 23, ],
+    [ 18, 6, 12, 14, 19, 5, 24, 23, 15, 13, 8, 17, 9, 0, 7, 3, 25, 11, 4, 1, 21, 16, 10, 22, 20, 2, ],
+    [ 10, 4, 7, 8, 12, 24, 18, 5, 2, 0, 11, 25, 22, 20, 16, 15, 13, 21, 17, 6, 3, 1, 23, 19, 9, 14, ],
+    [ 17, 19, 16, 0, 14, 13, 21, 24, 5, 15, 12, 6, 1, 11, 23, 20, 10, 7, 4, 2, 8, 25, 3, 22, 9, 18, ],
+    [ 17, 9, 12, 15, 20, 14, 3, 0, 13, 5, 24, 10, 7, 11, 8, 4, 1, 23, 22, 6, 21, 19, 18, 25, 16, 2, ],
+    [ 25, 17, 22, 12, 10, 7, 20, 11, 16, 21, 15, 4, 8, 2, 24, 13, 1, 9, 5, 3, 0, 18, 23, 14, 19, 6, ],
+    [ 8, 4, 1, 25, 23, 16, 19, 11, 15, 21, 6, 5, 10, 22, 20, 3, 7, 0, 2, 14, 9, 18, 12, 24, 13, 17, ],
+    [ 9, 4, 1, 15, 19, 23, 13, 21, 3, 7, 18, 16, 2, 14, 11, 6, 24, 10, 12, 25, 22, 5, 0, 8, 20, 17, ],
+    [ 10, 20, 19, 2, 5, 0, 18, 3, 15, 22, 13, 6, 9, 24, 12, 17, 8, 25, 7, 23, 21, 1, 16, 11, 14, 4, ],
+    [ 19, 4, 11, 8, 14, 22, 20, 3, 25, 10, 9, 1, 18, 7, 16, 23, 5, 2, 13, 17, 6, 24, 0, 15, 21, 12, ],
+    [ 4, 21, 10, 15, 19, 1, 17, 8, 12, 2, 24, 9, 7, 22, 18, 25, 3, 14, 23, 0, 11, 20, 6, 13, 16, 5, ],
+    [ 18, 24, 17, 7, 20, 8, 25, 16, 3, 14, 22, 13, 10, 19, 15, 2, 0, 9, 12, 1, 11, 6, 5, 4, 23, 21, ],
 ];
-const NOTCHES: [[i16; 2]; 10] = [
-    [16, 16],
-    [4, 4],
-    [21, 21],
-    [9, 9],
-    [25, 25],
-    [25, 12],
-    [25, 12],
-    [25, 12],
-    [-1, -1], // Beta rotor does not turn others
-    [-1, -1], // Gamma rotor does not turn others
+const NOTCHES: [&[i16]; 28] = [
+    &[16],
+    &[4],
+    &[21],
+    &[9],
+    &[25],
+    &[25, 12],
+    &[25, 12],
+    &[25, 12],
+    &[], // Beta rotor does not turn others
+    &[], // Gamma rotor does not turn others
+    &[24],
+    &[4],
+    &[13],
+    &[24],
+    &[4],
+    &[13],
+    &[13],
+    &[4],
+    &[24],
+    &[0, 1, 2, 4, 5, 6, 8, 10, 11, 14, 15, 16, 18, 20, 21, 22, 25],
+    &[0, 2, 3, 5, 6, 7, 10, 12, 13, 16, 18, 19, 21, 24, 25],
+    &[0, 4, 5, 7, 10, 12, 13, 17, 20, 22, 23],
+    &[0, 1, 2, 4, 5, 6, 8, 10, 11, 14, 15, 16, 18, 20, 21, 22, 25],
+    &[0, 2, 3, 5, 6, 7, 10, 12, 13, 16, 18, 19, 21, 24, 25],
+    &[0, 4, 5, 7, 10, 12, 13, 17, 20, 22, 23],
+    &[0, 1, 2, 4, 5, 6, 8, 10, 11, 14, 15, 16, 18, 20, 21, 22, 25],
+    &[0, 2, 3, 5, 6, 7, 10, 12, 13, 16, 18, 19, 21, 24, 25],
+    &[5, 7, 12, 16, 18, 22, 25],
 ];
 
-/// Represents an Enigma Rotor (See Enigma cipher)
+/// The number of letters on the built-in rotors
+const BUILT_IN_LEN: usize = 26;
+
+/// The wiring and notches of a rotor, which can be one of the built-in rotor types
+/// or defined at runtime for captured or fictional rotors. The wiring can use any
+/// alphabet length.
+///
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct RotorDefinition {
+    /// The output of the rotor for each input letter
+    wiring: Vec<i16>,
+    /// The input of the rotor for each output letter
+    inverse: Vec<i16>,
+    /// The positions at which the rotor turns the next rotor
+    notches: Vec<i16>,
+}
+
+impl RotorDefinition {
+    /// Creates a rotor definition, provided that the wiring is a permutation and the
+    /// notches are valid positions
+    ///
+    /// # Arguments
+    ///
+    /// * `wiring` The output of the rotor for each input letter
+    /// * `notches` The positions at which the rotor turns the next rotor
+    ///
+    pub fn new(wiring: &[i16], notches: &[i16]) -> Result<RotorDefinition> {
+        let len = wiring.len();
+
+        let mut inverse = vec![-1; len];
+        for (letter, &output) in wiring.iter().enumerate() {
+            if output < 0 || output as usize >= len || inverse[output as usize] != -1 {
+                return Err(Error::InvalidKeyFmt {
+                    expected: format!("A permutation of the {} letters", len),
+                    actual: format!("{:?}", wiring),
+                });
+            }
+            inverse[output as usize] = letter as i16;
+        }
+
+        if let Some(&notch) = notches
+            .iter()
+            .find(|&&notch| notch < 0 || notch as usize >= len)
+        {
+            return Err(Error::InvalidKeyFmt {
+                expected: format!("A notch position from 0 to {}", len as i16 - 1),
+                actual: notch.to_string(),
+            });
+        }
+
+        let mut notches = notches.to_vec();
+        notches.sort_unstable();
+        notches.dedup();
+
+        Ok(RotorDefinition {
+            wiring: wiring.to_vec(),
+            inverse,
+            notches,
+        })
+    }
+
+    /// Parses a rotor definition from the letters of the wiring, in the current
+    /// alphabet of the language, and the letters of the notch positions
+    ///
+    /// # Arguments
+    ///
+    /// * `language` A [`Language`] instance
+    /// * `wiring` The output letter of the rotor for each letter of the alphabet
+    /// * `notches` The letters at which the rotor turns the next rotor
+    ///
+    pub fn parse(language: &Language, wiring: &str, notches: &str) -> Result<RotorDefinition> {
+        let wiring_cps = language.string_to_vec(wiring);
+        if wiring_cps.len() != language.alphabet_len() {
+            return Err(Error::InvalidKeyFmt {
+                expected: format!("A permutation of the {} letters", language.alphabet_len()),
+                actual: wiring.to_string(),
+            });
+        }
+
+        RotorDefinition::new(&wiring_cps, &language.string_to_vec(notches))
+    }
+
+    /// The number of letters on the rotor
+    ///
+    pub fn len(&self) -> usize {
+        self.wiring.len()
+    }
+
+    /// Whether the rotor has no letters
+    ///
+    pub fn is_empty(&self) -> bool {
+        self.wiring.is_empty()
+    }
+
+    /// The output of the rotor for each input letter
+    ///
+    pub fn wiring(&self) -> &[i16] {
+        &self.wiring
+    }
+
+    /// The positions at which the rotor turns the next rotor
+    ///
+    pub fn notches(&self) -> &[i16] {
+        &self.notches
+    }
+
+    /// The definition of the rotor when it is inserted backwards, so the signal
+    /// passes through the wiring from the other side and the positions of the letters
+    /// (and notches) are mirrored
+    ///
+    pub fn reversed(&self) -> RotorDefinition {
+        let len = self.len() as i16;
+        let mirror = |letter: i16| util::modulo(-letter, len);
+
+        let mut notches = self
+            .notches
+            .iter()
+            .map(|&notch| mirror(notch))
+            .collect::<Vec<_>>();
+        notches.sort_unstable();
+
+        RotorDefinition {
+            wiring: (0..len)
+                .map(|letter| mirror(self.inverse[mirror(letter) as usize]))
+                .collect(),
+            inverse: (0..len)
+                .map(|letter| mirror(self.wiring[mirror(letter) as usize]))
+                .collect(),
+            notches,
+        }
+    }
+}
+
+impl From<RotorType> for RotorDefinition {
+    fn from(arg: RotorType) -> Self {
+        RotorDefinition {
+            wiring: WIRINGS[arg as usize].to_vec(),
+            inverse: INV_WIRINGS[arg as usize].to_vec(),
+            notches: NOTCHES[arg as usize].to_vec(),
+        }
+    }
+}
+
+/// Represents an Enigma Rotor (See Enigma cipher). The rotor is either one of the
+/// built-in rotor types, or a custom [`RotorDefinition`], which can be used in any
+/// position of the machine as long as it has the same number of letters.
 ///
 #[derive(Clone)]
 pub struct Rotor {
-    /// The internal wiring to use
-    wiring_type: RotorType,
+    /// The built-in type of the rotor, or `None` for a custom rotor
+    wiring_type: Option<RotorType>,
+
+    /// The internal wiring and notches to use, mirrored if the rotor is reversed
+    definition: RotorDefinition,
+
+    /// Whether the rotor is inserted backwards
+    reversed: bool,
+
+    /// Whether the machine allows the rotor to be inserted backwards
+    reversible: bool,
 
     /// The outer position (incremental)
     pub grund: i16,
@@ -93,6 +390,9 @@ pub struct Rotor {
     /// The inner position (fixed)
     pub rings: i16,
 
+    /// The rotor types that can be used in this position of the machine
+    allowed: Vec<RotorType>,
+
     info: KeyInfo,
 }
 
@@ -106,26 +406,30 @@ impl Rotor {
     /// * `reverse` Whether to send the letter through backwards
     ///
     pub fn input(&self, letter: i16, reverse: bool) -> i16 {
+        let len = self.definition.len() as i16;
         let offset = self.grund - self.rings;
-        let rotor_idx = self.wiring_type as usize;
-        let pos = util::modulo(letter + offset, 26) as usize;
+        let pos = util::modulo(letter + offset, len) as usize;
 
-        let wirings = if reverse { &INV_WIRINGS } else { &WIRINGS };
+        let wiring = if reverse {
+            &self.definition.inverse
+        } else {
+            &self.definition.wiring
+        };
 
-        util::modulo(wirings[rotor_idx][pos] - offset, 26)
+        util::modulo(wiring[pos] - offset, len)
     }
 
     /// Is the rotor's current outer position (grund) at the notch point / one of
     /// the notch points for that rotor?
     ///
     pub fn is_on_notch(&self) -> bool {
-        NOTCHES[self.wiring_type as usize].contains(&self.grund)
+        self.definition.notches.contains(&self.grund)
     }
 
     /// Advance the outer position (grund) by a single step (rotates the rotor).
     ///
     pub fn step(&mut self) {
-        self.grund = util::modulo(self.grund + 1, 26);
+        self.grund = util::modulo(self.grund + 1, self.definition.len() as i16);
     }
 
     /// Reset the outer (grund) and inner (rings) positions
@@ -134,19 +438,186 @@ impl Rotor {
         self.grund = 0;
         self.rings = 0;
     }
+
+    /// The built-in type of the rotor, which decides its wiring and notches, or
+    /// `None` for a custom rotor
+    ///
+    pub fn wiring_type(&self) -> Option<RotorType> {
+        self.wiring_type
+    }
+
+    /// The wiring and notches of the rotor
+    ///
+    pub fn definition(&self) -> &RotorDefinition {
+        &self.definition
+    }
+
+    /// Restricts the built-in rotor types that can be set, for the position of this
+    /// rotor in a particular machine. If the current type is not allowed, it is set to
+    /// the first allowed type.
+    ///
+    /// # Arguments
+    ///
+    /// * `allowed` The allowed rotor types, which must not be empty
+    ///
+    pub fn set_allowed(&mut self, allowed: &[RotorType]) {
+        debug_assert!(!allowed.is_empty());
+
+        self.allowed = allowed.to_vec();
+        if self
+            .wiring_type
+            .is_some_and(|rotor_type| !self.allowed.contains(&rotor_type))
+        {
+            self.wiring_type = Some(self.allowed[0]);
+            self.set_definition(RotorDefinition::from(self.allowed[0]));
+        }
+    }
+
+    /// Whether the rotor is inserted backwards
+    ///
+    pub fn is_reversed(&self) -> bool {
+        self.reversed
+    }
+
+    /// Inserts the rotor backwards or forwards, provided that the machine allows
+    /// reversed rotors (See [`Rotor::set_reversible`])
+    ///
+    /// # Arguments
+    ///
+    /// * `reversed` Whether the rotor should be inserted backwards
+    ///
+    pub fn set_reversed(&mut self, reversed: bool) {
+        debug_assert!(self.reversible || !reversed);
+
+        if reversed != self.reversed {
+            self.reversed = reversed;
+            self.definition = self.definition.reversed();
+        }
+    }
+
+    /// Sets whether the machine allows the rotor to be inserted backwards. If not,
+    /// a reversed rotor is inserted forwards.
+    ///
+    /// # Arguments
+    ///
+    /// * `reversible` Whether the rotor can be inserted backwards
+    ///
+    pub fn set_reversible(&mut self, reversible: bool) {
+        if !reversible {
+            self.set_reversed(false);
+        }
+        self.reversible = reversible;
+    }
+
+    /// Sets the wiring and notches of the rotor, mirroring them if the rotor is
+    /// reversed
+    ///
+    /// # Arguments
+    ///
+    /// * `definition` The wiring and notches of the rotor inserted forwards
+    ///
+    fn set_definition(&mut self, definition: RotorDefinition) {
+        self.definition = if self.reversed {
+            definition.reversed()
+        } else {
+            definition
+        };
+    }
+
+    /// Checks that a wiring has the same number of letters as the rotor
+    ///
+    /// # Arguments
+    ///
+    /// * `len` The number of letters of the wiring
+    ///
+    fn check_len(&self, len: usize) -> Result<()> {
+        if len == self.definition.len() {
+            Ok(())
+        } else {
+            Err(Error::InvalidKeyFmt {
+                expected: format!("A wiring of {} letters", self.definition.len()),
+                actual: format!("A wiring of {} letters", len),
+            })
+        }
+    }
+
+    /// Parses a position of the rotor
+    ///
+    /// # Arguments
+    ///
+    /// * `position` The position to parse
+    ///
+    fn parse_position(&self, position: &str) -> Result<i16> {
+        match position.parse::<usize>() {
+            Ok(num) => {
+                if num >= self.definition.len() {
+                    return Err(Error::InvalidKeyFmt {
+                        expected: format!("An integer from 0 to {}", self.definition.len() - 1),
+                        actual: position.to_string(),
+                    });
+                }
+                Ok(num as i16)
+            }
+            Err(_) => Err(Error::InvalidKeyFmt {
+                expected: "An integer".to_string(),
+                actual: position.to_string(),
+            }),
+        }
+    }
 }
 
 impl Key<RotorType> for Rotor {
     fn new(_language: &mut Language, arg: RotorType) -> Result<Box<Self>> {
         Ok(Box::new(Rotor {
-            wiring_type: arg,
+            wiring_type: Some(arg),
+            definition: RotorDefinition::from(arg),
+            reversed: false,
+            reversible: false,
             grund: 0,
             rings: 0,
+            allowed: ROTOR_TYPES.to_vec(),
             info: KeyInfo::default(),
         }))
     }
     fn set(&mut self, _language: &mut Language, arg: RotorType) -> Result<()> {
-        self.wiring_type = arg;
+        if !self.allowed.contains(&arg) {
+            return Err(Error::InvalidKeyFmt {
+                expected: format!(
+                    "One of [{}]",
+                    self.allowed
+                        .iter()
+                        .map(|rotor| rotor.name().to_lowercase())
+                        .collect::<Vec<_>>()
+                        .join(", ")
+                ),
+                actual: arg.name().to_lowercase(),
+            });
+        }
+        self.check_len(BUILT_IN_LEN)?;
+
+        self.wiring_type = Some(arg);
+        self.set_definition(RotorDefinition::from(arg));
+        Ok(())
+    }
+}
+impl Key<RotorDefinition> for Rotor {
+    fn new(_language: &mut Language, arg: RotorDefinition) -> Result<Box<Self>> {
+        Ok(Box::new(Rotor {
+            wiring_type: None,
+            definition: arg,
+            reversed: false,
+            reversible: false,
+            grund: 0,
+            rings: 0,
+            allowed: ROTOR_TYPES.to_vec(),
+            info: KeyInfo::default(),
+        }))
+    }
+    fn set(&mut self, _language: &mut Language, arg: RotorDefinition) -> Result<()> {
+        self.check_len(arg.len())?;
+
+        self.wiring_type = None;
+        self.set_definition(arg);
         Ok(())
     }
 }
@@ -156,63 +627,67 @@ impl Key<&str> for Rotor {
         result.set(language, arg)?;
         Ok(result)
     }
-    fn set(&mut self, _language: &mut Language, arg: &str) -> Result<()> {
+    fn set(&mut self, language: &mut Language, arg: &str) -> Result<()> {
         let args = arg.split(':').collect::<Vec<_>>();
-        if let Some(&rotor) = args.get(0) {
-            match rotor.to_lowercase().as_str() {
-                "i" => self.wiring_type = RotorType::I,
-                "ii" => self.wiring_type = RotorType::II,
-                "iii" => self.wiring_type = RotorType::III,
-                "iv" => self.wiring_type = RotorType::IV,
-                "v" => self.wiring_type = RotorType::V,
-                "vi" => self.wiring_type = RotorType::VI,
-                "vii" => self.wiring_type = RotorType::VII,
-                "viii" => self.wiring_type = RotorType::VIII,
-                "beta" | "b" => self.wiring_type = RotorType::Beta,
-                "gamma" | "g" => self.wiring_type = RotorType::Gamma,
-                _ => {
-                    return Err(Error::InvalidKeyFmt {
-                        expected: "One of [i, ii, iii, iv, v, vi, vii, viii, beta, gamma]"
-                            .to_string(),
-                        actual: arg.to_string(),
-                    })
-                }
+        if let Some(&rotor) = args.first() {
+            let rotor_type = match rotor.to_lowercase().as_str() {
+                "b" => Some(RotorType::Beta),
+                "g" => Some(RotorType::Gamma),
+                name => ROTOR_TYPES
+                    .iter()
+                    .copied()
+                    .find(|rotor_type| rotor_type.name().to_lowercase() == name),
             };
-        }
-        if let Some(&grund) = args.get(1) {
-            match grund.parse::<usize>() {
-                Ok(num) => {
-                    if num >= 26 {
+            match rotor_type {
+                Some(rotor_type) => self.set(language, rotor_type)?,
+                None => {
+                    // a custom rotor, as the letters of the wiring then the notches
+                    let mut parts = rotor.splitn(2, '/');
+                    let wiring = parts.next().unwrap_or_default();
+                    let notches = parts.next().unwrap_or_default();
+
+                    if !language.set_alph_len(self.definition.len()) {
                         return Err(Error::InvalidKeyFmt {
-                            expected: "An integer from 0 to 25".to_string(),
-                            actual: grund.to_string(),
+                            expected: "An alphabet length supported by the language".to_string(),
+                            actual: self.definition.len().to_string(),
                         });
                     }
-                    self.grund = num as i16;
-                }
-                Err(_) => {
-                    return Err(Error::InvalidKeyFmt {
-                        expected: "An integer".to_string(),
-                        actual: grund.to_string(),
-                    })
+                    let definition =
+                        RotorDefinition::parse(language, wiring, notches).map_err(|_| {
+                            Error::InvalidKeyFmt {
+                                expected: format!(
+                                    "One of [{}], or a permutation of the letters followed by '/' and the notch letters",
+                                    ROTOR_TYPES
+                                        .iter()
+                                        .map(|rotor_type| rotor_type.name().to_lowercase())
+                                        .collect::<Vec<_>>()
+                                        .join(", ")
+                                ),
+                                actual: arg.to_string(),
+                            }
+                        })?;
+                    self.set(language, definition)?;
                 }
             }
         }
+        if let Some(&grund) = args.get(1) {
+            self.grund = self.parse_position(grund)?;
+        }
         if let Some(&rings) = args.get(2) {
-            match rings.parse::<usize>() {
-                Ok(num) => {
-                    if num >= 26 {
-                        return Err(Error::InvalidKeyFmt {
-                            expected: "An integer from 0 to 25".to_string(),
-                            actual: rings.to_string(),
-                        });
-                    }
-                    self.rings = num as i16;
-                }
-                Err(_) => {
+            self.rings = self.parse_position(rings)?;
+        }
+        if let Some(&direction) = args.get(3) {
+            match direction.to_lowercase().as_str() {
+                "f" => self.set_reversed(false),
+                "r" if self.reversible => self.set_reversed(true),
+                _ => {
                     return Err(Error::InvalidKeyFmt {
-                        expected: "An integer".to_string(),
-                        actual: rings.to_string(),
+                        expected: if self.reversible {
+                            "f (forwards) or r (reversed)".to_string()
+                        } else {
+                            "f (forwards), as the rotor cannot be reversed".to_string()
+                        },
+                        actual: direction.to_string(),
                     })
                 }
             }
@@ -223,32 +698,49 @@ impl Key<&str> for Rotor {
 
 impl StatefulKey for Rotor {
     fn reset(&mut self, _language: &mut Language) {
-        self.wiring_type = RotorType::I;
+        self.set_reversed(false);
+        if self.definition.len() == BUILT_IN_LEN {
+            self.wiring_type = Some(self.allowed[0]);
+            self.set_definition(RotorDefinition::from(self.allowed[0]));
+        }
         self.reset_positions();
     }
-    fn to_string(&self, _language: &mut Language) -> String {
+    fn to_string(&self, language: &mut Language) -> String {
+        let name = match self.wiring_type {
+            Some(rotor_type) => rotor_type.name().to_string(),
+            None => {
+                let definition = if self.reversed {
+                    self.definition.reversed()
+                } else {
+                    self.definition.clone()
+                };
+                format!(
+                    "{}/{}",
+                    language.vec_to_string(&definition.wiring).to_uppercase(),
+                    language.vec_to_string(&definition.notches).to_uppercase()
+                )
+            }
+        };
         format!(
-            "type:{}, grund:{}, rings:{}",
-            match self.wiring_type {
-                RotorType::I => "I",
-                RotorType::II => "II",
-                RotorType::III => "III",
-                RotorType::IV => "IV",
-                RotorType::V => "V",
-                RotorType::VI => "VI",
-                RotorType::VII => "VII",
-                RotorType::VIII => "VIII",
-                RotorType::Beta => "Beta",
-                RotorType::Gamma => "Gamma",
-            },
+            "type:{}, grund:{}, rings:{}{}",
+            name,
             self.grund,
-            self.rings
+            self.rings,
+            if self.reversed { ", reversed" } else { "" }
         )
     }
     fn randomize(&mut self, _language: &mut Language) {
-        self.wiring_type = RotorType::from(fastrand::usize(0..10));
-        self.grund = fastrand::i16(0..26);
-        self.rings = fastrand::i16(0..26);
+        if self.definition.len() == BUILT_IN_LEN {
+            let rotor_type = self.allowed[fastrand::usize(0..self.allowed.len())];
+            self.wiring_type = Some(rotor_type);
+            self.set_definition(RotorDefinition::from(rotor_type));
+        }
+        if self.reversible {
+            self.set_reversed(fastrand::bool());
+        }
+        let len = self.definition.len() as i16;
+        self.grund = fastrand::i16(0..len);
+        self.rings = fastrand::i16(0..len);
     }
 }
 
@@ -263,6 +755,42 @@ impl IoKey for Rotor {
         &mut self.info
     }
     fn desc(&self) -> String {
-        "<rotor_name>:<grund>:<rings>".to_string()
+        if self.reversible {
+            "<rotor_name or wiring/notches>:<grund>:<rings>:<f or r>".to_string()
+        } else {
+            "<rotor_name or wiring/notches>:<grund>:<rings>".to_string()
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn custom_rotor() {
+        let mut language = Language::from_file("examples/data/english.bin").unwrap();
+
+        // rotor I, defined at runtime
+        let definition =
+            RotorDefinition::parse(&language, "EKMFLGDQVZNTOWYHXUSPAIBRCJ", "Q").unwrap();
+        assert_eq!(definition, RotorDefinition::from(RotorType::I));
+        assert!(RotorDefinition::parse(&language, "EKMFLGDQVZNTOWYHXUSPAIBRCE", "Q").is_err());
+        assert!(RotorDefinition::parse(&language, "EKMF", "Q").is_err());
+        assert!(RotorDefinition::new(&[1, 0, 2], &[3]).is_err());
+
+        // a rotor for a 25 letter alphabet
+        language.set_alph_len(25);
+        let wiring = language.vec_to_string(&(0..25).rev().collect::<Vec<_>>());
+        let definition = RotorDefinition::parse(&language, &wiring, "A").unwrap();
+        let mut rotor = Rotor::new(&mut language, definition).unwrap();
+        rotor.grund = 24;
+        assert!(!rotor.is_on_notch());
+        rotor.step();
+        assert_eq!(rotor.grund, 0);
+        assert!(rotor.is_on_notch());
+        assert_eq!(rotor.input(0, false), 24);
+        assert!(rotor.set(&mut language, RotorType::I).is_err());
+        assert!(rotor.set(&mut language, "i").is_err());
     }
 }
@@ -0,0 +1,220 @@
+use crate::{
+    error::{Error, Result},
+    key::{IdentityKey, IoKey, Key, KeyInfo, StatefulKey},
+    lang::Language,
+};
+
+/// The number of bars in the lug cage
+const BARS: usize = 27;
+/// The number of pin wheels, which are the positions of the lugs other than neutral
+const WHEELS: usize = 6;
+/// The number of sets of wheels whose pins against the guide arms can be effective
+const ACTIVE_SETS: usize = 1 << WHEELS;
+
+/// Represents the lug cage of the Hagelin M-209 and C-38 (See M209 cipher). Each of the
+/// 27 bars has two lugs, which can be placed against any of the 6 pin wheels or in a
+/// neutral position. A bar is shifted when a lug faces a wheel whose pin against the
+/// guide arm is effective, and the number of shifted bars is the shift of the letter.
+/// When both lugs of a bar face effective pins, the bar is still only shifted once.
+///
+#[derive(Clone)]
+pub struct LugCage {
+    /// The positions of the two lugs of each bar, from 1 to 6 for a wheel or 0 for
+    /// neutral
+    bars: Vec<(usize, usize)>,
+    /// The shift for each set of wheels with effective pins
+    shifts: [i16; ACTIVE_SETS],
+    info: KeyInfo,
+}
+
+impl LugCage {
+    /// The positions of the two lugs of each bar, from 1 to 6 for a wheel or 0 for
+    /// neutral
+    ///
+    pub fn bars(&self) -> &[(usize, usize)] {
+        &self.bars
+    }
+
+    /// Moves the lugs of a bar, provided that the lugs are valid
+    ///
+    /// # Arguments
+    ///
+    /// * `bar` The index of the bar, from 0 to 26
+    /// * `lugs` The positions of the two lugs, from 1 to 6 for a wheel or 0 for
+    ///   neutral, which must not face the same wheel
+    ///
+    pub fn set_bar(&mut self, bar: usize, lugs: (usize, usize)) {
+        debug_assert!(lugs.0 <= WHEELS && lugs.1 <= WHEELS);
+        debug_assert!(lugs.0 != lugs.1 || lugs.0 == 0);
+
+        self.bars[bar] = lugs;
+        self.find_shifts();
+    }
+
+    /// The number of bars that are shifted, given the wheels whose pins against the
+    /// guide arms are effective
+    ///
+    /// # Arguments
+    ///
+    /// * `active` The wheels with effective pins, where bit `n` is set if the pin of
+    ///   wheel `n` (counting from 0) is effective
+    ///
+    pub fn shift(&self, active: u8) -> i16 {
+        self.shifts[active as usize]
+    }
+
+    /// Finds the shift for every set of wheels with effective pins. A bar is not
+    /// shifted when neither of its wheels is effective, so the number of such bars is
+    /// counted for every set of ineffective wheels, by summing over its subsets.
+    ///
+    fn find_shifts(&mut self) {
+        let mut unshifted = [0; ACTIVE_SETS];
+        let mut used = 0;
+        for &(lug1, lug2) in self.bars.iter() {
+            let wheels = Self::lug_mask(lug1) | Self::lug_mask(lug2);
+            if wheels != 0 {
+                unshifted[wheels] += 1;
+                used += 1;
+            }
+        }
+
+        for wheel in 0..WHEELS {
+            for set in 0..ACTIVE_SETS {
+                if set & 1 << wheel != 0 {
+                    unshifted[set] += unshifted[set ^ 1 << wheel];
+                }
+            }
+        }
+
+        for (set, shift) in self.shifts.iter_mut().enumerate() {
+            *shift = used - unshifted[!set & (ACTIVE_SETS - 1)];
+        }
+    }
+
+    /// The wheel faced by a lug, as a set of wheels
+    ///
+    /// # Arguments
+    ///
+    /// * `lug` The position of the lug, from 1 to 6 for a wheel or 0 for neutral
+    ///
+    fn lug_mask(lug: usize) -> usize {
+        match lug {
+            0 => 0,
+            _ => 1 << (lug - 1),
+        }
+    }
+}
+
+impl Key<&[(usize, usize)]> for LugCage {
+    fn new(language: &mut Language, arg: &[(usize, usize)]) -> Result<Box<Self>> {
+        let mut result = LugCage::identity(language);
+        result.set(language, arg)?;
+        Ok(Box::new(result))
+    }
+    fn set(&mut self, _language: &mut Language, arg: &[(usize, usize)]) -> Result<()> {
+        if arg.len() > BARS {
+            return Err(Error::InvalidKeyFmt {
+                expected: format!("At most {} bars", BARS),
+                actual: format!("{} bars", arg.len()),
+            });
+        }
+        if let Some(&(lug1, lug2)) = arg
+            .iter()
+            .find(|&&(lug1, lug2)| lug1 > WHEELS || lug2 > WHEELS || (lug1 == lug2 && lug1 != 0))
+        {
+            return Err(Error::InvalidKeyFmt {
+                expected: "Lugs from 0 to 6, with the two lugs of a bar facing different wheels"
+                    .to_string(),
+                actual: format!("{}-{}", lug1, lug2),
+            });
+        }
+
+        self.bars = arg.to_vec();
+        self.bars.resize(BARS, (0, 0));
+        self.find_shifts();
+
+        Ok(())
+    }
+}
+impl Key<&str> for LugCage {
+    fn new(language: &mut Language, arg: &str) -> Result<Box<Self>> {
+        let mut result = LugCage::identity(language);
+        result.set(language, arg)?;
+        Ok(Box::new(result))
+    }
+    fn set(&mut self, language: &mut Language, arg: &str) -> Result<()> {
+        let bars = arg
+            .split(':')
+            .filter(|token| !token.is_empty())
+            .map(|token| {
+                let lugs = token
+                    .chars()
+                    .map(|c| c.to_digit(10).map(|d| d as usize))
+                    .collect::<Option<Vec<_>>>();
+                match lugs.as_deref() {
+                    Some(&[lug1, lug2]) => Ok((lug1, lug2)),
+                    _ => Err(Error::InvalidKeyFmt {
+                        expected:
+                            "A colon ':' delimited string of pairs of lug positions from 0 to 6"
+                                .to_string(),
+                        actual: arg.to_string(),
+                    }),
+                }
+            })
+            .collect::<Result<Vec<_>>>()?;
+
+        self.set(language, bars.as_slice())
+    }
+}
+
+impl IdentityKey for LugCage {
+    fn identity(_language: &mut Language) -> Self {
+        Self {
+            bars: vec![(0, 0); BARS],
+            shifts: [0; ACTIVE_SETS],
+            info: KeyInfo::default(),
+        }
+    }
+}
+
+impl StatefulKey for LugCage {
+    fn reset(&mut self, _language: &mut Language) {
+        self.bars.fill((0, 0));
+        self.find_shifts();
+    }
+    fn to_string(&self, _language: &mut Language) -> String {
+        self.bars
+            .iter()
+            .filter(|&&bar| bar != (0, 0))
+            .map(|(lug1, lug2)| format!("{}{}", lug1, lug2))
+            .collect::<Vec<_>>()
+            .join(":")
+    }
+    fn randomize(&mut self, _language: &mut Language) {
+        // every bar has at least one lug, and some bars overlap two wheels
+        for bar in self.bars.iter_mut() {
+            let lug1 = fastrand::usize(1..=WHEELS);
+            let lug2 = match fastrand::usize(0..4) {
+                0 => (lug1 + fastrand::usize(0..WHEELS - 1)) % WHEELS + 1,
+                _ => 0,
+            };
+            *bar = (lug1, lug2);
+        }
+        self.find_shifts();
+    }
+}
+
+impl IoKey for LugCage {
+    fn set_key_str(&mut self, language: &mut Language, arg: &str) -> Result<()> {
+        self.set(language, arg)
+    }
+    fn info(&self) -> &KeyInfo {
+        &self.info
+    }
+    fn info_mut(&mut self) -> &mut KeyInfo {
+        &mut self.info
+    }
+    fn desc(&self) -> String {
+        "<up to 27 colon delimited pairs of lug positions from 0 (neutral) to 6>".to_string()
+    }
+}
@@ -0,0 +1,2 @@
+pub mod lug_cage;
+pub mod pin_wheels;
@@ -0,0 +1,271 @@
+use crate::{
+    error::{Error, Result},
+    key::{IdentityKey, IoKey, Key, KeyInfo, StatefulKey},
+    lang::Language,
+};
+
+/// The number of pin wheels
+const WHEELS: usize = 6;
+/// The number of pins on each wheel, from left to right
+const WHEEL_LENS: [usize; WHEELS] = [26, 25, 23, 21, 19, 17];
+/// The number of positions from the letter shown in the window of each wheel to the
+/// pin against the guide arm
+const GUIDE_ARM_OFFSETS: [usize; WHEELS] = [15, 14, 13, 12, 11, 10];
+/// The letter W, which is missing from all but the first wheel
+const LETTER_W: i16 = 22;
+
+/// Represents the six pin wheels of the Hagelin M-209 and C-38 (See M209 cipher). The
+/// wheels have 26, 25, 23, 21, 19 and 17 pins, each of which can be effective or
+/// ineffective, and are labelled with the letters A to Z, A to Z without W, A to X
+/// without W, A to U, A to S and A to Q. Each wheel steps once for every letter.
+///
+#[derive(Clone)]
+pub struct PinWheels {
+    /// Whether each pin of each wheel is effective
+    pins: Vec<Vec<bool>>,
+    /// The index of the letter shown in the window of each wheel
+    positions: [usize; WHEELS],
+    info: KeyInfo,
+}
+
+impl PinWheels {
+    /// The number of pins on a wheel
+    ///
+    /// # Arguments
+    ///
+    /// * `wheel` The index of the wheel, from 0 (leftmost) to 5
+    ///
+    pub fn wheel_len(&self, wheel: usize) -> usize {
+        WHEEL_LENS[wheel]
+    }
+
+    /// Whether a pin of a wheel is effective
+    ///
+    /// # Arguments
+    ///
+    /// * `wheel` The index of the wheel, from 0 (leftmost) to 5
+    /// * `pin` The index of the pin on the wheel
+    ///
+    pub fn pin(&self, wheel: usize, pin: usize) -> bool {
+        self.pins[wheel][pin]
+    }
+
+    /// Sets whether a pin of a wheel is effective
+    ///
+    /// # Arguments
+    ///
+    /// * `wheel` The index of the wheel, from 0 (leftmost) to 5
+    /// * `pin` The index of the pin on the wheel
+    /// * `effective` Whether the pin should be effective
+    ///
+    pub fn set_pin(&mut self, wheel: usize, pin: usize, effective: bool) {
+        self.pins[wheel][pin] = effective;
+    }
+
+    /// The index of the pin of a wheel that is against the guide arm, which is offset
+    /// from the letter shown in the window
+    ///
+    /// # Arguments
+    ///
+    /// * `wheel` The index of the wheel, from 0 (leftmost) to 5
+    ///
+    pub fn active_pin(&self, wheel: usize) -> usize {
+        (self.positions[wheel] + GUIDE_ARM_OFFSETS[wheel]) % WHEEL_LENS[wheel]
+    }
+
+    /// The wheels whose pins against the guide arm are effective, where bit `n` is set
+    /// if the pin of wheel `n` is effective
+    ///
+    pub fn active(&self) -> u8 {
+        (0..WHEELS)
+            .filter(|&wheel| self.pins[wheel][self.active_pin(wheel)])
+            .fold(0, |mask, wheel| mask | 1 << wheel)
+    }
+
+    /// Steps every wheel forward by one position
+    ///
+    pub fn step(&mut self) {
+        for (position, len) in self.positions.iter_mut().zip(WHEEL_LENS.iter()) {
+            *position = (*position + 1) % len;
+        }
+    }
+
+    /// The code points of the letters on a wheel, in order
+    ///
+    /// # Arguments
+    ///
+    /// * `wheel` The index of the wheel, from 0 (leftmost) to 5
+    ///
+    fn letters(wheel: usize) -> Vec<i16> {
+        (0..26)
+            .filter(|&cp| wheel == 0 || cp != LETTER_W)
+            .take(WHEEL_LENS[wheel])
+            .collect()
+    }
+
+    /// Parses the effective pins of a wheel, given as the letters of the pins
+    ///
+    /// # Arguments
+    ///
+    /// * `language` A [`Language`] instance
+    /// * `arg` The whole key string, for error messages
+    /// * `wheel` The index of the wheel, from 0 (leftmost) to 5
+    /// * `pins` The letters of the effective pins
+    ///
+    fn parse_pins(
+        language: &mut Language,
+        arg: &str,
+        wheel: usize,
+        pins: &str,
+    ) -> Result<Vec<bool>> {
+        let letters = Self::letters(wheel);
+        let mut result = vec![false; WHEEL_LENS[wheel]];
+
+        for cp in language.string_to_vec(pins) {
+            match letters.iter().position(|&letter| letter == cp) {
+                Some(pin) if !result[pin] => result[pin] = true,
+                _ => {
+                    return Err(Error::InvalidKeyFmt {
+                        expected: format!(
+                            "Each effective pin of wheel {} to be one of {}, given once",
+                            wheel + 1,
+                            language.vec_to_string(&letters).to_uppercase()
+                        ),
+                        actual: arg.to_string(),
+                    })
+                }
+            }
+        }
+
+        Ok(result)
+    }
+
+    /// Parses the letters shown in the windows of the wheels
+    ///
+    /// # Arguments
+    ///
+    /// * `language` A [`Language`] instance
+    /// * `arg` The whole key string, for error messages
+    /// * `window` The letter shown in the window of each wheel
+    ///
+    fn parse_positions(
+        language: &mut Language,
+        arg: &str,
+        window: &str,
+    ) -> Result<[usize; WHEELS]> {
+        let err = || Error::InvalidKeyFmt {
+            expected: "A letter on each of the 6 wheels, shown in the windows".to_string(),
+            actual: arg.to_string(),
+        };
+
+        let window = language.string_to_vec(window);
+        if window.len() != WHEELS {
+            return Err(err());
+        }
+
+        let mut result = [0; WHEELS];
+        for (wheel, &cp) in window.iter().enumerate() {
+            result[wheel] = Self::letters(wheel)
+                .iter()
+                .position(|&letter| letter == cp)
+                .ok_or_else(err)?;
+        }
+
+        Ok(result)
+    }
+}
+
+impl Key<&str> for PinWheels {
+    fn new(language: &mut Language, arg: &str) -> Result<Box<Self>> {
+        let mut result = PinWheels::identity(language);
+        result.set(language, arg)?;
+        Ok(Box::new(result))
+    }
+    fn set(&mut self, language: &mut Language, arg: &str) -> Result<()> {
+        language.set_alph_len(26);
+
+        let tokens = arg.split(':').collect::<Vec<_>>();
+        if tokens.len() != WHEELS && tokens.len() != WHEELS + 1 {
+            return Err(Error::InvalidKeyFmt {
+                expected: "The effective pins of the 6 wheels, and optionally the letters shown in the windows, delimited by colons ':'".to_string(),
+                actual: arg.to_string(),
+            });
+        }
+
+        let pins = tokens[..WHEELS]
+            .iter()
+            .enumerate()
+            .map(|(wheel, pins)| Self::parse_pins(language, arg, wheel, pins))
+            .collect::<Result<Vec<_>>>()?;
+        let positions = match tokens.get(WHEELS) {
+            Some(window) => Self::parse_positions(language, arg, window)?,
+            None => [0; WHEELS],
+        };
+
+        self.pins = pins;
+        self.positions = positions;
+
+        Ok(())
+    }
+}
+
+impl IdentityKey for PinWheels {
+    fn identity(_language: &mut Language) -> Self {
+        Self {
+            pins: WHEEL_LENS.iter().map(|&len| vec![false; len]).collect(),
+            positions: [0; WHEELS],
+            info: KeyInfo::default(),
+        }
+    }
+}
+
+impl StatefulKey for PinWheels {
+    fn reset(&mut self, _language: &mut Language) {
+        self.pins.iter_mut().for_each(|pins| pins.fill(false));
+        self.positions = [0; WHEELS];
+    }
+    fn to_string(&self, language: &mut Language) -> String {
+        let mut tokens = (0..WHEELS)
+            .map(|wheel| {
+                let effective = Self::letters(wheel)
+                    .into_iter()
+                    .zip(self.pins[wheel].iter())
+                    .filter(|(_, &effective)| effective)
+                    .map(|(letter, _)| letter)
+                    .collect::<Vec<_>>();
+                language.vec_to_string(&effective).to_uppercase()
+            })
+            .collect::<Vec<_>>();
+
+        let window = (0..WHEELS)
+            .map(|wheel| Self::letters(wheel)[self.positions[wheel]])
+            .collect::<Vec<_>>();
+        tokens.push(language.vec_to_string(&window).to_uppercase());
+
+        tokens.join(":")
+    }
+    fn randomize(&mut self, _language: &mut Language) {
+        self.pins
+            .iter_mut()
+            .flatten()
+            .for_each(|pin| *pin = fastrand::bool());
+        for (position, len) in self.positions.iter_mut().zip(WHEEL_LENS.iter()) {
+            *position = fastrand::usize(0..*len);
+        }
+    }
+}
+
+impl IoKey for PinWheels {
+    fn set_key_str(&mut self, language: &mut Language, arg: &str) -> Result<()> {
+        self.set(language, arg)
+    }
+    fn info(&self) -> &KeyInfo {
+        &self.info
+    }
+    fn info_mut(&mut self) -> &mut KeyInfo {
+        &mut self.info
+    }
+    fn desc(&self) -> String {
+        "<6 colon delimited sets of effective pin letters>[:<6 window letters>]".to_string()
+    }
+}
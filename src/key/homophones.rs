@@ -0,0 +1,216 @@
+use crate::{
+    error::{Error, Result},
+    key::{IdentityKey, IoKey, Key, KeyInfo, StatefulKey},
+    lang::Language,
+    util,
+};
+
+/// The number of homophones used by default
+const DEFAULT_NUM_SYMBOLS: usize = 64;
+
+/// Represents the homophones of a substitution (See Homophonic cipher). Each
+/// ciphertext symbol stands for a single plaintext letter, and common letters have
+/// more symbols than rare letters.
+///
+/// As a string, the key gives the plaintext letter of each symbol in order, so the
+/// length of the string is the number of symbols.
+///
+pub struct Homophones {
+    value: Vec<i16>,
+    info: KeyInfo,
+}
+
+impl Homophones {
+    /// Returns the number of symbols
+    ///
+    pub fn len(&self) -> usize {
+        self.value.len()
+    }
+
+    /// Are there no symbols?
+    ///
+    pub fn is_empty(&self) -> bool {
+        self.value.is_empty()
+    }
+
+    /// Gets the plaintext letter of each symbol as a slice
+    ///
+    pub fn as_slice(&self) -> &[i16] {
+        &self.value
+    }
+
+    /// Finds the symbols of each plaintext letter
+    ///
+    /// # Arguments
+    ///
+    /// * `alphabet_len` The length of the plaintext alphabet
+    ///
+    pub fn symbols(&self, alphabet_len: usize) -> Vec<Vec<usize>> {
+        let mut result = vec![Vec::new(); alphabet_len];
+        for (symbol, &cp) in self.value.iter().enumerate() {
+            result[cp as usize].push(symbol);
+        }
+        result
+    }
+
+    /// Calculates how many symbols each letter should have, in proportion to its
+    /// probability from [`Language::unigram_probabilities`]. Every letter has at least
+    /// one symbol, so there must be at least as many symbols as letters.
+    ///
+    /// # Arguments
+    ///
+    /// * `language` A [`Language`] instance
+    /// * `num_symbols` The total number of symbols, at least the alphabet length
+    ///
+    pub fn counts(language: &Language, num_symbols: usize) -> Result<Vec<usize>> {
+        let alphabet_len = language.alphabet_len();
+        if num_symbols < alphabet_len {
+            return Err(Error::InvalidKeyFmt {
+                expected: format!("At least {} symbols", alphabet_len),
+                actual: num_symbols.to_string(),
+            });
+        }
+
+        let probabilities = &language.unigram_probabilities[..alphabet_len];
+        let total = probabilities.iter().sum::<f64>();
+
+        let mut counts = probabilities
+            .iter()
+            .map(|p| ((p / total * num_symbols as f64) as usize).max(1))
+            .collect::<Vec<_>>();
+
+        // the probability covered by each symbol of a letter, if it had one more symbol
+        let share = |counts: &[usize], cp: usize, extra: usize| {
+            probabilities[cp] / (counts[cp] + extra) as f64
+        };
+
+        while counts.iter().sum::<usize>() < num_symbols {
+            let cp = (0..alphabet_len)
+                .max_by(|&a, &b| share(&counts, a, 1).total_cmp(&share(&counts, b, 1)))
+                .unwrap();
+            counts[cp] += 1;
+        }
+        while counts.iter().sum::<usize>() > num_symbols {
+            let cp = (0..alphabet_len)
+                .filter(|&cp| counts[cp] > 1)
+                .min_by(|&a, &b| share(&counts, a, 0).total_cmp(&share(&counts, b, 0)))
+                .unwrap();
+            counts[cp] -= 1;
+        }
+
+        Ok(counts)
+    }
+
+    /// Sets the number of symbols, giving each letter a number of symbols in proportion
+    /// to its probability (See [`Homophones::counts`]). The symbols of each letter are
+    /// consecutive.
+    ///
+    /// # Arguments
+    ///
+    /// * `language` A [`Language`] instance
+    /// * `num_symbols` The total number of symbols, at least the alphabet length
+    ///
+    pub fn set_len(&mut self, language: &Language, num_symbols: usize) -> Result<()> {
+        self.value = Homophones::counts(language, num_symbols)?
+            .into_iter()
+            .enumerate()
+            .flat_map(|(cp, count)| vec![cp as i16; count])
+            .collect();
+
+        Ok(())
+    }
+}
+
+impl Key<&[i16]> for Homophones {
+    fn new(language: &mut Language, arg: &[i16]) -> Result<Box<Self>> {
+        let mut result = Box::new(Homophones::identity(language));
+        result.set(language, arg)?;
+        Ok(result)
+    }
+    fn set(&mut self, language: &mut Language, arg: &[i16]) -> Result<()> {
+        let cp_count = language.cp_count();
+        if arg.is_empty() || arg.iter().any(|&cp| !(0..cp_count).contains(&cp)) {
+            return Err(Error::InvalidKeyFmt {
+                expected: format!("At least one symbol, each in the range 0..{}", cp_count),
+                actual: format!("{:?}", arg),
+            });
+        }
+
+        self.value = Vec::from(arg);
+        Ok(())
+    }
+}
+impl Key<&str> for Homophones {
+    fn new(language: &mut Language, arg: &str) -> Result<Box<Self>> {
+        let mut result = Box::new(Homophones::identity(language));
+        result.set(language, arg)?;
+        Ok(result)
+    }
+    fn set(&mut self, language: &mut Language, arg: &str) -> Result<()> {
+        let vec = language.string_to_vec(arg);
+        self.set(language, vec.as_slice())
+    }
+}
+
+impl IdentityKey for Homophones {
+    fn identity(language: &mut Language) -> Self {
+        let mut result = Self {
+            value: Vec::new(),
+            info: KeyInfo::default(),
+        };
+        result
+            .set_len(language, DEFAULT_NUM_SYMBOLS.max(language.alphabet_len()))
+            .unwrap();
+        result
+    }
+}
+
+impl StatefulKey for Homophones {
+    fn reset(&mut self, language: &mut Language) {
+        self.set_len(language, self.value.len().max(language.alphabet_len()))
+            .unwrap();
+    }
+    fn to_string(&self, language: &mut Language) -> String {
+        language.vec_to_string(&self.value)
+    }
+    fn randomize(&mut self, language: &mut Language) {
+        self.reset(language);
+        util::shuffle(&mut self.value);
+    }
+}
+
+impl IoKey for Homophones {
+    fn set_key_str(&mut self, language: &mut Language, arg: &str) -> Result<()> {
+        self.set(language, arg)
+    }
+    fn info(&self) -> &KeyInfo {
+        &self.info
+    }
+    fn info_mut(&mut self) -> &mut KeyInfo {
+        &mut self.info
+    }
+    fn desc(&self) -> String {
+        "<string: the letter of each symbol>".to_string()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn counts() {
+        let language = Language::from_file("examples/data/english.bin").unwrap();
+        let counts = Homophones::counts(&language, 64).unwrap();
+
+        assert_eq!(counts.iter().sum::<usize>(), 64);
+        assert!(counts.iter().all(|&count| count > 0));
+    }
+
+    #[test]
+    fn counts_too_few_symbols() {
+        let language = Language::from_file("examples/data/english.bin").unwrap();
+
+        assert!(Homophones::counts(&language, 10).is_err());
+    }
+}
@@ -37,6 +37,12 @@ impl Keyword {
         self.value[idx]
     }
 
+    /// Gets the keyword as a slice of code points
+    ///
+    pub fn as_slice(&self) -> &[i16] {
+        &self.value
+    }
+
     /// Finds the indexes of the items in the keyword
     ///
     pub fn find_order(&self) -> Vec<usize> {
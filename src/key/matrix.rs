@@ -5,112 +5,162 @@ use crate::{
     util,
 };
 
-/// The dimension size of a matrix (2x2 or 3x3)
-///
-#[derive(Clone, Copy)]
-pub enum MatrixDimSize {
-    Two = 2,
-    Three = 3,
-}
+/// The smallest dimension size of a matrix
+const MIN_SIZE: usize = 2;
+/// The largest dimension size of a matrix
+const MAX_SIZE: usize = 8;
 
-/// Represents a Matrix (See Hill Cipher)
+/// Represents a square Matrix (See Hill Cipher), from 2x2 up to 8x8. The elements are
+/// code points, and the determinant and inverse are found modulo the number of code
+/// points in the alphabet.
 ///
 pub struct Matrix {
-    pub size: MatrixDimSize,
+    size: usize,
     value: Vec<i16>,
     info: KeyInfo,
 }
 
 impl Matrix {
-    /// Determinant of a 2x2 matrix
+    /// Row reduces a matrix modulo `modulus`, finding its determinant and, if it exists,
+    /// its inverse. Since the modulus need not be prime, rows are combined using the
+    /// euclidean algorithm rather than dividing by the pivot, which leaves the gcd of
+    /// each column on the diagonal.
     ///
     /// # Arguments
     ///
-    /// * `matrix` The matrix to find det from
+    /// * `matrix` The matrix (row by row)
+    /// * `size` The number of rows (and columns)
+    /// * `modulus` The modulus of each element
     ///
-    fn x2_det(matrix: &[i16]) -> i16 {
-        matrix[0] * matrix[3] - matrix[1] * matrix[2]
+    fn row_reduce(matrix: &[i16], size: usize, modulus: i16) -> (i16, Option<Vec<i16>>) {
+        let m = modulus as i32;
+        let width = size * 2;
+
+        // the matrix, augmented with the identity matrix
+        let mut rows = (0..size)
+            .map(|row| {
+                (0..width)
+                    .map(|col| match col < size {
+                        true => (matrix[row * size + col] as i32).rem_euclid(m),
+                        false => (col - size == row) as i32,
+                    })
+                    .collect::<Vec<_>>()
+            })
+            .collect::<Vec<_>>();
+
+        // subtracts a multiple of the row `src` from the row `dest`
+        let sub_row = |dest: &mut [i32], src: &[i32], factor: i32| {
+            for (x, y) in dest.iter_mut().zip(src) {
+                *x = (*x - factor * y).rem_euclid(m);
+            }
+        };
+
+        let mut det = 1;
+        for col in 0..size {
+            for row in col + 1..size {
+                // clear the element below the pivot
+                while rows[row][col] != 0 {
+                    let q = rows[col][col] / rows[row][col];
+                    let src = rows[row].clone();
+                    sub_row(&mut rows[col], &src, q);
+                    rows.swap(col, row);
+                    det = -det;
+                }
+            }
+            det = (det * rows[col][col]).rem_euclid(m);
+        }
+
+        let det = det as i16;
+        if util::mmi(det, modulus).is_none() {
+            return (det, None);
+        }
+
+        // each pivot is a unit, since the determinant is
+        for col in (0..size).rev() {
+            let inv = util::mmi(rows[col][col] as i16, modulus).unwrap() as i32;
+            rows[col].iter_mut().for_each(|x| *x = *x * inv % m);
+
+            let src = rows[col].clone();
+            for row in rows.iter_mut().take(col) {
+                let factor = row[col];
+                sub_row(row, &src, factor);
+            }
+        }
+
+        let inverse = rows
+            .into_iter()
+            .flat_map(|row| row.into_iter().skip(size).map(|x| x as i16))
+            .collect();
+
+        (det, Some(inverse))
     }
 
-    /// Calculate the determinant of the matrix
+    /// Finds the number of rows (and columns) of a matrix with `len` elements, if `len`
+    /// is a square within the supported sizes
     ///
     /// # Arguments
     ///
-    /// `matrix` The matrix to calculate determinant from
+    /// * `len` The number of elements
     ///
-    fn det(matrix: &[i16], language: &Language) -> i16 {
-        match matrix.len() {
-            4 => Matrix::x2_det(matrix),
-            9 => {
-                let mut det = 0;
-
-                // calculate determinant from top row
-                let mut x2_mat = vec![0; 4];
-                for col in 0..3 {
-                    // for each position, find 2x2 determinant
-                    let mut i = 0;
-                    for r in 1..3 {
-                        for c in (0..3).filter(|x| *x != col) {
-                            x2_mat[i] = util::modulo(matrix[r * 3 + c], language.cp_count());
-                            i += 1;
-                        }
-                    }
-
-                    det += Matrix::x2_det(&x2_mat) * if col == 1 { -1 } else { 1 } * matrix[col];
-                }
+    fn size_of(len: usize) -> Option<usize> {
+        (MIN_SIZE..=MAX_SIZE).find(|size| size * size == len)
+    }
 
-                det
-            }
-            _ => unreachable!("Matrix must be 2x2 or 3x3"),
+    /// Gets the number of rows (and columns) of the matrix
+    ///
+    pub fn size(&self) -> usize {
+        self.size
+    }
+
+    /// Sets the number of rows (and columns) of the matrix, resetting it to the identity
+    /// matrix
+    ///
+    /// # Arguments
+    ///
+    /// * `language` A [`Language`] instance
+    /// * `size` The number of rows (and columns), from 2 to 8
+    ///
+    pub fn set_size(&mut self, language: &mut Language, size: usize) -> Result<()> {
+        if !(MIN_SIZE..=MAX_SIZE).contains(&size) {
+            return Err(Error::InvalidKeyFmt {
+                expected: format!("A size from {} to {}", MIN_SIZE, MAX_SIZE),
+                actual: size.to_string(),
+            });
         }
+
+        self.size = size;
+        self.reset(language);
+
+        Ok(())
+    }
+
+    /// Calculates the determinant of the matrix, modulo the number of code points
+    ///
+    /// # Arguments
+    ///
+    /// * `language` A [`Language`] instance
+    ///
+    pub fn det(&self, language: &Language) -> i16 {
+        Matrix::row_reduce(&self.value, self.size, language.cp_count()).0
     }
 
     /// Inverts the matrix, if possible to do so
     ///
+    /// # Arguments
+    ///
+    /// * `language` A [`Language`] instance
+    ///
+    /// # Panics
+    ///
+    /// If the matrix is not invertible
+    ///
     pub fn invert(&self, language: &Language) -> Matrix {
         Matrix {
             info: KeyInfo::default(),
             size: self.size,
-            value: {
-                let mut adj = vec![0; self.size as usize * self.size as usize];
-
-                match self.size {
-                    MatrixDimSize::Two => {
-                        adj[0] = self.value[3];
-                        adj[1] = util::modulo(-self.value[1], 26);
-                        adj[2] = util::modulo(-self.value[2], 26);
-                        adj[3] = self.value[0];
-                    }
-                    MatrixDimSize::Three => {
-                        let mut x2_mat = vec![0; 4];
-                        // iterate through all matrix positions
-                        for row in 0..3 {
-                            for col in 0..3 {
-                                // for each position, find 2x2 determinant
-                                let mut i = 0;
-                                for r in (0..3).filter(|x| *x != row) {
-                                    for c in (0..3).filter(|x| *x != col) {
-                                        x2_mat[i] = self.value[r * 3 + c];
-                                        i += 1;
-                                    }
-                                }
-
-                                let pos = col * 3 + row;
-                                adj[pos] = Matrix::x2_det(&x2_mat);
-                                adj[pos] *= if (row * 3 + col) % 2 == 0 { 1 } else { -1 };
-                            }
-                        }
-                    }
-                }
-
-                let det = util::modulo(Matrix::det(&self.value, language), language.cp_count());
-                let inv = util::mmi(det, language.cp_count())
-                    .expect("Failed to calculate matrix inverse");
-
-                (0..self.size as usize * self.size as usize)
-                    .map(|i| util::modulo(adj[i] * inv, language.cp_count()))
-                    .collect()
-            },
+            value: Matrix::row_reduce(&self.value, self.size, language.cp_count())
+                .1
+                .expect("Failed to calculate matrix inverse"),
         }
     }
 
@@ -121,7 +171,7 @@ impl Matrix {
     /// `language` A [`Language`] instance
     ///
     pub fn is_invertible(&self, language: &Language) -> bool {
-        util::mmi(Matrix::det(&self.value, language), language.cp_count()).is_some()
+        util::mmi(self.det(language), language.cp_count()).is_some()
     }
 
     /// Gets the element at (row, col) in the matrix
@@ -133,7 +183,13 @@ impl Matrix {
     ///
     #[inline(always)]
     pub fn at(&self, row: usize, col: usize) -> i16 {
-        self.value[row * self.size as usize + col]
+        self.value[row * self.size + col]
+    }
+
+    /// Gets the value of the matrix (row by row) as a slice
+    ///
+    pub fn as_slice(&self) -> &[i16] {
+        &self.value
     }
 }
 
@@ -144,29 +200,28 @@ impl Key<&[i16]> for Matrix {
         Ok(Box::new(result))
     }
     fn set(&mut self, language: &mut Language, arg: &[i16]) -> Result<()> {
-        if arg.len() != 4 && arg.len() != 9 {
+        let size = Matrix::size_of(arg.len()).ok_or_else(|| Error::InvalidKeyFmt {
+            expected: format!(
+                "A square number of values, from {} to {}",
+                MIN_SIZE * MIN_SIZE,
+                MAX_SIZE * MAX_SIZE
+            ),
+            actual: format!("{} values, data: {:?}", arg.len(), arg),
+        })?;
+
+        let cp_count = language.cp_count();
+        let (det, _) = Matrix::row_reduce(arg, size, cp_count);
+
+        if util::mmi(det, cp_count).is_none() {
             Err(Error::InvalidKeyFmt {
-                expected: "Expected 4 or 9 values".to_string(),
-                actual: format!("{} values, data: {:?}", arg.len(), arg),
+                expected: "Matrix to have an inverse".to_string(),
+                actual: format!("{:?}", arg),
             })
         } else {
-            let val = Vec::from(arg);
+            self.value = arg.iter().map(|&x| util::modulo(x, cp_count)).collect();
+            self.size = size;
 
-            if util::mmi(Matrix::det(&val, &language), language.cp_count()).is_none() {
-                Err(Error::InvalidKeyFmt {
-                    expected: "Matrix to have an inverse".to_string(),
-                    actual: format!("{:?}", arg),
-                })
-            } else {
-                self.value = val;
-                self.size = match arg.len() {
-                    4 => MatrixDimSize::Two,
-                    9 => MatrixDimSize::Three,
-                    _ => unreachable!(),
-                };
-
-                Ok(())
-            }
+            Ok(())
         }
     }
 }
@@ -183,20 +238,22 @@ impl Key<&str> for Matrix {
 }
 
 impl IdentityKey for Matrix {
-    fn identity(_language: &mut Language) -> Self {
-        Self {
-            value: { vec![0; 4] },
-            size: MatrixDimSize::Two,
+    fn identity(language: &mut Language) -> Self {
+        let mut result = Self {
+            value: Vec::new(),
+            size: MIN_SIZE,
             info: KeyInfo::default(),
-        }
+        };
+        result.reset(language);
+        result
     }
 }
 
 impl StatefulKey for Matrix {
     fn reset(&mut self, _language: &mut Language) {
-        self.value = vec![0; self.size as usize * self.size as usize];
-        for i in 0..self.size as usize {
-            self.value[(self.size as usize + 1) * i] = 1;
+        self.value = vec![0; self.size * self.size];
+        for i in 0..self.size {
+            self.value[(self.size + 1) * i] = 1;
         }
     }
     fn to_string(&self, language: &mut Language) -> String {
@@ -204,7 +261,7 @@ impl StatefulKey for Matrix {
             .iter()
             .map(|&item| language.cp_to_upper(item))
             .fold(
-                String::with_capacity(self.size as usize * self.size as usize),
+                String::with_capacity(self.size * self.size),
                 |mut acc, ch| {
                     acc.push(ch);
                     acc
@@ -212,8 +269,9 @@ impl StatefulKey for Matrix {
             )
     }
     fn randomize(&mut self, language: &mut Language) {
+        // a good proportion of random matrices are invertible, even for larger sizes
         loop {
-            for i in 0..self.size as usize * self.size as usize {
+            for i in 0..self.size * self.size {
                 self.value[i] = fastrand::i16(0..language.cp_count());
             }
             if self.is_invertible(language) {
@@ -234,7 +292,7 @@ impl IoKey for Matrix {
         &mut self.info
     }
     fn desc(&self) -> String {
-        "<4 or 9 letter string>".to_string()
+        "<string: n*n letters for an nxn matrix, where n is from 2 to 8>".to_string()
     }
 }
 
@@ -260,4 +318,30 @@ mod tests {
             mat.invert(&language).value
         );
     }
+
+    #[test]
+    fn x4_inv() {
+        let mut language = Language::from_file("examples/data/english.bin").unwrap();
+        let mut mat = Matrix::identity(&mut language);
+
+        for _ in 0..10 {
+            mat.set_size(&mut language, 4).unwrap();
+            mat.randomize(&mut language);
+
+            let inv = mat.invert(&language);
+            let product = (0..16)
+                .map(|i| {
+                    (0..4).fold(0, |acc, k| {
+                        util::modulo(acc + mat.at(i / 4, k) * inv.at(k, i % 4), 26)
+                    })
+                })
+                .collect::<Vec<_>>();
+
+            assert_eq!(inv.invert(&language).value, mat.value);
+            assert_eq!(
+                product,
+                vec![1, 0, 0, 0, 0, 1, 0, 0, 0, 0, 1, 0, 0, 0, 0, 1]
+            );
+        }
+    }
 }
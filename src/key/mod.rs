@@ -10,7 +10,10 @@ use crate::{error::Result, lang::Language};
 
 mod alphabet;
 mod cards;
+mod digits;
 mod enigma;
+mod hagelin;
+mod homophones;
 mod keyword;
 mod matrix;
 mod number;
@@ -20,19 +23,24 @@ mod vigenere_square;
 
 pub use alphabet::Alphabet;
 pub use cards::Cards;
+pub use digits::Digits;
 pub use enigma::plugboard::Plugboard;
 pub use enigma::reflector::*;
 pub use enigma::rotor::*;
+pub use hagelin::lug_cage::LugCage;
+pub use hagelin::pin_wheels::PinWheels;
+pub use homophones::Homophones;
 pub use keyword::Keyword;
-pub use matrix::{Matrix, MatrixDimSize};
+pub use matrix::Matrix;
 pub use number::Number;
+pub use polybius_square::PolybiusSquare;
+pub use straddle_checkerboard::StraddleCheckerboard;
 pub use vigenere_square::bellaso_square::BellasoSquare;
 pub use vigenere_square::classic_vig_square::ClassicVigSquare;
 pub use vigenere_square::keyed_vig_square::KeyedVigSquare;
 pub use vigenere_square::porta_square::PortaSquare;
+pub use vigenere_square::quagmire_square::QuagmireSquare;
 pub use vigenere_square::VigSquare;
-// pub use polybius_square::PolybiusSquare;
-// pub use straddle_checkerboard::StraddleCheckerboard;
 
 #[derive(Default, Clone)]
 pub struct KeyInfo {
@@ -1,50 +1,365 @@
 use std::collections::HashMap;
 
 use crate::{
-    error::Result,
+    error::{Error, Result},
     key::{IdentityKey, IoKey, Key, KeyInfo, StatefulKey},
     lang::Language,
     util,
 };
 
-/// Represents a Polybius Square
+/// The digits used to fill any cells of the square not taken by letters
+///
+const DIGITS: &str = "0123456789";
+
+/// Represents a Polybius Square (See Playfair and ADFGVX ciphers). The square
+/// holds every letter of the alphabet, followed by digits if the alphabet
+/// length is not a square number (a 26 letter alphabet gives a 6x6 square,
+/// including the digits `0-9`). Code points from the alphabet length upwards
+/// represent digits. Each row and column has a label, which defaults to the digits
+/// `1..=dim_size`.
+///
+/// As a string, the square is filled with the letters of a keyword followed by the
+/// rest of the alphabet. The labels of the rows and columns can be given after the
+/// keyword, separated by colons, such as `"keyword:ADFGX:ADFGX"`.
 ///
 pub struct PolybiusSquare {
     value: Vec<i16>,
     inverse: Vec<i16>,
-    row_keys: Vec<i16>,
-    col_keys: Vec<i16>,
-    row_lookup: HashMap<i16, usize>,
-    col_lookup: HashMap<i16, usize>,
+    row_labels: Vec<char>,
+    col_labels: Vec<char>,
+    row_lookup: HashMap<char, usize>,
+    col_lookup: HashMap<char, usize>,
     dim_size: usize,
+    num_letters: usize,
+    info: KeyInfo,
 }
 
 impl PolybiusSquare {
-    /// Encrypts `cp` and returns a pair of row/col coordinates
+    /// Finds the dimension size of the smallest square holding `len` letters
+    ///
+    /// # Arguments
+    ///
+    /// * `len` The number of letters in the square
+    ///
+    fn find_dim_size(len: usize) -> usize {
+        (1..=len).find(|x| x * x >= len).unwrap_or(0)
+    }
+
+    /// Sets the row/col labels to the digits `1..=dim_size`
+    ///
+    fn init_labels(&mut self) {
+        let labels = DIGITS[1..=self.dim_size].to_string();
+        self.set_labels(&labels, &labels).unwrap();
+    }
+
+    /// Sets the labels of the rows and columns of the square
+    ///
+    /// # Arguments
+    ///
+    /// * `row_labels` A distinct char for each row
+    /// * `col_labels` A distinct char for each column
+    ///
+    pub fn set_labels(&mut self, row_labels: &str, col_labels: &str) -> Result<()> {
+        let row_labels = row_labels.chars().collect::<Vec<_>>();
+        let col_labels = col_labels.chars().collect::<Vec<_>>();
+
+        let to_lookup = |labels: &[char]| {
+            labels
+                .iter()
+                .enumerate()
+                .map(|(idx, &label)| (label, idx))
+                .collect::<HashMap<_, _>>()
+        };
+        let row_lookup = to_lookup(&row_labels);
+        let col_lookup = to_lookup(&col_labels);
+
+        if row_labels.len() != self.dim_size
+            || col_labels.len() != self.dim_size
+            || row_lookup.len() != self.dim_size
+            || col_lookup.len() != self.dim_size
+        {
+            return Err(Error::InvalidKeyFmt {
+                expected: format!("{} distinct labels for the rows and columns", self.dim_size),
+                actual: format!(
+                    "{}:{}",
+                    row_labels.iter().collect::<String>(),
+                    col_labels.iter().collect::<String>()
+                ),
+            });
+        }
+
+        self.row_labels = row_labels;
+        self.col_labels = col_labels;
+        self.row_lookup = row_lookup;
+        self.col_lookup = col_lookup;
+
+        Ok(())
+    }
+
+    /// Gets the label of each row
+    ///
+    pub fn row_labels(&self) -> &[char] {
+        &self.row_labels
+    }
+
+    /// Gets the label of each column
+    ///
+    pub fn col_labels(&self) -> &[char] {
+        &self.col_labels
+    }
+
+    /// Changes the number of rows (and columns) of the square, selecting the
+    /// alphabet of the language which fits the square: a 5x5 square holds a 25
+    /// letter alphabet, and a 6x6 square holds a 26 letter alphabet with the
+    /// digits `0-9`. The square and its labels are reset.
+    ///
+    /// # Arguments
+    ///
+    /// * `language` A [`Language`] instance
+    /// * `dim_size` The number of rows (and columns)
+    ///
+    pub fn resize(&mut self, language: &mut Language, dim_size: usize) -> Result<()> {
+        let max_len = dim_size * dim_size;
+        let min_len = max_len.saturating_sub(DIGITS.len()).max(1);
+
+        let num_letters = (min_len..=max_len)
+            .rev()
+            .find(|&len| {
+                PolybiusSquare::find_dim_size(len) == dim_size && language.set_alph_len(len)
+            })
+            .ok_or_else(|| Error::InvalidKeyFmt {
+                expected: "The size of a square fitting an alphabet of the language".to_string(),
+                actual: format!("{}x{}", dim_size, dim_size),
+            })?;
+
+        debug_assert_eq!(language.alphabet_len(), num_letters);
+
+        let info = std::mem::take(&mut self.info);
+        *self = PolybiusSquare::identity(language);
+        self.info = info;
+
+        Ok(())
+    }
+
+    /// Fills the square from a slice of code points, and recalculates the inverse
+    ///
+    /// # Arguments
+    ///
+    /// * `arg` The (partial) alphabet to fill the square with
+    ///
+    fn fill(&mut self, arg: &[i16]) {
+        self.value = util::fill_alphabet_from_start(arg, self.value.len());
+        self.inverse = util::invert(&self.value);
+    }
+
+    /// Encrypts `cp` and returns the labels of its row and column
     ///
     /// # Arguments
     ///
     /// * `cp` The letter to encrypt
     ///
-    pub fn encrypt(&self, cp: i16) -> (i16, i16) {
-        let pos = self.inverse[cp as usize] as usize;
-        let row = self.value[(pos / self.dim_size) as usize] as usize;
-        let col = self.value[(pos % self.dim_size) as usize] as usize;
-        (self.row_keys[row], self.col_keys[col])
+    pub fn encrypt(&self, cp: i16) -> (char, char) {
+        let (row, col) = self.pos(cp);
+        (self.row_labels[row], self.col_labels[col])
     }
 
-    /// Decrypts a pair of (`row`, `col`). If the row/col are
+    /// Decrypts a pair of (`row`, `col`) labels. If the row/col are
     /// not found returns `None`.
     ///
     /// # Arguments
     ///
-    /// * `row` The row coordinate of the letter
-    /// * `col` The col coordinate of the letter
+    /// * `row` The row label of the letter
+    /// * `col` The col label of the letter
     ///
-    pub fn decrypt(&self, row: i16, col: i16) -> Option<i16> {
+    pub fn decrypt(&self, row: char, col: char) -> Option<i16> {
         let row_idx = self.row_lookup.get(&row)?;
         let col_idx = self.col_lookup.get(&col)?;
-        let pos = *row_idx * self.dim_size + *col_idx;
-        Some(self.value[pos])
+        Some(self.at(*row_idx, *col_idx))
+    }
+
+    /// Finds the (row, col) position of `cp` within the square
+    ///
+    /// # Arguments
+    ///
+    /// * `cp` The letter to find
+    ///
+    #[inline(always)]
+    pub fn pos(&self, cp: i16) -> (usize, usize) {
+        let pos = self.inverse[cp as usize] as usize;
+        (pos / self.dim_size, pos % self.dim_size)
+    }
+
+    /// Gets the letter at (row, col) in the square
+    ///
+    /// # Arguments
+    ///
+    /// * `row` The row
+    /// * `col` The column
+    ///
+    #[inline(always)]
+    pub fn at(&self, row: usize, col: usize) -> i16 {
+        self.value[row * self.dim_size + col]
+    }
+
+    /// Returns the number of rows (and columns) in the square
+    ///
+    pub fn dim_size(&self) -> usize {
+        self.dim_size
+    }
+
+    /// Returns the number of letters in the square. The remaining cells hold digits.
+    ///
+    pub fn num_letters(&self) -> usize {
+        self.num_letters
+    }
+
+    /// Converts a letter or digit to its code point within the square, or `None` if
+    /// the char is not in the square. The language should be using the alphabet
+    /// of the square.
+    ///
+    /// # Arguments
+    ///
+    /// * `language` A [`Language`] instance
+    /// * `ch` The char to convert
+    ///
+    pub fn char_to_cp(&self, language: &Language, ch: char) -> Option<i16> {
+        if language.is_letter(&ch) {
+            Some(language.get_cp(&ch))
+        } else {
+            DIGITS
+                .chars()
+                .take(self.value.len() - self.num_letters)
+                .position(|x| x == ch)
+                .map(|pos| (self.num_letters + pos) as i16)
+        }
+    }
+
+    /// Converts a code point within the square to an uppercase letter or digit. The
+    /// language should be using the alphabet of the square.
+    ///
+    /// # Arguments
+    ///
+    /// * `language` A [`Language`] instance
+    /// * `cp` The code point to convert
+    ///
+    pub fn cp_to_char(&self, language: &Language, cp: i16) -> char {
+        if (cp as usize) < self.num_letters {
+            language.cp_to_upper(cp)
+        } else {
+            DIGITS.as_bytes()[cp as usize - self.num_letters] as char
+        }
+    }
+
+    /// Gets the value of the square (row by row) as a slice
+    ///
+    pub fn as_slice(&self) -> &[i16] {
+        &self.value
+    }
+}
+
+impl Key<&[i16]> for PolybiusSquare {
+    fn new(language: &mut Language, arg: &[i16]) -> Result<Box<Self>> {
+        let mut result = PolybiusSquare::identity(language);
+        result.set(language, arg)?;
+        Ok(Box::new(result))
+    }
+    fn set(&mut self, _language: &mut Language, arg: &[i16]) -> Result<()> {
+        if arg
+            .iter()
+            .any(|&cp| cp < 0 || cp as usize >= self.value.len())
+        {
+            Err(Error::InvalidKeyFmt {
+                expected: format!("Code points in the range 0..{}", self.value.len()),
+                actual: format!("{:?}", arg),
+            })
+        } else {
+            self.fill(arg);
+            Ok(())
+        }
+    }
+}
+impl Key<&str> for PolybiusSquare {
+    fn new(language: &mut Language, arg: &str) -> Result<Box<Self>> {
+        let mut result = PolybiusSquare::identity(language);
+        result.set(language, arg)?;
+        Ok(Box::new(result))
+    }
+    fn set(&mut self, language: &mut Language, arg: &str) -> Result<()> {
+        language.set_alph_len(self.num_letters);
+
+        let arr = arg
+            .chars()
+            .filter_map(|ch| self.char_to_cp(language, ch))
+            .collect::<Vec<_>>();
+        self.set(language, arr.as_slice())
+    }
+}
+
+impl IdentityKey for PolybiusSquare {
+    fn identity(language: &mut Language) -> Self {
+        let num_letters = language.alphabet_len();
+        let dim_size = PolybiusSquare::find_dim_size(num_letters);
+        let len = dim_size * dim_size;
+
+        debug_assert!(len - num_letters <= DIGITS.len());
+
+        let mut result = PolybiusSquare {
+            value: (0..len as i16).collect(),
+            inverse: (0..len as i16).collect(),
+            row_labels: Vec::new(),
+            col_labels: Vec::new(),
+            row_lookup: HashMap::new(),
+            col_lookup: HashMap::new(),
+            dim_size,
+            num_letters,
+            info: KeyInfo::default(),
+        };
+
+        result.init_labels();
+
+        result
+    }
+}
+
+impl StatefulKey for PolybiusSquare {
+    fn reset(&mut self, _language: &mut Language) {
+        self.fill(&[]);
+    }
+    fn to_string(&self, language: &mut Language) -> String {
+        language.set_alph_len(self.num_letters);
+        self.value
+            .iter()
+            .map(|&cp| self.cp_to_char(language, cp))
+            .collect()
+    }
+    fn randomize(&mut self, _language: &mut Language) {
+        util::shuffle(&mut self.value);
+        self.inverse = util::invert(&self.value);
+    }
+}
+
+impl IoKey for PolybiusSquare {
+    fn set_key_str(&mut self, language: &mut Language, arg: &str) -> Result<()> {
+        let parts = arg.split(':').collect::<Vec<_>>();
+        match parts.as_slice() {
+            [keyword] => self.set(language, *keyword),
+            [keyword, row_labels, col_labels] => {
+                self.set_labels(row_labels, col_labels)?;
+                self.set(language, *keyword)
+            }
+            _ => Err(Error::InvalidKeyFmt {
+                expected: self.desc(),
+                actual: arg.to_string(),
+            }),
+        }
+    }
+    fn info(&self) -> &KeyInfo {
+        &self.info
+    }
+    fn info_mut(&mut self) -> &mut KeyInfo {
+        &mut self.info
+    }
+    fn desc(&self) -> String {
+        "<string: keyword[:row labels:col labels]>".to_string()
     }
 }
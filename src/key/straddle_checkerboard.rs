@@ -1 +1,439 @@
+use crate::{
+    error::{Error, Result},
+    key::{IdentityKey, IoKey, Key, KeyInfo, StatefulKey},
+    lang::Language,
+    util,
+};
 
+/// Each row of the checkerboard has a column for every digit
+const NUM_COLS: usize = 10;
+/// The number of cells left blank in the top row, which is also the number of
+/// rows beneath it
+const NUM_BLANKS: usize = 2;
+/// The number of cells which hold a symbol
+const NUM_CELLS: usize = NUM_COLS * (NUM_BLANKS + 1) - NUM_BLANKS;
+/// The symbols stored after the letters of the alphabet: a full stop, and the
+/// figure shift which switches between letters and digits
+const EXTRA_SYMBOLS: [char; 2] = ['.', '/'];
+/// Marks a blank cell in the grid
+const BLANK: i16 = -1;
+
+/// Represents a Straddling Checkerboard (See VIC cipher). The checkerboard holds the
+/// 26 letter alphabet, a full stop and a figure shift. The top row has two blank cells,
+/// and each of the other symbols in the top row is encoded as the single digit labelling
+/// its column. The symbols in the rows beneath are encoded as the digit labelling a
+/// blank cell (which labels the row), followed by the digit labelling the column.
+///
+/// As a string, the checkerboard is given by its top row, such as `"AT ONE SIR"`, where
+/// the two spaces mark the blank cells. Any symbols after the top row fill the rows
+/// beneath, followed by the remaining symbols in order. Without exactly two spaces in
+/// the first 10 chars, the string is a keyword which fills the checkerboard from the
+/// start, keeping the current blanks. The digits labelling the columns can be given
+/// after a colon, such as `"AT ONE SIR:6381049725"`.
+///
+#[derive(Clone)]
+pub struct StraddleCheckerboard {
+    /// The symbols in the order they fill the cells, row by row, skipping blanks
+    value: Vec<i16>,
+    /// The symbols in each cell of the grid, row by row, including blanks
+    grid: Vec<i16>,
+    /// The position of each symbol in the grid
+    inverse: Vec<usize>,
+    /// The columns of the top row which are left blank
+    blanks: [usize; NUM_BLANKS],
+    /// The digit labelling each column
+    col_digits: [i16; NUM_COLS],
+    /// The column labelled by each digit
+    digit_cols: [usize; NUM_COLS],
+    num_letters: usize,
+    info: KeyInfo,
+}
+
+impl StraddleCheckerboard {
+    /// Recalculates the grid and lookup tables after a change to the checkerboard
+    ///
+    fn update(&mut self) {
+        let mut symbols = self.value.iter();
+        self.grid = (0..NUM_COLS * (NUM_BLANKS + 1))
+            .map(|pos| {
+                if pos < NUM_COLS && self.blanks.contains(&pos) {
+                    BLANK
+                } else {
+                    *symbols.next().unwrap()
+                }
+            })
+            .collect();
+
+        self.inverse = vec![0; NUM_CELLS];
+        for (pos, &symbol) in self.grid.iter().enumerate() {
+            if symbol != BLANK {
+                self.inverse[symbol as usize] = pos;
+            }
+        }
+
+        for (col, &digit) in self.col_digits.iter().enumerate() {
+            self.digit_cols[digit as usize] = col;
+        }
+    }
+
+    /// Converts a letter, full stop or figure shift to its symbol in the
+    /// checkerboard, or `None` if the char is not in the checkerboard
+    ///
+    /// # Arguments
+    ///
+    /// * `language` A [`Language`] instance
+    /// * `ch` The char to convert
+    ///
+    pub fn char_to_symbol(&self, language: &Language, ch: char) -> Option<i16> {
+        if language.is_letter(&ch) {
+            Some(language.get_cp(&ch))
+        } else {
+            EXTRA_SYMBOLS
+                .iter()
+                .position(|&x| x == ch)
+                .map(|pos| (self.num_letters + pos) as i16)
+        }
+    }
+
+    /// Converts a symbol in the checkerboard to an uppercase letter, full stop
+    /// or figure shift
+    ///
+    /// # Arguments
+    ///
+    /// * `language` A [`Language`] instance
+    /// * `symbol` The symbol to convert
+    ///
+    pub fn symbol_to_char(&self, language: &Language, symbol: i16) -> char {
+        if (symbol as usize) < self.num_letters {
+            language.cp_to_upper(symbol)
+        } else {
+            EXTRA_SYMBOLS[symbol as usize - self.num_letters]
+        }
+    }
+
+    /// The symbol representing a full stop
+    ///
+    pub fn full_stop(&self) -> i16 {
+        self.num_letters as i16
+    }
+
+    /// The symbol which switches between letters and digits
+    ///
+    pub fn figure_shift(&self) -> i16 {
+        self.num_letters as i16 + 1
+    }
+
+    /// Gets the digit labelling each column
+    ///
+    pub fn col_digits(&self) -> &[i16] {
+        &self.col_digits
+    }
+
+    /// Sets the digit labelling each column
+    ///
+    /// # Arguments
+    ///
+    /// * `col_digits` The digits `0-9` in any order
+    ///
+    pub fn set_col_digits(&mut self, col_digits: &[i16]) -> Result<()> {
+        let mut sorted = col_digits.to_vec();
+        sorted.sort_unstable();
+
+        if sorted != (0..NUM_COLS as i16).collect::<Vec<_>>() {
+            return Err(Error::InvalidKeyFmt {
+                expected: "Each of the digits 0-9 once".to_string(),
+                actual: format!("{:?}", col_digits),
+            });
+        }
+
+        self.col_digits.copy_from_slice(col_digits);
+        self.update();
+
+        Ok(())
+    }
+
+    /// Gets the columns of the top row which are left blank
+    ///
+    pub fn blanks(&self) -> [usize; NUM_BLANKS] {
+        self.blanks
+    }
+
+    /// Sets the columns of the top row which are left blank. The symbols fill the
+    /// checkerboard in the same order as before.
+    ///
+    /// # Arguments
+    ///
+    /// * `blanks` Two different columns in the range `0..10`
+    ///
+    pub fn set_blanks(&mut self, blanks: [usize; NUM_BLANKS]) -> Result<()> {
+        if blanks[0] == blanks[1] || blanks.iter().any(|&col| col >= NUM_COLS) {
+            return Err(Error::InvalidKeyFmt {
+                expected: "Two different columns in the range 0..10".to_string(),
+                actual: format!("{:?}", blanks),
+            });
+        }
+
+        self.blanks = blanks;
+        self.blanks.sort_unstable();
+        self.update();
+
+        Ok(())
+    }
+
+    /// Appends the digits encoding `symbol` to `dest`
+    ///
+    /// # Arguments
+    ///
+    /// * `symbol` The symbol to encode
+    /// * `dest` Where to store the digits
+    ///
+    pub fn encode(&self, symbol: i16, dest: &mut Vec<i16>) {
+        let pos = self.inverse[symbol as usize];
+        let (row, col) = (pos / NUM_COLS, pos % NUM_COLS);
+
+        if row > 0 {
+            dest.push(self.col_digits[self.blanks[row - 1]]);
+        }
+        dest.push(self.col_digits[col]);
+    }
+
+    /// Decodes the symbol at the start of `digits`, returning the symbol and the
+    /// number of digits used, or `None` if `digits` is empty or ends part way
+    /// through a symbol
+    ///
+    /// # Arguments
+    ///
+    /// * `digits` The digits to decode
+    ///
+    pub fn decode(&self, digits: &[i16]) -> Option<(i16, usize)> {
+        let col = self.digit_cols[*digits.first()? as usize];
+
+        match self.blanks.iter().position(|&x| x == col) {
+            Some(row) => {
+                let col = self.digit_cols[*digits.get(1)? as usize];
+                Some((self.grid[(row + 1) * NUM_COLS + col], 2))
+            }
+            None => Some((self.grid[col], 1)),
+        }
+    }
+
+    /// Encodes a message using the checkerboard. Digits are written between two figure
+    /// shifts, with each digit repeated `figure_repeats` times, and any chars which are
+    /// not in the checkerboard are ignored.
+    ///
+    /// # Arguments
+    ///
+    /// * `language` A [`Language`] instance
+    /// * `msg` The message to encode
+    /// * `figure_repeats` The number of times each digit of a number is written
+    ///
+    pub fn encode_msg(&self, language: &Language, msg: &str, figure_repeats: usize) -> Vec<i16> {
+        let mut result = Vec::new();
+        let mut figures = false;
+
+        for ch in msg.chars() {
+            if let Some(digit) = ch.to_digit(10) {
+                if !figures {
+                    self.encode(self.figure_shift(), &mut result);
+                    figures = true;
+                }
+                result.extend(std::iter::repeat_n(digit as i16, figure_repeats));
+            } else if let Some(symbol) = self
+                .char_to_symbol(language, ch)
+                .filter(|&symbol| symbol != self.figure_shift())
+            {
+                if figures {
+                    self.encode(self.figure_shift(), &mut result);
+                    figures = false;
+                }
+                self.encode(symbol, &mut result);
+            }
+        }
+
+        if figures {
+            self.encode(self.figure_shift(), &mut result);
+        }
+
+        result
+    }
+
+    /// Decodes a message with the checkerboard, where each digit of a number is
+    /// repeated `figure_repeats` times between two figure shifts. Decoding stops at a
+    /// symbol cut short by the end of the digits.
+    ///
+    /// The figure shift and the symbol after it can look like a repeated digit, so a
+    /// repeated digit is only read as part of a number when it is followed by another
+    /// repeated digit or a figure shift.
+    ///
+    /// # Arguments
+    ///
+    /// * `language` A [`Language`] instance
+    /// * `digits` The digits to decode
+    /// * `figure_repeats` The number of times each digit of a number is written, which
+    ///   should be at least 2 so that the closing figure shift can be found
+    ///
+    pub fn decode_msg(&self, language: &Language, digits: &[i16], figure_repeats: usize) -> String {
+        let mut result = String::new();
+        let mut figures = false;
+        let mut i = 0;
+
+        let mut shift = Vec::new();
+        self.encode(self.figure_shift(), &mut shift);
+        let is_repeated = |digits: &[i16]| {
+            digits.len() >= figure_repeats
+                && digits[1..figure_repeats].iter().all(|&x| x == digits[0])
+        };
+
+        while i < digits.len() {
+            let rest = &digits[i..];
+
+            if figures
+                && is_repeated(rest)
+                && (is_repeated(&rest[figure_repeats..])
+                    || rest[figure_repeats..].starts_with(&shift))
+            {
+                result.push((b'0' + rest[0] as u8) as char);
+                i += figure_repeats;
+                continue;
+            }
+
+            match self.decode(rest) {
+                Some((symbol, used)) => {
+                    if symbol == self.figure_shift() {
+                        figures = !figures;
+                    } else {
+                        result.push(self.symbol_to_char(language, symbol));
+                    }
+                    i += used;
+                }
+                None => break,
+            }
+        }
+
+        result
+    }
+}
+
+impl Key<&[i16]> for StraddleCheckerboard {
+    fn new(language: &mut Language, arg: &[i16]) -> Result<Box<Self>> {
+        let mut result = Box::new(StraddleCheckerboard::identity(language));
+        result.set(language, arg)?;
+        Ok(result)
+    }
+    fn set(&mut self, _language: &mut Language, arg: &[i16]) -> Result<()> {
+        if arg
+            .iter()
+            .any(|&symbol| symbol < 0 || symbol as usize >= NUM_CELLS)
+        {
+            return Err(Error::InvalidKeyFmt {
+                expected: format!("Symbols in the range 0..{}", NUM_CELLS),
+                actual: format!("{:?}", arg),
+            });
+        }
+
+        self.value = util::fill_alphabet_from_start(arg, NUM_CELLS);
+        self.update();
+
+        Ok(())
+    }
+}
+impl Key<&str> for StraddleCheckerboard {
+    fn new(language: &mut Language, arg: &str) -> Result<Box<Self>> {
+        let mut result = Box::new(StraddleCheckerboard::identity(language));
+        result.set(language, arg)?;
+        Ok(result)
+    }
+    fn set(&mut self, language: &mut Language, arg: &str) -> Result<()> {
+        language.set_alph_len(self.num_letters);
+
+        let chars = arg.chars().collect::<Vec<_>>();
+        let top_row = chars.get(..NUM_COLS).unwrap_or(&chars);
+        let blanks = top_row
+            .iter()
+            .enumerate()
+            .filter(|(_, &ch)| ch == ' ')
+            .map(|(col, _)| col)
+            .collect::<Vec<_>>();
+
+        let symbols = chars
+            .iter()
+            .filter_map(|&ch| self.char_to_symbol(language, ch))
+            .collect::<Vec<_>>();
+
+        // a keyword fills the checkerboard without moving the blanks
+        if blanks.len() == NUM_BLANKS {
+            self.blanks = [blanks[0], blanks[1]];
+        }
+        self.set(language, symbols.as_slice())
+    }
+}
+
+impl IdentityKey for StraddleCheckerboard {
+    fn identity(language: &mut Language) -> Self {
+        let num_letters = language.alphabet_len();
+
+        debug_assert_eq!(num_letters + EXTRA_SYMBOLS.len(), NUM_CELLS);
+
+        let mut result = StraddleCheckerboard {
+            value: (0..NUM_CELLS as i16).collect(),
+            grid: Vec::new(),
+            inverse: Vec::new(),
+            blanks: [NUM_COLS - 2, NUM_COLS - 1],
+            col_digits: [0, 1, 2, 3, 4, 5, 6, 7, 8, 9],
+            digit_cols: [0; NUM_COLS],
+            num_letters,
+            info: KeyInfo::default(),
+        };
+
+        result.update();
+
+        result
+    }
+}
+
+impl StatefulKey for StraddleCheckerboard {
+    fn reset(&mut self, _language: &mut Language) {
+        self.value = (0..NUM_CELLS as i16).collect();
+        self.update();
+    }
+    fn to_string(&self, language: &mut Language) -> String {
+        language.set_alph_len(self.num_letters);
+        self.grid
+            .iter()
+            .map(|&symbol| match symbol {
+                BLANK => ' ',
+                _ => self.symbol_to_char(language, symbol),
+            })
+            .collect()
+    }
+    fn randomize(&mut self, _language: &mut Language) {
+        util::shuffle(&mut self.value);
+        self.update();
+    }
+}
+
+impl IoKey for StraddleCheckerboard {
+    fn set_key_str(&mut self, language: &mut Language, arg: &str) -> Result<()> {
+        match arg.split_once(':') {
+            Some((checkerboard, col_digits)) => {
+                let col_digits = col_digits
+                    .chars()
+                    .filter_map(|ch| ch.to_digit(10))
+                    .map(|digit| digit as i16)
+                    .collect::<Vec<_>>();
+                self.set_col_digits(&col_digits)?;
+                self.set(language, checkerboard)
+            }
+            None => self.set(language, arg),
+        }
+    }
+    fn info(&self) -> &KeyInfo {
+        &self.info
+    }
+    fn info_mut(&mut self) -> &mut KeyInfo {
+        &mut self.info
+    }
+    fn desc(&self) -> String {
+        "<string: top row with 2 blanks or keyword[:column digits]>".to_string()
+    }
+}
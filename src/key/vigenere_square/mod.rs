@@ -2,6 +2,7 @@ pub mod bellaso_square;
 pub mod classic_vig_square;
 pub mod keyed_vig_square;
 pub mod porta_square;
+pub mod quagmire_square;
 
 use crate::lang::Language;
 
@@ -79,8 +80,8 @@ pub trait VigSquare {
                 square_as_string.push(language.cp_to_upper(y));
             }
             square_as_string.push_str(" | ");
-            for x in 0..max_x {
-                square_as_string.push(language.cp_to_upper(square[y as usize][x]));
+            for &cp in square[y as usize].iter().take(max_x) {
+                square_as_string.push(language.cp_to_upper(cp));
                 square_as_string.push(' ');
             }
             square_as_string.push('\n')
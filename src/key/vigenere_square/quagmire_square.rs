@@ -0,0 +1,88 @@
+use super::VigSquare;
+use crate::{
+    key::{IdentityKey, StatefulKey},
+    lang::Language,
+    util,
+};
+
+/// Represents a Quagmire tableau, with separate plaintext and ciphertext alphabets.
+/// (See Quagmire ciphers)
+///
+/// The row for each indicator letter places the ciphertext alphabet beneath the
+/// plaintext alphabet, slid so that the indicator letter is beneath plaintext `A`.
+///
+pub struct QuagmireSquare {
+    square: Vec<Vec<i16>>,
+    inverse: Vec<Vec<i16>>,
+}
+
+impl QuagmireSquare {
+    /// Sets the plaintext and ciphertext alphabets of the tableau
+    ///
+    /// # Arguments
+    ///
+    /// * `plaintext` The plaintext alphabet, giving the letter at each position
+    /// * `ciphertext` The ciphertext alphabet, giving the letter at each position
+    ///
+    pub fn set_alphabets(&mut self, plaintext: &[i16], ciphertext: &[i16]) {
+        let pt_positions = util::invert(plaintext);
+        let ct_positions = util::invert(ciphertext);
+
+        for (row, ct_position) in ct_positions.iter().enumerate() {
+            // the offset of the ciphertext alphabet for this indicator letter
+            let offset = ct_position - pt_positions[0];
+
+            for (col, pt_position) in pt_positions.iter().enumerate() {
+                let letter = ciphertext[util::modulo(pt_position + offset, 26) as usize];
+                self.square[row][col] = letter;
+                self.inverse[row][letter as usize] = col as i16;
+            }
+        }
+    }
+}
+
+impl VigSquare for QuagmireSquare {
+    fn init_squares(&mut self) {
+        let alphabet = (0..26).collect::<Vec<_>>();
+        self.set_alphabets(&alphabet, &alphabet);
+    }
+
+    #[inline(always)]
+    fn encrypt(&self, x: i16, y: i16) -> i16 {
+        self.square[y as usize][x as usize]
+    }
+    #[inline(always)]
+    fn decrypt(&self, x: i16, y: i16) -> i16 {
+        self.inverse[x as usize][y as usize]
+    }
+}
+
+impl IdentityKey for QuagmireSquare {
+    fn identity(_language: &mut Language) -> Self {
+        let my_square = vec![vec![0; 26]; 26];
+        let mut vig_square = QuagmireSquare {
+            square: my_square.clone(),
+            inverse: my_square,
+        };
+        vig_square.init_squares();
+        vig_square
+    }
+}
+
+impl StatefulKey for QuagmireSquare {
+    fn reset(&mut self, _language: &mut Language) {
+        self.init_squares();
+    }
+    fn to_string(&self, language: &mut Language) -> String {
+        Self::vig_square_to_string(language, &self.square, 26, 26)
+    }
+    fn randomize(&mut self, _language: &mut Language) {
+        let mut plaintext = vec![0; 26];
+        let mut ciphertext = vec![0; 26];
+        util::fill_consecutive_vec(&mut plaintext, 0, 26);
+        util::fill_consecutive_vec(&mut ciphertext, 0, 26);
+        util::shuffle(&mut plaintext);
+        util::shuffle(&mut ciphertext);
+        self.set_alphabets(&plaintext, &ciphertext);
+    }
+}
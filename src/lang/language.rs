@@ -258,7 +258,7 @@ impl Language {
             })
         } else {
             // read bytes then deserialize
-            match File::open(&path) {
+            match File::open(path) {
                 Ok(mut file) => {
                     let mut bytes = Vec::new();
 
@@ -547,7 +547,7 @@ impl Language {
     /// * `letter` The char to check
     ///
     pub fn is_letter(&self, letter: &char) -> bool {
-        self.alph().char_to_cp.contains_key(&letter)
+        self.alph().char_to_cp.contains_key(letter)
     }
 
     /// Is a particular letter punctuation (not a letter)?
@@ -17,7 +17,10 @@ pub use cipher::{Asymmetric, Keyed, Solve, Symmetric};
 /* -------------------------------------------------------------------------- */
 /*                              Electromechanical                             */
 /* -------------------------------------------------------------------------- */
-pub use cipher::electromechanical::enigma::Enigma;
+pub use cipher::electromechanical::bombe::{crib_positions, Bombe, Menu, Stop};
+pub use cipher::electromechanical::enigma::{Enigma, EnigmaModel};
+pub use cipher::electromechanical::m209::M209;
+pub use cipher::electromechanical::typex::Typex;
 
 /* -------------------------------------------------------------------------- */
 /*                               Monoalphabetic                               */
@@ -26,9 +29,13 @@ pub use cipher::monoalph::affine::Affine;
 pub use cipher::monoalph::atbash::Atbash;
 pub use cipher::monoalph::baconian::Baconian;
 pub use cipher::monoalph::caesar::Caesar;
+pub use cipher::monoalph::homophonic::Homophonic;
 pub use cipher::monoalph::morse::Morse;
+pub use cipher::monoalph::playfair::{DoubleLetters, Playfair};
+pub use cipher::monoalph::polybius_square::PolybiusSquareCipher;
 pub use cipher::monoalph::rot13::Rot13;
 pub use cipher::monoalph::simple_sub::SimpleSubstitution;
+pub use cipher::monoalph::straddle_checkerboard::StraddleCheckerboardCipher;
 
 /* -------------------------------------------------------------------------- */
 /*                               Polyalphabetic                               */
@@ -36,19 +43,37 @@ pub use cipher::monoalph::simple_sub::SimpleSubstitution;
 pub use cipher::polyalph::autokey::Autokey;
 pub use cipher::polyalph::beaufort::Beaufort;
 pub use cipher::polyalph::bellaso::Bellaso;
+pub use cipher::polyalph::chaocipher::Chaocipher;
 pub use cipher::polyalph::classic_vig::ClassicVigenere;
-pub use cipher::polyalph::keyed_vig::KeyedVigenere;
+pub use cipher::polyalph::keyed_vig::{KeyedVigenere, QuagmireIII};
 pub use cipher::polyalph::porta::Porta;
+pub use cipher::polyalph::quagmire1::QuagmireI;
+pub use cipher::polyalph::quagmire2::QuagmireII;
+pub use cipher::polyalph::quagmire4::QuagmireIV;
 
 /* -------------------------------------------------------------------------- */
 /*                                 Polygraphic                                */
 /* -------------------------------------------------------------------------- */
+pub use cipher::polygraph::adfgvx::Adfgvx;
+pub use cipher::polygraph::adfgx::Adfgx;
+pub use cipher::polygraph::bifid::Bifid;
+pub use cipher::polygraph::four_square::FourSquare;
+pub use cipher::polygraph::fracmorse::FractionatedMorse;
 pub use cipher::polygraph::hill::Hill;
+pub use cipher::polygraph::trifid::Trifid;
+pub use cipher::polygraph::two_square::{TwoSquare, TwoSquareOrientation};
+pub use cipher::polygraph::vic::Vic;
+
+/* -------------------------------------------------------------------------- */
+/*                                   Stream                                   */
+/* -------------------------------------------------------------------------- */
+pub use cipher::stream::solitaire::Solitaire;
 
 /* -------------------------------------------------------------------------- */
 /*                                Transposition                               */
 /* -------------------------------------------------------------------------- */
 pub use cipher::transpos::block_transpos::BlockTransposition;
 pub use cipher::transpos::column_transpos::ColumnTransposition;
+pub use cipher::transpos::myszkowski::Myszkowski;
 pub use cipher::transpos::railfence::Railfence;
 pub use cipher::transpos::scytale::Scytale;
@@ -202,11 +202,9 @@ pub fn fill_alphabet_from_start(key: &[i16], alphabet_size: usize) -> Vec<i16> {
 /// assert_eq!(my_vec, vec![0,1,2,3,4,0,1,2,3,4]);
 /// ```
 ///
-pub fn fill_consecutive_vec(arr: &mut Vec<i16>, start: i16, modulus: i16) {
-    let mut i = start;
-    for item in arr {
+pub fn fill_consecutive_vec(arr: &mut [i16], start: i16, modulus: i16) {
+    for (item, i) in arr.iter_mut().zip(start..) {
         *item = modulo(i, modulus);
-        i += 1;
     }
 }
 
@@ -241,7 +239,7 @@ pub fn fill_random_array(arr: &mut Vec<i16>, max_exclusive: i16) {
 ///
 /// # Arguments
 ///
-/// * `arr` A mutable borrow of the Vec<i16> to shuffle
+/// * `arr` A mutable borrow of the slice to shuffle
 ///
 /// # Examples
 ///
@@ -252,7 +250,7 @@ pub fn fill_random_array(arr: &mut Vec<i16>, max_exclusive: i16) {
 /// println!("{:?}", my_vec);
 /// ```
 ///
-pub fn shuffle<T>(arr: &mut Vec<T>) {
+pub fn shuffle<T>(arr: &mut [T]) {
     for i in 0..arr.len() {
         let j = fastrand::usize(0..arr.len());
         arr.swap(i, j);
@@ -365,7 +363,7 @@ pub fn mmi(a: i16, b: i16) -> Option<i16> {
 /// * `a` The first value
 /// * `b` The second value
 ///
-#[allow(clippy::clippy::many_single_char_names)]
+#[allow(clippy::many_single_char_names)]
 pub fn extended_gcd(a: i32, b: i32) -> (i32, i32, i32) {
     match a {
         0 => (b, 0, 1),
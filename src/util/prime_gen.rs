@@ -33,7 +33,7 @@ impl PrimeGen {
             self.reset();
             while num != 1 {
                 let prime = self.next().unwrap();
-                while num % prime == 0 {
+                while num.is_multiple_of(prime) {
                     num /= prime;
                     factors.push(prime);
                 }
@@ -66,7 +66,7 @@ impl Iterator for PrimeGen {
                     .primes
                     .iter()
                     .take_while(|&&prime| prime <= sqrt)
-                    .any(|&prime| num % prime == 0)
+                    .any(|&prime| num.is_multiple_of(prime))
                 {
                     break;
                 }